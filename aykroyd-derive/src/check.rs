@@ -0,0 +1,1205 @@
+//! Offline query checking against a schema snapshot.
+//!
+//! With `#[aykroyd(check)]`, the derive macros read a JSON snapshot of
+//! the database schema and check the query text against it:
+//!
+//! ```json
+//! {
+//!   "dialect": "postgres",
+//!   "tables": {
+//!     "customers": {
+//!       "id": { "type": "int4", "nullable": false },
+//!       "name": { "type": "text", "nullable": true }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! The SQL is not fully parsed, we just scan it for the shapes we
+//! understand: table references, qualified column references, insert
+//! column lists, `SET` assignments, comparisons against parameters,
+//! and simple select lists.  The snapshot describes the default schema,
+//! so tables in other schemas and the system catalogs aren't checked.
+//! Anything else is skipped too, so a checked query should never fail
+//! to compile for a reason other than a real mismatch with the snapshot.
+
+use std::collections::HashMap;
+
+/// The default snapshot path, relative to the crate manifest.
+const DEFAULT_PATH: &str = "aykroyd-schema.json";
+
+pub struct Schema {
+    strict: bool,
+    tables: HashMap<String, HashMap<String, ColumnDef>>,
+}
+
+pub struct ColumnDef {
+    ty: String,
+    nullable: bool,
+}
+
+impl Schema {
+    pub fn parse(json: &str) -> Result<Schema, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| format!("invalid schema snapshot: {e}"))?;
+
+        let strict = match value.get("dialect").and_then(|d| d.as_str()) {
+            None | Some("postgres") => true,
+            Some("mysql") | Some("sqlite") => false,
+            Some(other) => return Err(format!("unknown schema dialect {other}")),
+        };
+
+        let mut tables = HashMap::new();
+        let table_values = value
+            .get("tables")
+            .and_then(|t| t.as_object())
+            .ok_or("schema snapshot has no tables object")?;
+        for (table_name, columns_value) in table_values {
+            let mut columns = HashMap::new();
+            let columns_value = columns_value
+                .as_object()
+                .ok_or_else(|| format!("table {table_name} is not an object"))?;
+            for (column_name, column) in columns_value {
+                let ty = column
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .ok_or_else(|| format!("column {table_name}.{column_name} has no type"))?;
+                let nullable = column
+                    .get("nullable")
+                    .and_then(|n| n.as_bool())
+                    .unwrap_or(true);
+                columns.insert(
+                    column_name.to_lowercase(),
+                    ColumnDef {
+                        ty: normalize_type(ty),
+                        nullable,
+                    },
+                );
+            }
+            tables.insert(table_name.to_lowercase(), columns);
+        }
+
+        Ok(Schema { strict, tables })
+    }
+}
+
+fn normalize_type(ty: &str) -> String {
+    let ty = ty.to_lowercase();
+    let ty = match ty.find('(') {
+        Some(index) => &ty[..index],
+        None => &ty[..],
+    };
+    ty.trim().to_string()
+}
+
+/// Check the query, panicking with every problem found.
+///
+/// Returns tokens that make the snapshot a build input, so that
+/// changes to the schema trigger a recompile.
+pub fn check(
+    path: Option<&syn::LitStr>,
+    query_text: &str,
+    params: &[&syn::Type],
+    row: Option<&syn::Type>,
+) -> proc_macro2::TokenStream {
+    let relative = path.map(syn::LitStr::value);
+    let relative = relative.as_deref().unwrap_or(DEFAULT_PATH);
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
+    let path = std::path::Path::new(&manifest_dir).join(relative);
+
+    let json = match std::fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(e) => panic!("unable to read schema snapshot {}: {e}", path.display()),
+    };
+    let schema = match Schema::parse(&json) {
+        Ok(schema) => schema,
+        Err(e) => panic!("{e} in {}", path.display()),
+    };
+
+    let problems = check_query(&schema, query_text, params, row);
+    if !problems.is_empty() {
+        panic!(
+            "query does not match schema snapshot {}:\n  {}",
+            relative,
+            problems.join("\n  "),
+        );
+    }
+
+    let path = path.to_string_lossy().into_owned();
+    quote::quote! {
+        const _: &[u8] = include_bytes!(#path);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Quoted(String),
+    Param(usize),
+    Punct(String),
+    Literal,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Ident(ident) if ident == keyword)
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self, Token::Punct(p) if p == punct)
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Token::Ident(name) if !is_reserved(name) => Some(name),
+            Token::Quoted(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+const RESERVED: &[&str] = &[
+    "all",
+    "and",
+    "any",
+    "as",
+    "asc",
+    "between",
+    "by",
+    "case",
+    "cross",
+    "default",
+    "delete",
+    "desc",
+    "distinct",
+    "else",
+    "end",
+    "except",
+    "exists",
+    "false",
+    "fetch",
+    "for",
+    "from",
+    "full",
+    "group",
+    "having",
+    "ilike",
+    "in",
+    "inner",
+    "insert",
+    "intersect",
+    "into",
+    "is",
+    "join",
+    "lateral",
+    "left",
+    "like",
+    "limit",
+    "natural",
+    "not",
+    "null",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "outer",
+    "returning",
+    "right",
+    "select",
+    "set",
+    "some",
+    "then",
+    "true",
+    "union",
+    "update",
+    "using",
+    "values",
+    "when",
+    "where",
+    "window",
+    "with",
+];
+
+fn is_reserved(ident: &str) -> bool {
+    RESERVED.contains(&ident)
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut positional = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '\'' {
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    if chars.get(i + 1) == Some(&'\'') {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Literal);
+        } else if c == '"' || c == '`' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != c {
+                i += 1;
+            }
+            tokens.push(Token::Quoted(
+                chars[start..i.min(chars.len())].iter().collect(),
+            ));
+            i += 1;
+        } else if c == '$' && chars.get(i + 1).is_some_and(char::is_ascii_digit) {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let index: String = chars[start..i].iter().collect();
+            let index: usize = index.parse().unwrap_or(1);
+            tokens.push(Token::Param(index.saturating_sub(1)));
+        } else if c == '?' && !is_jsonb_operator(&chars[i + 1..]) {
+            positional.push(tokens.len());
+            tokens.push(Token::Punct("?".into()));
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+            let ident: String = chars[start..i].iter().collect();
+            tokens.push(Token::Ident(ident.to_lowercase()));
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Literal);
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            if ["<>", "!=", "<=", ">=", "::", "||", "?|", "?&"].contains(&two.as_str()) {
+                tokens.push(Token::Punct(two));
+                i += 2;
+            } else {
+                tokens.push(Token::Punct(c.to_string()));
+                i += 1;
+            }
+        }
+    }
+
+    // With numbered placeholders, a `?` must be an operator instead.
+    if !tokens.iter().any(|t| matches!(t, Token::Param(_))) {
+        for (index, position) in positional.into_iter().enumerate() {
+            tokens[position] = Token::Param(index);
+        }
+    }
+
+    tokens
+}
+
+/// Does a `?` followed by these characters look like a PostgreSQL
+/// jsonb operator, such as `?|`, `?&` or `? 'key'`?
+fn is_jsonb_operator(rest: &[char]) -> bool {
+    matches!(
+        rest.iter().find(|c| !c.is_whitespace()),
+        Some('|' | '&' | '\''),
+    )
+}
+
+/// A table in scope: `None` if we don't know its columns.
+type Scope = HashMap<String, Option<String>>;
+
+struct Analysis<'a> {
+    schema: &'a Schema,
+    tokens: Vec<Token>,
+    scope: Scope,
+    opaque: bool,
+    problems: Vec<String>,
+    params: HashMap<usize, (String, String)>,
+}
+
+fn check_query(
+    schema: &Schema,
+    query_text: &str,
+    params: &[&syn::Type],
+    row: Option<&syn::Type>,
+) -> Vec<String> {
+    let mut analysis = Analysis {
+        schema,
+        tokens: tokenize(query_text),
+        scope: HashMap::new(),
+        opaque: false,
+        problems: vec![],
+        params: HashMap::new(),
+    };
+
+    analysis.tables();
+    analysis.qualified_columns();
+    analysis.insert_columns();
+    analysis.assignments();
+    analysis.comparisons();
+
+    let used = analysis
+        .tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Param(index) => Some(index + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    if used != params.len() {
+        analysis.problems.push(format!(
+            "query text uses {used} parameters, but there are {} fields",
+            params.len(),
+        ));
+    }
+
+    let mut param_indexes: Vec<_> = analysis.params.keys().copied().collect();
+    param_indexes.sort();
+    for index in param_indexes {
+        let (table, column) = &analysis.params[&index];
+        let def = match schema.tables[table].get(column) {
+            Some(def) => def,
+            None => continue,
+        };
+        if let Some(ty) = params.get(index) {
+            if !schema.accepts(ty, &def.ty) {
+                analysis.problems.push(format!(
+                    "parameter ${} has Rust type {}, but column {table}.{column} is {}",
+                    index + 1,
+                    type_string(ty),
+                    def.ty,
+                ));
+            }
+        }
+    }
+
+    if let Some(syn::Type::Tuple(tuple)) = row {
+        analysis.results(&tuple.elems.iter().collect::<Vec<_>>());
+    }
+
+    analysis.problems
+}
+
+impl Schema {
+    fn accepts(&self, ty: &syn::Type, sql_type: &str) -> bool {
+        let (_, rust) = match rust_type(ty) {
+            Some(rust) => rust,
+            None => return true,
+        };
+        if self.strict {
+            rust.postgres_types().contains(&sql_type)
+        } else {
+            rust.affinities().contains(&affinity(sql_type))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RustType {
+    Bool,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    Text,
+    Bytes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Affinity {
+    Integer,
+    Real,
+    Numeric,
+    Text,
+    Blob,
+}
+
+impl RustType {
+    fn postgres_types(self) -> &'static [&'static str] {
+        match self {
+            RustType::Bool => &["bool", "boolean"],
+            RustType::I16 => &["int2", "smallint", "smallserial"],
+            RustType::I32 => &["int4", "int", "integer", "serial", "serial4"],
+            RustType::I64 => &["int8", "bigint", "bigserial", "serial8"],
+            RustType::F32 => &["float4", "real"],
+            RustType::F64 => &["float8", "double precision"],
+            RustType::Text => &[
+                "text",
+                "varchar",
+                "character varying",
+                "char",
+                "character",
+                "bpchar",
+                "name",
+                "citext",
+                "unknown",
+            ],
+            RustType::Bytes => &["bytea"],
+        }
+    }
+
+    fn affinities(self) -> &'static [Affinity] {
+        match self {
+            RustType::Bool => &[Affinity::Integer, Affinity::Numeric],
+            RustType::I16 | RustType::I32 | RustType::I64 => {
+                &[Affinity::Integer, Affinity::Numeric]
+            }
+            RustType::F32 | RustType::F64 => &[Affinity::Real, Affinity::Numeric],
+            RustType::Text => &[Affinity::Text],
+            RustType::Bytes => &[Affinity::Blob],
+        }
+    }
+}
+
+/// Type affinity, following the SQLite rules.
+fn affinity(sql_type: &str) -> Affinity {
+    if sql_type.contains("int") {
+        Affinity::Integer
+    } else if sql_type.contains("char") || sql_type.contains("clob") || sql_type.contains("text") {
+        Affinity::Text
+    } else if sql_type.contains("blob") || sql_type.contains("binary") || sql_type.is_empty() {
+        Affinity::Blob
+    } else if sql_type.contains("real") || sql_type.contains("floa") || sql_type.contains("doub") {
+        Affinity::Real
+    } else {
+        Affinity::Numeric
+    }
+}
+
+/// Recognize a few common Rust types: returns nullability and the type.
+fn rust_type(ty: &syn::Type) -> Option<(bool, RustType)> {
+    match ty {
+        syn::Type::Reference(reference) => rust_type(&reference.elem),
+        syn::Type::Paren(paren) => rust_type(&paren.elem),
+        syn::Type::Slice(slice) => match rust_type(&slice.elem) {
+            Some((false, RustType::Bytes)) => None,
+            _ if is_u8(&slice.elem) => Some((false, RustType::Bytes)),
+            _ => None,
+        },
+        syn::Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let argument = match &segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+                    Some(syn::GenericArgument::Type(ty)) => Some(ty),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let rust = match segment.ident.to_string().as_str() {
+                "Option" => return rust_type(argument?).map(|(_, rust)| (true, rust)),
+                "Vec" if argument.is_some_and(is_u8) => RustType::Bytes,
+                "Box" | "Cow" | "Rc" | "Arc" => return rust_type(argument?),
+                "bool" => RustType::Bool,
+                "i16" => RustType::I16,
+                "i32" => RustType::I32,
+                "i64" => RustType::I64,
+                "f32" => RustType::F32,
+                "f64" => RustType::F64,
+                "String" | "str" => RustType::Text,
+                _ => return None,
+            };
+            Some((false, rust))
+        }
+        _ => None,
+    }
+}
+
+fn is_u8(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(path) if path.path.is_ident("u8"))
+}
+
+fn type_string(ty: &syn::Type) -> String {
+    quote::quote!(#ty).to_string()
+}
+
+impl<'a> Analysis<'a> {
+    fn token(&self, index: usize) -> Option<&Token> {
+        self.tokens.get(index)
+    }
+
+    /// Find the tables in scope, and report any unknown tables.
+    fn tables(&mut self) {
+        let ctes = self.ctes();
+        let mut function_parens = vec![];
+
+        let mut i = 0;
+        while i < self.tokens.len() {
+            let token = self.tokens[i].clone();
+            if token.is_punct("(") {
+                let function = match i.checked_sub(1).and_then(|j| self.token(j)) {
+                    Some(Token::Ident(name)) => !is_reserved(name),
+                    Some(Token::Quoted(_)) => true,
+                    _ => false,
+                };
+                function_parens.push(function);
+            } else if token.is_punct(")") {
+                function_parens.pop();
+            } else if function_parens.last() == Some(&true) {
+                // FROM inside e.g. EXTRACT(MONTH FROM x) isn't a table.
+            } else if token.is_keyword("from") || token.is_keyword("join") {
+                i = self.table_list(i + 1, &ctes, true);
+                continue;
+            } else if token.is_keyword("into") || token.is_keyword("update") {
+                i = self.table_list(i + 1, &ctes, false);
+                continue;
+            }
+            i += 1;
+        }
+    }
+
+    /// Names defined by `WITH name AS (...)`.
+    fn ctes(&self) -> Vec<String> {
+        let mut ctes = vec![];
+        for i in 0..self.tokens.len() {
+            if let (Some(name), Some(Token::Ident(as_)), Some(paren)) =
+                (self.tokens[i].name(), self.token(i + 1), self.token(i + 2))
+            {
+                if as_ == "as" && paren.is_punct("(") {
+                    ctes.push(name.to_string());
+                }
+            }
+        }
+        ctes
+    }
+
+    /// Parse a list of table references, returning the next index.
+    fn table_list(&mut self, mut i: usize, ctes: &[String], list: bool) -> usize {
+        loop {
+            i = self.table_ref(i, ctes);
+            if list && self.token(i).is_some_and(|t| t.is_punct(",")) {
+                i += 1;
+            } else {
+                return i;
+            }
+        }
+    }
+
+    fn table_ref(&mut self, mut i: usize, ctes: &[String]) -> usize {
+        if self
+            .token(i)
+            .is_some_and(|t| t.is_keyword("lateral") || t.is_keyword("only"))
+        {
+            i += 1;
+        }
+
+        let mut name = match self.token(i).and_then(Token::name) {
+            Some(name) => name.to_string(),
+            None => {
+                // A subquery or something else we don't understand.
+                self.opaque = true;
+                return i;
+            }
+        };
+        i += 1;
+
+        let mut schema = None;
+        while self.token(i).is_some_and(|t| t.is_punct(".")) {
+            match self.token(i + 1).and_then(Token::name) {
+                Some(part) => {
+                    schema = Some(std::mem::replace(&mut name, part.to_string()));
+                    i += 2;
+                }
+                None => break,
+            }
+        }
+
+        let table = if !in_snapshot(schema.as_deref(), &name) {
+            // A system catalog, or a schema the snapshot doesn't cover.
+            self.opaque = true;
+            None
+        } else if self.token(i).is_some_and(|t| t.is_punct("(")) {
+            // A table function, or an insert column list.
+            if self.token(i - 2).is_some_and(|t| t.is_keyword("into")) {
+                self.known_table(&name, ctes)
+            } else {
+                self.opaque = true;
+                None
+            }
+        } else {
+            self.known_table(&name, ctes)
+        };
+
+        if self.token(i).is_some_and(|t| t.is_keyword("as")) {
+            i += 1;
+        }
+        let alias = match self.token(i).and_then(Token::name) {
+            Some(alias) => {
+                i += 1;
+                alias.to_string()
+            }
+            None => name.clone(),
+        };
+
+        self.scope.insert(alias, table.clone());
+        self.scope.insert(name, table);
+        i
+    }
+
+    fn known_table(&mut self, name: &str, ctes: &[String]) -> Option<String> {
+        if ctes.iter().any(|cte| cte == name) {
+            self.opaque = true;
+            None
+        } else if self.schema.tables.contains_key(name) {
+            Some(name.to_string())
+        } else {
+            self.problems.push(format!("unknown table {name}"));
+            self.opaque = true;
+            None
+        }
+    }
+
+    /// Resolve a column by name, optionally qualified by a table or alias.
+    ///
+    /// Returns the table, or `None` if we can't be sure.
+    fn resolve(&mut self, qualifier: Option<&str>, column: &str) -> Option<String> {
+        match qualifier {
+            Some(qualifier) => {
+                let table = self.scope.get(qualifier)?.clone()?;
+                if self.schema.tables[&table].contains_key(column) {
+                    Some(table)
+                } else {
+                    self.problems
+                        .push(format!("unknown column {table}.{column}"));
+                    None
+                }
+            }
+            None => {
+                let mut tables: Vec<_> = self.scope.values().flatten().cloned().collect();
+                tables.sort();
+                tables.dedup();
+                let mut found = tables
+                    .into_iter()
+                    .filter(|table| self.schema.tables[table].contains_key(column));
+                match (found.next(), found.next()) {
+                    (Some(table), None) => Some(table),
+                    (None, _) if !self.opaque && !self.scope.is_empty() => {
+                        self.problems.push(format!("unknown column {column}"));
+                        None
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// A column reference at the given index: qualifier, name and next index.
+    fn column_at(&self, i: usize) -> Option<(Option<String>, String, usize)> {
+        let first = self.token(i)?.name()?.to_string();
+        if self.token(i + 1).is_some_and(|t| t.is_punct(".")) {
+            let second = self.token(i + 2)?.name()?.to_string();
+            if self
+                .token(i + 3)
+                .is_some_and(|t| t.is_punct("(") || t.is_punct("."))
+            {
+                return None;
+            }
+            Some((Some(first), second, i + 3))
+        } else {
+            if self.token(i + 1).is_some_and(|t| t.is_punct("(")) {
+                return None;
+            }
+            Some((None, first, i + 1))
+        }
+    }
+
+    fn qualified_columns(&mut self) {
+        for i in 0..self.tokens.len() {
+            let qualified = match self.column_at(i) {
+                Some((Some(qualifier), column, _)) => Some((qualifier, column)),
+                _ => None,
+            };
+            if let Some((qualifier, column)) = qualified {
+                if i > 0 && self.tokens[i - 1].is_punct(".") {
+                    continue;
+                }
+                if self.scope.contains_key(&qualifier) {
+                    self.resolve(Some(&qualifier), &column);
+                }
+            }
+        }
+    }
+
+    /// Check `INSERT INTO t (a, b) VALUES ($1, $2)`.
+    fn insert_columns(&mut self) {
+        let into = match self.tokens.iter().position(|t| t.is_keyword("into")) {
+            Some(into) => into,
+            None => return,
+        };
+        let table = match self.token(into + 1).and_then(Token::name) {
+            Some(table) => table.to_string(),
+            None => return,
+        };
+        let table = match self.scope.get(&table) {
+            Some(Some(table)) => table.clone(),
+            _ => return,
+        };
+        let mut i = into + 2;
+        if !self.token(i).is_some_and(|t| t.is_punct("(")) {
+            return;
+        }
+
+        let mut columns = vec![];
+        loop {
+            i += 1;
+            match self.token(i).and_then(Token::name) {
+                Some(column) => columns.push(column.to_string()),
+                None => return,
+            }
+            i += 1;
+            match self.token(i) {
+                Some(t) if t.is_punct(",") => continue,
+                Some(t) if t.is_punct(")") => break,
+                _ => return,
+            }
+        }
+        for column in &columns {
+            if !self.schema.tables[&table].contains_key(column) {
+                self.problems
+                    .push(format!("unknown column {table}.{column}"));
+            }
+        }
+
+        i += 1;
+        if !self.token(i).is_some_and(|t| t.is_keyword("values")) {
+            return;
+        }
+        i += 1;
+        if !self.token(i).is_some_and(|t| t.is_punct("(")) {
+            return;
+        }
+        for column in &columns {
+            i += 1;
+            if let Some(Token::Param(index)) = self.token(i) {
+                let next = self.token(i + 1);
+                if next.is_some_and(|t| t.is_punct(",") || t.is_punct(")")) {
+                    self.params.insert(*index, (table.clone(), column.clone()));
+                }
+            }
+            while self
+                .token(i)
+                .is_some_and(|t| !t.is_punct(",") && !t.is_punct(")"))
+            {
+                i += 1;
+            }
+        }
+    }
+
+    /// Check `UPDATE t SET a = $1, b = $2`.
+    fn assignments(&mut self) {
+        let set = match self.tokens.iter().position(|t| t.is_keyword("set")) {
+            Some(set) => set,
+            None => return,
+        };
+        let mut i = set + 1;
+        while let Some((qualifier, column, next)) = self.column_at(i) {
+            if !self.token(next).is_some_and(|t| t.is_punct("=")) {
+                return;
+            }
+            let table = self.resolve(qualifier.as_deref(), &column);
+            if let (Some(table), Some(Token::Param(index))) = (table, self.token(next + 1)) {
+                let alone = match self.token(next + 2) {
+                    None => true,
+                    Some(t) => t.is_punct(",") || matches!(t, Token::Ident(_)),
+                };
+                if alone {
+                    self.params.insert(*index, (table, column));
+                }
+            }
+
+            i = next + 1;
+            let mut depth = 0;
+            while let Some(token) = self.token(i) {
+                if token.is_punct("(") {
+                    depth += 1;
+                } else if token.is_punct(")") {
+                    depth -= 1;
+                } else if depth == 0 && (token.is_punct(",") || is_clause(token)) {
+                    break;
+                }
+                i += 1;
+            }
+            if !self.token(i).is_some_and(|t| t.is_punct(",")) {
+                return;
+            }
+            i += 1;
+        }
+    }
+
+    /// Check `column = $1` and `$1 = column` comparisons.
+    fn comparisons(&mut self) {
+        const OPERATORS: &[&str] = &["=", "<>", "!=", "<", ">", "<=", ">="];
+        let is_operator = |token: &Token| match token {
+            Token::Punct(p) => OPERATORS.contains(&p.as_str()),
+            Token::Ident(i) => i == "like" || i == "ilike",
+            _ => false,
+        };
+
+        for i in 0..self.tokens.len() {
+            let index = match self.tokens[i] {
+                Token::Param(index) => index,
+                _ => continue,
+            };
+
+            if i >= 2 && is_operator(&self.tokens[i - 1]) {
+                let after = self.token(i + 1);
+                if after.is_some_and(|t| t.is_punct("::") || t.is_punct(".")) {
+                    continue;
+                }
+                let (start, qualified) = if i >= 4 && self.tokens[i - 3].is_punct(".") {
+                    (i - 4, true)
+                } else {
+                    (i - 2, false)
+                };
+                if start > 0
+                    && (self.tokens[start - 1].is_punct(".")
+                        || self.tokens[start - 1].is_punct("::"))
+                {
+                    continue;
+                }
+                if let Some((qualifier, column, next)) = self.column_at(start) {
+                    if next == i - 1 && qualified == qualifier.is_some() {
+                        if self.tokens[i - 1].is_punct("=") && self.is_assignment(start) {
+                            continue;
+                        }
+                        if let Some(table) = self.resolve(qualifier.as_deref(), &column) {
+                            self.params.entry(index).or_insert((table, column));
+                        }
+                    }
+                }
+            } else if self.token(i + 1).is_some_and(is_operator) {
+                if i > 0 && self.tokens[i - 1].is_punct("::") {
+                    continue;
+                }
+                if let Some((qualifier, column, next)) = self.column_at(i + 2) {
+                    if self
+                        .token(next)
+                        .is_some_and(|t| t.is_punct("::") || t.is_punct("("))
+                    {
+                        continue;
+                    }
+                    if let Some(table) = self.resolve(qualifier.as_deref(), &column) {
+                        self.params.entry(index).or_insert((table, column));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Is the column at this index the target of a `SET` assignment?
+    fn is_assignment(&self, i: usize) -> bool {
+        match i.checked_sub(1).and_then(|j| self.token(j)) {
+            Some(token) => token.is_keyword("set") || token.is_punct(","),
+            None => false,
+        }
+    }
+
+    /// Check the select list (or returning list) against a tuple row type.
+    fn results(&mut self, row: &[&syn::Type]) {
+        let start = match self.result_list_start() {
+            Some(start) => start,
+            None => return,
+        };
+
+        let mut items = vec![];
+        let mut item = vec![];
+        let mut depth = 0;
+        let mut i = start;
+        while let Some(token) = self.token(i) {
+            if token.is_punct("(") {
+                depth += 1;
+            } else if token.is_punct(")") {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            } else if depth == 0 && token.is_punct(",") {
+                items.push(std::mem::take(&mut item));
+                i += 1;
+                continue;
+            } else if depth == 0 && (token.is_keyword("from") || is_clause(token)) {
+                break;
+            }
+            item.push(i);
+            i += 1;
+        }
+        if !item.is_empty() {
+            items.push(item);
+        }
+
+        if items
+            .iter()
+            .any(|item| item.iter().any(|&i| self.tokens[i].is_punct("*")))
+        {
+            return;
+        }
+
+        if items.len() != row.len() {
+            self.problems.push(format!(
+                "query returns {} columns, but the row has {}",
+                items.len(),
+                row.len(),
+            ));
+            return;
+        }
+
+        for (item, ty) in items.iter().zip(row) {
+            let (qualifier, column, next) = match self.column_at(item[0]) {
+                Some(column) => column,
+                None => continue,
+            };
+            let rest = &item[(next - item[0])..];
+            let is_alias = match rest {
+                [] => true,
+                [alias] => self.tokens[*alias].name().is_some(),
+                [as_, alias] => {
+                    self.tokens[*as_].is_keyword("as") && self.tokens[*alias].name().is_some()
+                }
+                _ => false,
+            };
+            if !is_alias {
+                continue;
+            }
+
+            let table = match self.resolve(qualifier.as_deref(), &column) {
+                Some(table) => table,
+                None => continue,
+            };
+            let def = &self.schema.tables[&table][&column];
+            if !self.schema.accepts(ty, &def.ty) {
+                self.problems.push(format!(
+                    "column {table}.{column} is {}, but the row has Rust type {}",
+                    def.ty,
+                    type_string(ty),
+                ));
+            }
+            if def.nullable && matches!(rust_type(ty), Some((false, _))) {
+                self.problems.push(format!(
+                    "column {table}.{column} is nullable, but Rust type {} is not an Option",
+                    type_string(ty),
+                ));
+            }
+        }
+    }
+
+    /// Where the result columns are listed: the top-level `SELECT` or `RETURNING`.
+    fn result_list_start(&self) -> Option<usize> {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate() {
+            if token.is_punct("(") {
+                depth += 1;
+            } else if token.is_punct(")") {
+                depth -= 1;
+            } else if depth == 0 && (token.is_keyword("select") || token.is_keyword("returning")) {
+                let mut start = i + 1;
+                if self
+                    .token(start)
+                    .is_some_and(|t| t.is_keyword("distinct") || t.is_keyword("all"))
+                {
+                    start += 1;
+                }
+                return Some(start);
+            }
+        }
+        None
+    }
+}
+
+/// Could this table be in the snapshot?
+///
+/// The snapshot describes the default schema, so tables in any other
+/// schema, and the system catalogs, are skipped rather than unknown.
+fn in_snapshot(schema: Option<&str>, name: &str) -> bool {
+    match schema {
+        Some(schema) => schema == "public" || schema == "main",
+        None => !name.starts_with("pg_") && !name.starts_with("sqlite_"),
+    }
+}
+
+fn is_clause(token: &Token) -> bool {
+    [
+        "where",
+        "group",
+        "order",
+        "limit",
+        "having",
+        "returning",
+        "union",
+        "from",
+    ]
+    .iter()
+    .any(|clause| token.is_keyword(clause))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "tables": {
+            "customers": {
+                "id": { "type": "int4", "nullable": false },
+                "first_name": { "type": "text", "nullable": false },
+                "nickname": { "type": "varchar(64)", "nullable": true }
+            },
+            "orders": {
+                "id": { "type": "int4", "nullable": false },
+                "customer_id": { "type": "int4", "nullable": false },
+                "total": { "type": "numeric", "nullable": false }
+            }
+        }
+    }"#;
+
+    fn check(text: &str, params: &[&str], row: Option<&str>) -> Vec<String> {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let params: Vec<syn::Type> = params.iter().map(|p| syn::parse_str(p).unwrap()).collect();
+        let params: Vec<&syn::Type> = params.iter().collect();
+        let row: Option<syn::Type> = row.map(|r| syn::parse_str(r).unwrap());
+        check_query(&schema, text, &params, row.as_ref())
+    }
+
+    #[test]
+    fn matching() {
+        let problems = check(
+            "SELECT c.id, c.first_name, o.total FROM customers c \
+                JOIN orders AS o ON o.customer_id = c.id \
+                WHERE c.first_name = $1 AND o.total > $2",
+            &["&'a str", "rust_decimal::Decimal"],
+            Some("(i32, String, Decimal)"),
+        );
+        assert_eq!(Vec::<String>::new(), problems);
+
+        let problems = check(
+            "INSERT INTO customers (first_name, nickname) VALUES ($1, $2) RETURNING id",
+            &["String", "Option<&'a str>"],
+            Some("(i32,)"),
+        );
+        assert_eq!(Vec::<String>::new(), problems);
+
+        let problems = check(
+            "SELECT EXTRACT(MONTH FROM now()), count(*) FROM orders",
+            &[],
+            None,
+        );
+        assert_eq!(Vec::<String>::new(), problems);
+    }
+
+    #[test]
+    fn unknown_names() {
+        let problems = check("SELECT id FROM customer", &[], None);
+        assert_eq!(vec!["unknown table customer"], problems);
+
+        let problems = check("SELECT c.name FROM customers c", &[], None);
+        assert_eq!(vec!["unknown column customers.name"], problems);
+
+        let problems = check(
+            "UPDATE customers SET name = $1 WHERE id = $2",
+            &["String", "i32"],
+            None,
+        );
+        assert_eq!(vec!["unknown column name"], problems);
+
+        let problems = check(
+            "INSERT INTO orders (customer, total) VALUES (?, ?)",
+            &["i32", "Decimal"],
+            None,
+        );
+        assert_eq!(vec!["unknown column orders.customer"], problems);
+
+        let problems = check(
+            "SELECT c.relname FROM pg_catalog.pg_class c \
+                JOIN information_schema.tables t ON t.table_name = c.relname \
+                JOIN pg_namespace n ON n.oid = c.relnamespace",
+            &[],
+            None,
+        );
+        assert_eq!(Vec::<String>::new(), problems);
+
+        let problems = check("SELECT id FROM public.customer", &[], None);
+        assert_eq!(vec!["unknown table customer"], problems);
+    }
+
+    #[test]
+    fn jsonb_operators() {
+        let problems = check(
+            "SELECT id FROM customers WHERE tags ? 'vip' AND tags ?| $1 AND tags ?& $2",
+            &["Vec<String>", "Vec<String>"],
+            None,
+        );
+        assert_eq!(Vec::<String>::new(), problems);
+
+        let problems = check(
+            "SELECT id FROM customers WHERE tags ? first_name AND id = $1",
+            &["i32"],
+            None,
+        );
+        assert_eq!(Vec::<String>::new(), problems);
+    }
+
+    #[test]
+    fn types() {
+        let problems = check("SELECT id FROM customers WHERE id = $1", &["i64"], None);
+        assert_eq!(
+            vec!["parameter $1 has Rust type i64, but column customers.id is int4"],
+            problems,
+        );
+
+        let problems = check(
+            "SELECT id, nickname FROM customers",
+            &[],
+            Some("(String, String)"),
+        );
+        assert_eq!(
+            vec![
+                "column customers.id is int4, but the row has Rust type String",
+                "column customers.nickname is nullable, but Rust type String is not an Option",
+            ],
+            problems,
+        );
+
+        let problems = check("SELECT id, first_name FROM customers", &[], Some("(i32,)"));
+        assert_eq!(vec!["query returns 2 columns, but the row has 1"], problems);
+
+        let problems = check("SELECT id FROM customers WHERE id = $2", &["i32"], None);
+        assert_eq!(
+            vec!["query text uses 2 parameters, but there are 1 fields"],
+            problems,
+        );
+    }
+
+    #[test]
+    fn lenient_dialect() {
+        let schema =
+            Schema::parse(r#"{"dialect": "sqlite", "tables": {"t": {"id": {"type": "INTEGER"}}}}"#)
+                .unwrap();
+        let i64_ty: syn::Type = syn::parse_str("i64").unwrap();
+        let text_ty: syn::Type = syn::parse_str("String").unwrap();
+        assert!(check_query(&schema, "SELECT id FROM t WHERE id = ?", &[&i64_ty], None).is_empty());
+        assert_eq!(
+            1,
+            check_query(&schema, "SELECT id FROM t WHERE id = ?", &[&text_ty], None).len(),
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use quote::quote;
 
+mod check;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Key {
     Index,
@@ -12,6 +14,13 @@ enum Delegate {
     FromColumns,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum QueryKind {
+    Statement,
+    Query,
+    QueryOne,
+}
+
 /// Derive macro available if aykroyd is built with `features = ["derive"]`.
 #[proc_macro_derive(Statement, attributes(aykroyd))]
 pub fn derive_statement(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -19,20 +28,23 @@ pub fn derive_statement(input: proc_macro::TokenStream) -> proc_macro::TokenStre
 
     let name = &ast.ident;
     let generics = &ast.generics;
+    let fields = match &ast.data {
+        syn::Data::Enum(data) => return derive_enum(&ast, data, QueryKind::Statement).into(),
+        syn::Data::Union(_) => panic!("Cannot derive Statement on union!"),
+        syn::Data::Struct(s) => &s.fields,
+    };
+
     let attr = ast
         .attrs
         .iter()
         .find(|attr| attr.path().is_ident("aykroyd"))
         .unwrap();
 
-    let fields = match &ast.data {
-        syn::Data::Enum(_) => panic!("Cannot derive Statement on enum!"),
-        syn::Data::Union(_) => panic!("Cannot derive Statement on union!"),
-        syn::Data::Struct(s) => &s.fields,
-    };
-
-    let query_text = {
+    let (query_text, check, insert_values, returning) = {
         let mut query_text = None;
+        let mut check = None;
+        let mut insert_values = false;
+        let mut returning = None;
 
         attr.parse_nested_meta(|meta| {
             if meta.path.is_ident("text") {
@@ -42,21 +54,55 @@ pub fn derive_statement(input: proc_macro::TokenStream) -> proc_macro::TokenStre
                 return Ok(());
             }
 
+            if meta.path.is_ident("check") {
+                check = Some(parse_check(&meta)?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("insert_values") {
+                insert_values = true;
+                return Ok(());
+            }
+
+            if meta.path.is_ident("returning") {
+                let content;
+                syn::parenthesized!(content in meta.input);
+                let column: syn::Ident = content.parse()?;
+                content.parse::<syn::Token![:]>()?;
+                let ty: syn::Type = content.parse()?;
+                returning = Some((column, ty));
+                return Ok(());
+            }
+
             Err(meta.error("unknown meta path"))
         })
         .unwrap();
 
         match query_text {
-            Some(q) => q,
+            Some(q) => (q, check, insert_values, returning),
             None => panic!("unable to find query text"),
         }
     };
 
-    let query_text_impl = impl_static_query_text(name, generics, &query_text);
+    let query_text_impl = impl_query_text(name, generics, &query_text, fields);
     let to_params_impl = impl_to_params(name, generics, fields);
     let statement_impl = impl_statement(name, generics);
+    let describe_impl = impl_describe_query(name, generics, fields, None);
+    let check_impl = impl_check(check, &query_text, fields, None);
+    let register_impl = impl_register(&name.to_string(), &query_text, fields, quote!(Statement));
+    let insert_values_impl = match insert_values {
+        true => impl_insert_values(name, generics, &query_text, fields),
+        false => quote!(),
+    };
+    let returning_impl = match returning {
+        Some((column, ty)) => impl_returning(name, generics, &column, &ty),
+        None => quote!(),
+    };
 
-    let body = quote!(#query_text_impl #to_params_impl #statement_impl);
+    let body = quote! {
+        #query_text_impl #to_params_impl #statement_impl #describe_impl #check_impl #register_impl
+        #insert_values_impl #returning_impl
+    };
     body.into()
 }
 
@@ -67,21 +113,23 @@ pub fn derive_query(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
     let name = &ast.ident;
     let generics = &ast.generics;
+    let fields = match &ast.data {
+        syn::Data::Enum(data) => return derive_enum(&ast, data, QueryKind::Query).into(),
+        syn::Data::Union(_) => panic!("Cannot derive Query on union!"),
+        syn::Data::Struct(s) => &s.fields,
+    };
+
     let attr = ast
         .attrs
         .iter()
         .find(|attr| attr.path().is_ident("aykroyd"))
         .unwrap();
 
-    let fields = match &ast.data {
-        syn::Data::Enum(_) => panic!("Cannot derive Query on enum!"),
-        syn::Data::Union(_) => panic!("Cannot derive Query on union!"),
-        syn::Data::Struct(s) => &s.fields,
-    };
-
-    let (query_text, row) = {
+    let (query_text, row, check, primary) = {
         let mut query_text = None;
         let mut row = None;
+        let mut check = None;
+        let mut primary = false;
 
         attr.parse_nested_meta(|meta| {
             if meta.path.is_ident("text") {
@@ -99,22 +147,37 @@ pub fn derive_query(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 return Ok(());
             }
 
+            if meta.path.is_ident("check") {
+                check = Some(parse_check(&meta)?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("primary") {
+                primary = true;
+                return Ok(());
+            }
+
             Err(meta.error("unknown meta path"))
         })
         .unwrap();
 
         match (query_text, row) {
-            (Some(q), Some(r)) => (q, r),
+            (Some(q), Some(r)) => (q, r, check, primary),
             (None, _) => panic!("unable to find query text"),
             (_, None) => panic!("unable to find row"),
         }
     };
 
-    let query_text_impl = impl_static_query_text(name, generics, &query_text);
+    let query_text_impl = impl_query_text(name, generics, &query_text, fields);
     let to_params_impl = impl_to_params(name, generics, fields);
-    let query_impl = impl_query(name, generics, &row);
+    let query_impl = impl_query(name, generics, &row, primary);
+    let describe_impl = impl_describe_query(name, generics, fields, Some(&row));
+    let check_impl = impl_check(check, &query_text, fields, Some(&row));
+    let register_impl = impl_register(&name.to_string(), &query_text, fields, quote!(Query));
 
-    let body = quote!(#query_text_impl #to_params_impl #query_impl);
+    let body = quote! {
+        #query_text_impl #to_params_impl #query_impl #describe_impl #check_impl #register_impl
+    };
     body.into()
 }
 
@@ -125,21 +188,23 @@ pub fn derive_query_one(input: proc_macro::TokenStream) -> proc_macro::TokenStre
 
     let name = &ast.ident;
     let generics = &ast.generics;
+    let fields = match &ast.data {
+        syn::Data::Enum(data) => return derive_enum(&ast, data, QueryKind::QueryOne).into(),
+        syn::Data::Union(_) => panic!("Cannot derive QueryOne on union!"),
+        syn::Data::Struct(s) => &s.fields,
+    };
+
     let attr = ast
         .attrs
         .iter()
         .find(|attr| attr.path().is_ident("aykroyd"))
         .unwrap();
 
-    let fields = match &ast.data {
-        syn::Data::Enum(_) => panic!("Cannot derive QueryOne on enum!"),
-        syn::Data::Union(_) => panic!("Cannot derive QueryOne on union!"),
-        syn::Data::Struct(s) => &s.fields,
-    };
-
-    let (query_text, row) = {
+    let (query_text, row, check, primary) = {
         let mut query_text = None;
         let mut row = None;
+        let mut check = None;
+        let mut primary = false;
 
         attr.parse_nested_meta(|meta| {
             if meta.path.is_ident("text") {
@@ -157,26 +222,382 @@ pub fn derive_query_one(input: proc_macro::TokenStream) -> proc_macro::TokenStre
                 return Ok(());
             }
 
+            if meta.path.is_ident("check") {
+                check = Some(parse_check(&meta)?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("primary") {
+                primary = true;
+                return Ok(());
+            }
+
             Err(meta.error("unknown meta path"))
         })
         .unwrap();
 
         match (query_text, row) {
-            (Some(q), Some(r)) => (q, r),
+            (Some(q), Some(r)) => (q, r, check, primary),
             (None, _) => panic!("unable to find query text"),
             (_, None) => panic!("unable to find row"),
         }
     };
 
-    let query_text_impl = impl_static_query_text(name, generics, &query_text);
+    let query_text_impl = impl_query_text(name, generics, &query_text, fields);
     let to_params_impl = impl_to_params(name, generics, fields);
-    let query_impl = impl_query(name, generics, &row);
+    let query_impl = impl_query(name, generics, &row, primary);
     let query_one_impl = impl_query_one(name, generics);
+    let describe_impl = impl_describe_query(name, generics, fields, Some(&row));
+    let check_impl = impl_check(check, &query_text, fields, Some(&row));
+    let register_impl = impl_register(&name.to_string(), &query_text, fields, quote!(QueryOne));
 
-    let body = quote!(#query_text_impl #to_params_impl #query_impl #query_one_impl);
+    let body = quote! {
+        #query_text_impl #to_params_impl #query_impl #query_one_impl #describe_impl #check_impl
+        #register_impl
+    };
     body.into()
 }
 
+/// Derive a statement or query on an enum, where each variant is a query.
+fn derive_enum(
+    ast: &syn::DeriveInput,
+    data: &syn::DataEnum,
+    kind: QueryKind,
+) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let generics = &ast.generics;
+
+    let (row, check, primary) = {
+        let mut row = None;
+        let mut check = None;
+        let mut primary = false;
+
+        let attr = ast
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("aykroyd"));
+
+        if let Some(attr) = attr {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("row") && kind != QueryKind::Statement {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let ty: syn::Type = content.parse()?;
+                    row = Some(ty);
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("check") {
+                    check = Some(parse_check(&meta)?);
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("primary") && kind != QueryKind::Statement {
+                    primary = true;
+                    return Ok(());
+                }
+
+                Err(meta.error("unknown meta path"))
+            })
+            .unwrap();
+        }
+
+        if kind != QueryKind::Statement && row.is_none() {
+            panic!("unable to find row");
+        }
+
+        (row, check, primary)
+    };
+
+    let kind_name = match kind {
+        QueryKind::Statement => quote!(Statement),
+        QueryKind::Query => quote!(Query),
+        QueryKind::QueryOne => quote!(QueryOne),
+    };
+
+    let mut texts = vec![];
+    let mut texts_for = vec![];
+    let mut params = vec![];
+    let mut describes = vec![];
+    let mut literals = vec![];
+    let mut unredacted = vec![];
+    let mut literal_wheres = vec![];
+    let mut wheres = vec![];
+    let mut extras = vec![];
+    let mut variant_describes = vec![];
+    let mut describe_wheres = vec![];
+
+    for variant in &data.variants {
+        let ident = &variant.ident;
+
+        let (query_text, variant_check) = {
+            let mut query_text = None;
+            let mut variant_check = None;
+
+            let attr = variant
+                .attrs
+                .iter()
+                .find(|attr| attr.path().is_ident("aykroyd"))
+                .unwrap_or_else(|| panic!("unable to find query text for {ident}"));
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("text") {
+                    let value = meta.value()?;
+                    let text: syn::LitStr = value.parse()?;
+                    query_text = Some(text);
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("check") {
+                    variant_check = Some(parse_check(&meta)?);
+                    return Ok(());
+                }
+
+                Err(meta.error("unknown meta path"))
+            })
+            .unwrap();
+
+            match query_text {
+                Some(q) => (q, variant_check.or_else(|| check.clone())),
+                None => panic!("unable to find query text for {ident}"),
+            }
+        };
+
+        // Bind every field by position, so a field named like one of the
+        // generated locals (`params`, `literals`) can't shadow it.
+        let bindings: Vec<_> = (0..variant.fields.len())
+            .map(|index| quote::format_ident!("field_{}", index))
+            .collect();
+        let names = variant.fields.iter().map(|field| &field.ident);
+        let pattern = match &variant.fields {
+            syn::Fields::Unit => quote!(Self::#ident),
+            syn::Fields::Named(_) => quote!(Self::#ident { #(#names: #bindings),* }),
+            syn::Fields::Unnamed(_) => quote!(Self::#ident(#(#bindings),*)),
+        };
+        let accesses: Vec<_> = bindings.iter().map(|binding| quote!(#binding)).collect();
+        let body = params_body(&variant.fields, &accesses);
+        params.push(quote!(#pattern => #body));
+        let describe = describe_body(&variant.fields, &accesses);
+        let literal = literals_body(&variant.fields, &accesses, true);
+        literals.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => #literal
+        });
+        let literal = literals_body(&variant.fields, &accesses, false);
+        unredacted.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => #literal
+        });
+        literal_wheres.extend(literals_wheres(&variant.fields));
+        describes.push(quote! {
+            #[allow(unused_variables)]
+            #pattern => #describe
+        });
+        wheres.extend(params_wheres(&variant.fields));
+
+        let text = query_text.value();
+        let text = text.trim();
+        let lists: Vec<_> = variant
+            .fields
+            .iter()
+            .zip(&bindings)
+            .enumerate()
+            .filter(|(_, (field, _))| is_expand(field))
+            .map(|(index, (_, binding))| {
+                let index = index + 1;
+                quote!((#index, ::aykroyd::list::ParamList::items(#binding).len()))
+            })
+            .collect();
+        if lists.is_empty() {
+            texts.push(quote!(Self::#ident { .. } => #text.into()));
+            texts_for.push(quote!(Self::#ident { .. } => #text.into()));
+        } else {
+            check_lists(text, &list_indexes(&variant.fields));
+            texts.push(quote! {
+                #[allow(unused_variables)]
+                #pattern => ::aykroyd::list::expand(#text, &[#(#lists),*])
+            });
+            texts_for.push(quote! {
+                #[allow(unused_variables)]
+                #pattern => ::aykroyd::list::bind::<C>(#text, &[#(#lists),*])
+            });
+        }
+
+        let (expected_params, expected_columns, expected_wheres) =
+            describe_expected(&variant.fields, row.as_ref());
+        let variant_name = ident.to_string();
+        let variant_lists = list_indexes(&variant.fields);
+        variant_describes.push(quote! {
+            ::aykroyd::verify::ExpectedVariant::new(
+                #variant_name,
+                #text,
+                &[#(#variant_lists),*],
+                #expected_params,
+                #expected_columns,
+            )
+        });
+        describe_wheres.extend(expected_wheres);
+
+        extras.push(impl_check(
+            variant_check,
+            &query_text,
+            &variant.fields,
+            row.as_ref(),
+        ));
+        extras.push(impl_register(
+            &format!("{name}::{ident}"),
+            &query_text,
+            &variant.fields,
+            kind_name.clone(),
+        ));
+    }
+
+    let generics_simple = simplify(generics);
+    let generics_c = insert_c(generics);
+    let query_text_impl = quote! {
+        #[automatically_derived]
+        impl #generics ::aykroyd::query::QueryText for #name #generics_simple {
+            fn query_text(&self) -> String {
+                match self {
+                    #(#texts,)*
+                }
+            }
+
+            fn query_text_for<C: ::aykroyd::client::Client>(&self) -> String {
+                match self {
+                    #(#texts_for,)*
+                }
+            }
+        }
+    };
+    let to_params_impl = quote! {
+        #[automatically_derived]
+        impl #generics_c ::aykroyd::query::ToParams<C> for #name #generics_simple
+        where
+            C: ::aykroyd::client::Client,
+            #(#wheres,)*
+        {
+            fn to_params(&self) -> Option<Vec<<C as ::aykroyd::client::Client>::Param<'_>>> {
+                match self {
+                    #(#params,)*
+                }
+            }
+
+            fn redacted_params(&self) -> Option<Vec<String>> {
+                Some(::aykroyd::query::DescribeParams::describe_params(self))
+            }
+        }
+
+        #[automatically_derived]
+        impl #generics ::aykroyd::query::DescribeParams for #name #generics_simple {
+            fn describe_params(&self) -> Vec<String> {
+                match self {
+                    #(#describes,)*
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #generics_c ::aykroyd::debug::ToLiterals<C> for #name #generics_simple
+        where
+            C: ::aykroyd::debug::Quote,
+            #(#literal_wheres,)*
+        {
+            fn to_literals(&self) -> Vec<String> {
+                match self {
+                    #(#literals,)*
+                }
+            }
+
+            fn to_literals_unredacted(&self) -> Vec<String> {
+                match self {
+                    #(#unredacted,)*
+                }
+            }
+        }
+    };
+
+    let kind_impl = match (kind, &row) {
+        (QueryKind::Statement, _) => impl_statement(name, generics),
+        (QueryKind::Query, Some(row)) => impl_query(name, generics, row, primary),
+        (QueryKind::QueryOne, Some(row)) => {
+            let query_impl = impl_query(name, generics, row, primary);
+            let query_one_impl = impl_query_one(name, generics);
+            quote!(#query_impl #query_one_impl)
+        }
+        (_, None) => unreachable!(),
+    };
+
+    let describe_impl = quote! {
+        #[automatically_derived]
+        impl #generics_c ::aykroyd::verify::DescribeVariants<C> for #name #generics_simple
+        where
+            C: ::aykroyd::verify::ClientTypes,
+            #(#describe_wheres,)*
+        {
+            fn describe_variants() -> Vec<::aykroyd::verify::ExpectedVariant<C>> {
+                vec![#(#variant_describes,)*]
+            }
+        }
+    };
+
+    quote!(#query_text_impl #to_params_impl #kind_impl #describe_impl #(#extras)*)
+}
+
+/// Parse `check` or `check = "path/to/schema.json"`.
+fn parse_check(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Option<syn::LitStr>> {
+    if meta.input.peek(syn::Token![=]) {
+        let value = meta.value()?;
+        Ok(Some(value.parse()?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn impl_check(
+    check: Option<Option<syn::LitStr>>,
+    query_text: &syn::LitStr,
+    fields: &syn::Fields,
+    row: Option<&syn::Type>,
+) -> proc_macro2::TokenStream {
+    let path = match check {
+        Some(path) => path,
+        None => return quote!(),
+    };
+
+    let query_text = query_text.value();
+    let params: Vec<_> = fields
+        .iter()
+        .map(|field| match is_expand(field) {
+            true => list_item_type(&field.ty),
+            false => &field.ty,
+        })
+        .collect();
+    check::check(path.as_ref(), query_text.trim(), &params, row)
+}
+
+/// Add the query to the registry, with the indexes of any list parameters.
+fn impl_register(
+    name: &str,
+    query_text: &syn::LitStr,
+    fields: &syn::Fields,
+    kind: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let query_text = query_text.value();
+    let query_text = query_text.trim();
+    let lists = list_indexes(fields);
+    quote! {
+        ::aykroyd::registry::submit! {
+            ::aykroyd::registry::QueryInfo::new(
+                ::std::concat!(::std::module_path!(), "::", #name),
+                #query_text,
+                &[#(#lists),*],
+                ::aykroyd::registry::QueryKind::#kind,
+            )
+        }
+    }
+}
+
 fn simplify(generics: &syn::Generics) -> proc_macro2::TokenStream {
     let params = generics.params.iter().map(|param| {
         use syn::GenericParam::*;
@@ -187,7 +608,7 @@ fn simplify(generics: &syn::Generics) -> proc_macro2::TokenStream {
         }
     });
 
-    quote!(<#(#params)*>)
+    quote!(<#(#params),*>)
 }
 
 fn insert_c(generics: &syn::Generics) -> syn::Generics {
@@ -221,53 +642,74 @@ fn impl_static_query_text(
     }
 }
 
-fn impl_to_params(
+/// Implement `QueryText`, which is static unless there are list parameters.
+fn impl_query_text(
     name: &syn::Ident,
     generics: &syn::Generics,
+    query_text: &syn::LitStr,
     fields: &syn::Fields,
 ) -> proc_macro2::TokenStream {
-    let fields = match &fields {
-        syn::Fields::Unit => vec![],
-        syn::Fields::Named(syn::FieldsNamed { named: fields, .. })
-        | syn::Fields::Unnamed(syn::FieldsUnnamed {
-            unnamed: fields, ..
-        }) => fields.into_iter().collect(),
-    };
+    let lists = list_indexes(fields);
+    if lists.is_empty() {
+        return impl_static_query_text(name, generics, query_text);
+    }
+    check_lists(&query_text.value(), &lists);
 
-    let mut params = vec![];
-    let mut wheres = vec![];
+    let lengths = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| is_expand(field))
+        .map(|(index, field)| {
+            let member = member(index, field);
+            let index = index + 1;
+            quote! {
+                (#index, ::aykroyd::list::ParamList::items(&self.#member).len())
+            }
+        })
+        .collect::<Vec<_>>();
 
-    for (index, field) in fields.iter().enumerate() {
-        let name = match &field.ident {
-            Some(name) => quote!(#name),
-            None => {
-                let index = index as u32;
-                let span = proc_macro2::Span::call_site();
-                let index = syn::Index { index, span };
-                quote!(#index)
+    let generics_simple = simplify(generics);
+    let query_text = query_text.value();
+    let query_text = query_text.trim();
+    quote! {
+        #[automatically_derived]
+        impl #generics ::aykroyd::query::QueryText for #name #generics_simple {
+            fn query_text(&self) -> String {
+                ::aykroyd::list::expand(#query_text, &[#(#lengths),*])
             }
-        };
-        params.push(quote! {
-            ::aykroyd::client::ToParam::to_param(&self.#name)
-        });
 
-        let ty = &field.ty;
-        wheres.push(quote! {
-            #ty: ::aykroyd::client::ToParam<C>
-        });
+            fn query_text_for<C: ::aykroyd::client::Client>(&self) -> String {
+                ::aykroyd::list::bind::<C>(#query_text, &[#(#lengths),*])
+            }
+        }
     }
+}
 
-    let body = if params.is_empty() {
-        quote!(None)
-    } else {
-        quote!(Some(vec![#(#params,)*]))
-    };
+fn impl_to_params(
+    name: &syn::Ident,
+    generics: &syn::Generics,
+    fields: &syn::Fields,
+) -> proc_macro2::TokenStream {
+    let accesses: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let member = member(index, field);
+            quote!(&self.#member)
+        })
+        .collect();
+    let body = params_body(fields, &accesses);
+    let describe = describe_body(fields, &accesses);
+    let literals = literals_body(fields, &accesses, true);
+    let unredacted = literals_body(fields, &accesses, false);
+    let wheres = params_wheres(fields);
+    let literal_wheres = literals_wheres(fields);
 
     let generics_simple = simplify(generics);
-    let generics = insert_c(generics);
+    let generics_c = insert_c(generics);
     quote! {
         #[automatically_derived]
-        impl #generics ::aykroyd::query::ToParams<C> for #name #generics_simple
+        impl #generics_c ::aykroyd::query::ToParams<C> for #name #generics_simple
         where
             C: ::aykroyd::client::Client,
             #(#wheres,)*
@@ -275,10 +717,444 @@ fn impl_to_params(
             fn to_params(&self) -> Option<Vec<<C as ::aykroyd::client::Client>::Param<'_>>> {
                 #body
             }
+
+            fn redacted_params(&self) -> Option<Vec<String>> {
+                Some(::aykroyd::query::DescribeParams::describe_params(self))
+            }
+        }
+
+        #[automatically_derived]
+        impl #generics ::aykroyd::query::DescribeParams for #name #generics_simple {
+            fn describe_params(&self) -> Vec<String> {
+                #describe
+            }
+        }
+
+        #[automatically_derived]
+        impl #generics_c ::aykroyd::debug::ToLiterals<C> for #name #generics_simple
+        where
+            C: ::aykroyd::debug::Quote,
+            #(#literal_wheres,)*
+        {
+            fn to_literals(&self) -> Vec<String> {
+                #literals
+            }
+
+            fn to_literals_unredacted(&self) -> Vec<String> {
+                #unredacted
+            }
+        }
+    }
+}
+
+/// The body of `to_params`, given an expression for each field.
+fn params_body(
+    fields: &syn::Fields,
+    accesses: &[proc_macro2::TokenStream],
+) -> proc_macro2::TokenStream {
+    if fields.is_empty() {
+        return quote!(None);
+    }
+
+    if list_indexes(fields).is_empty() {
+        return quote!(Some(
+            vec![#(::aykroyd::client::ToParam::to_param(#accesses),)*]
+        ));
+    }
+
+    let pushes = fields.iter().zip(accesses).map(|(field, access)| {
+        if is_expand(field) {
+            quote!(params.extend(::aykroyd::list::ToParamList::<C>::to_params(#access)))
+        } else {
+            quote!(params.push(::aykroyd::client::ToParam::to_param(#access)))
+        }
+    });
+    quote! {{
+        let mut params = vec![];
+        #(#pushes;)*
+        Some(params)
+    }}
+}
+
+/// The body of `describe_params`, given an expression for each field.
+///
+/// Sensitive fields are masked, and the rest use `Debug` if they can.
+fn describe_body(
+    fields: &syn::Fields,
+    accesses: &[proc_macro2::TokenStream],
+) -> proc_macro2::TokenStream {
+    if fields.is_empty() {
+        return quote!(vec![]);
+    }
+
+    let pushes = fields
+        .iter()
+        .zip(accesses)
+        .map(|field_access| match field_access {
+            (field, access) if is_expand(field) && is_sensitive(field) => quote! {
+                params.extend(
+                    ::aykroyd::list::ParamList::items(#access)
+                        .iter()
+                        .map(|_| ::aykroyd::query::describe::REDACTED.to_string())
+                )
+            },
+            (field, access) if is_expand(field) => quote! {
+                params.extend(
+                    ::aykroyd::list::ParamList::items(#access)
+                        .iter()
+                        .map(|item| (&::aykroyd::query::describe::Describe(item)).describe())
+                )
+            },
+            (field, _) if is_sensitive(field) => {
+                quote!(params.push(::aykroyd::query::describe::REDACTED.to_string()))
+            }
+            (_, access) => {
+                quote!(params.push((&::aykroyd::query::describe::Describe(#access)).describe()))
+            }
+        });
+    quote! {{
+        #[allow(unused_imports)]
+        use ::aykroyd::query::describe::{Opaque as _, ViaDebug as _};
+        let mut params = vec![];
+        #(#pushes;)*
+        params
+    }}
+}
+
+/// The body of `to_literals`, given an expression for each field.
+///
+/// With `redact`, sensitive fields are written as a comment instead.
+fn literals_body(
+    fields: &syn::Fields,
+    accesses: &[proc_macro2::TokenStream],
+    redact: bool,
+) -> proc_macro2::TokenStream {
+    if fields.is_empty() {
+        return quote!(vec![]);
+    }
+
+    let redacted = quote!(format!("/* {} */", ::aykroyd::query::describe::REDACTED));
+    let pushes = fields.iter().zip(accesses).map(|(field, access)| {
+        match (is_expand(field), redact && is_sensitive(field)) {
+            (true, true) => quote! {
+                literals.extend(
+                    ::aykroyd::list::ParamList::items(#access)
+                        .iter()
+                        .map(|_| #redacted)
+                )
+            },
+            (true, false) => quote! {
+                literals.extend(
+                    ::aykroyd::list::ParamList::items(#access)
+                        .iter()
+                        .map(::aykroyd::debug::ToLiteral::<C>::to_literal)
+                )
+            },
+            (false, true) => quote!(literals.push(#redacted)),
+            (false, false) => {
+                quote!(literals.push(::aykroyd::debug::ToLiteral::<C>::to_literal(#access)))
+            }
+        }
+    });
+    quote! {{
+        let mut literals = vec![];
+        #(#pushes;)*
+        literals
+    }}
+}
+
+/// The bounds needed to write each field as a literal.
+fn literals_wheres(fields: &syn::Fields) -> Vec<proc_macro2::TokenStream> {
+    fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            if is_expand(field) {
+                quote!(<#ty as ::aykroyd::list::ParamList>::Item: ::aykroyd::debug::ToLiteral<C>)
+            } else {
+                quote!(#ty: ::aykroyd::debug::ToLiteral<C>)
+            }
+        })
+        .collect()
+}
+
+/// The bounds needed to convert each field to params.
+fn params_wheres(fields: &syn::Fields) -> Vec<proc_macro2::TokenStream> {
+    fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            if is_expand(field) {
+                quote!(#ty: ::aykroyd::list::ToParamList<C>)
+            } else {
+                quote!(#ty: ::aykroyd::client::ToParam<C>)
+            }
+        })
+        .collect()
+}
+
+/// The name or index of a field.
+fn member(index: usize, field: &syn::Field) -> proc_macro2::TokenStream {
+    match &field.ident {
+        Some(name) => quote!(#name),
+        None => {
+            let index = index as u32;
+            let span = proc_macro2::Span::call_site();
+            let index = syn::Index { index, span };
+            quote!(#index)
+        }
+    }
+}
+
+/// Is the field a list parameter, marked `#[aykroyd(expand)]`?
+fn is_expand(field: &syn::Field) -> bool {
+    has_param_flag(field, "expand")
+}
+
+/// Is the field sensitive, marked `#[aykroyd(sensitive)]`?
+fn is_sensitive(field: &syn::Field) -> bool {
+    has_param_flag(field, "sensitive")
+}
+
+/// Is the parameter field marked with the given flag?
+fn has_param_flag(field: &syn::Field, flag: &str) -> bool {
+    let mut found = false;
+    for attr in &field.attrs {
+        if !attr.path().is_ident("aykroyd") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("expand") || meta.path.is_ident("sensitive") {
+                found |= meta.path.is_ident(flag);
+                return Ok(());
+            }
+
+            Err(meta.error("unknown meta path"))
+        })
+        .unwrap();
+    }
+    found
+}
+
+/// The parameter indexes of the list fields, counting from one.
+fn list_indexes(fields: &syn::Fields) -> Vec<usize> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| is_expand(field))
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// The item type of a list, if it can be found from the syntax.
+///
+/// This handles slices as well as the last type argument of a path,
+/// like `Vec<T>` or `InList<'a, T>`.
+fn list_item_type(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Reference(reference) => match &*reference.elem {
+            syn::Type::Slice(slice) => &slice.elem,
+            _ => ty,
+        },
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .and_then(|segment| match &segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => {
+                    args.args.iter().rev().find_map(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => Some(ty),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            })
+            .unwrap_or(ty),
+        _ => ty,
+    }
+}
+
+/// Split an insert statement around its `VALUES` tuple, for bulk inserts.
+fn impl_insert_values(
+    name: &syn::Ident,
+    generics: &syn::Generics,
+    query_text: &syn::LitStr,
+    fields: &syn::Fields,
+) -> proc_macro2::TokenStream {
+    if !list_indexes(fields).is_empty() {
+        panic!("insert_values statements can't have list parameters");
+    }
+
+    let query_text = query_text.value();
+    let query_text = query_text.trim();
+    let (start, end) = match values_tuple(query_text) {
+        Some(tuple) => tuple,
+        None => panic!("unable to find VALUES tuple for insert_values"),
+    };
+    let prefix = &query_text[..start];
+    let tuple = &query_text[start..end];
+    let suffix = &query_text[end..];
+    if has_placeholder(prefix) || has_placeholder(suffix) {
+        panic!("insert_values statements can only have parameters in the VALUES tuple");
+    }
+    let params = fields.len();
+
+    let generics_simple = simplify(generics);
+    quote! {
+        #[automatically_derived]
+        impl #generics ::aykroyd::bulk::InsertValues for #name #generics_simple {
+            const PREFIX: &'static str = #prefix;
+            const TUPLE: &'static str = #tuple;
+            const SUFFIX: &'static str = #suffix;
+            const PARAMS: usize = #params;
+        }
+    }
+}
+
+fn impl_returning(
+    name: &syn::Ident,
+    generics: &syn::Generics,
+    column: &syn::Ident,
+    ty: &syn::Type,
+) -> proc_macro2::TokenStream {
+    let column = column.to_string();
+    let generics_simple = simplify(generics);
+    quote! {
+        #[automatically_derived]
+        impl #generics ::aykroyd::query::Returning for #name #generics_simple {
+            type Id = #ty;
+            const ID_COLUMN: &'static str = #column;
+        }
+    }
+}
+
+/// Find the byte range of the parenthesized tuple after `VALUES`.
+fn values_tuple(query_text: &str) -> Option<(usize, usize)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut quote = None;
+    let mut keyword_end = None;
+    let mut previous = None;
+    for (at, c) in query_text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, _) if !previous.is_some_and(is_word) => {
+                let end = at + "values".len();
+                let keyword = query_text.get(at..end);
+                let after = query_text.get(end..).and_then(|rest| rest.chars().next());
+                if keyword.is_some_and(|keyword| keyword.eq_ignore_ascii_case("values"))
+                    && !after.is_some_and(is_word)
+                {
+                    keyword_end = Some(end);
+                    break;
+                }
+            }
+            _ => {}
+        }
+        previous = Some(c);
+    }
+
+    let keyword_end = keyword_end?;
+    let rest = &query_text[keyword_end..];
+    let start = keyword_end + rest.len() - rest.trim_start().len();
+    if !query_text[start..].starts_with('(') {
+        return None;
+    }
+
+    let mut quote = None;
+    let mut depth = 0;
+    for (at, c) in query_text[start..].char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((start, start + at + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Check that the placeholder of each list parameter directly follows
+/// `IN`, since that's the only place it can be expanded or bound as an
+/// array.
+fn check_lists(text: &str, lists: &[usize]) {
+    let mut quote = None;
+    let mut positional = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        let index = match (quote, c) {
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                continue;
+            }
+            (Some(q), _) if q == c => {
+                quote = None;
+                continue;
+            }
+            (None, '?') => {
+                positional += 1;
+                positional
+            }
+            (None, '$') if chars.peek().is_some_and(|(_, c)| c.is_ascii_digit()) => {
+                let mut index = 0;
+                while let Some(digit) = chars.peek().and_then(|(_, c)| c.to_digit(10)) {
+                    index = index * 10 + digit as usize;
+                    chars.next();
+                }
+                index
+            }
+            _ => continue,
+        };
+
+        if lists.contains(&index) && !follows_in(&text[..at]) {
+            panic!("list parameter {index} must directly follow IN");
         }
     }
 }
 
+/// Does the text end with the keyword `IN`?
+fn follows_in(text: &str) -> bool {
+    let text = text.trim_end();
+    let Some(at) = text.len().checked_sub(2) else {
+        return false;
+    };
+    match (text.get(..at), text.get(at..)) {
+        (Some(before), Some(keyword)) => {
+            keyword.eq_ignore_ascii_case("in")
+                && !before
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Does the text have any parameter placeholders?
+fn has_placeholder(text: &str) -> bool {
+    let mut quote = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '?') => return true,
+            (None, '$') if chars.peek().is_some_and(char::is_ascii_digit) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
 fn impl_statement(name: &syn::Ident, generics: &syn::Generics) -> proc_macro2::TokenStream {
     let generics_simple = simplify(generics);
     let generics = insert_c(generics);
@@ -297,7 +1173,14 @@ fn impl_query(
     name: &syn::Ident,
     generics: &syn::Generics,
     row: &syn::Type,
+    primary: bool,
 ) -> proc_macro2::TokenStream {
+    let primary = match primary {
+        true => quote!(
+            const PRIMARY: bool = true;
+        ),
+        false => quote!(),
+    };
     let generics_simple = simplify(generics);
     let generics = insert_c(generics);
     quote! {
@@ -309,14 +1192,13 @@ fn impl_query(
             Self: ::aykroyd::query::ToParams<C>,
         {
             type Row = #row;
+
+            #primary
         }
     }
 }
 
-fn impl_query_one(
-    name: &syn::Ident,
-    generics: &syn::Generics,
-) -> proc_macro2::TokenStream {
+fn impl_query_one(name: &syn::Ident, generics: &syn::Generics) -> proc_macro2::TokenStream {
     let generics_simple = simplify(generics);
     let generics = insert_c(generics);
     quote! {
@@ -330,6 +1212,73 @@ fn impl_query_one(
     }
 }
 
+fn impl_describe_query(
+    name: &syn::Ident,
+    generics: &syn::Generics,
+    fields: &syn::Fields,
+    row: Option<&syn::Type>,
+) -> proc_macro2::TokenStream {
+    let (params, columns, wheres) = describe_expected(fields, row);
+
+    let generics_simple = simplify(generics);
+    let generics = insert_c(generics);
+    quote! {
+        #[automatically_derived]
+        impl #generics ::aykroyd::verify::DescribeQuery<C> for #name #generics_simple
+        where
+            C: ::aykroyd::verify::ClientTypes,
+            #(#wheres,)*
+        {
+            fn describe_params() -> Vec<::aykroyd::verify::Expected<C>> {
+                #params
+            }
+
+            fn describe_columns() -> Vec<::aykroyd::verify::Expected<C>> {
+                #columns
+            }
+        }
+    }
+}
+
+/// The expected params and columns of a query, and the bounds they need.
+fn describe_expected(
+    fields: &syn::Fields,
+    row: Option<&syn::Type>,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    Vec<proc_macro2::TokenStream>,
+) {
+    let mut params = vec![];
+    let mut wheres = vec![];
+
+    for field in fields {
+        let ty = &field.ty;
+        let ty = match is_expand(field) {
+            true => quote!(<#ty as ::aykroyd::list::ParamList>::Item),
+            false => quote!(#ty),
+        };
+        params.push(quote! {
+            ::aykroyd::verify::Expected::param::<#ty>()
+        });
+        wheres.push(quote! {
+            #ty: ::aykroyd::verify::ParamType<C>
+        });
+    }
+
+    let columns = match row {
+        Some(row) => {
+            wheres.push(quote! {
+                #row: ::aykroyd::verify::DescribeRow<C>
+            });
+            quote!(<#row as ::aykroyd::verify::DescribeRow<C>>::describe_row())
+        }
+        None => quote!(vec![]),
+    };
+
+    (quote!(vec![#(#params,)*]), columns, wheres)
+}
+
 /// Derive macro available if aykroyd is built with `features = ["derive"]`.
 #[proc_macro_derive(FromRow, attributes(aykroyd))]
 pub fn derive_from_row(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -439,6 +1388,107 @@ pub fn derive_from_columns_named(input: proc_macro::TokenStream) -> proc_macro::
     body.into()
 }
 
+/// Derive macro available if aykroyd is built with `features = ["derive"]`.
+#[proc_macro_derive(Notification, attributes(aykroyd))]
+pub fn derive_notification(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast: syn::DeriveInput = syn::parse(input).unwrap();
+
+    let name = &ast.ident;
+    let fields = match &ast.data {
+        syn::Data::Enum(_) => panic!("Cannot derive Notification on enum!"),
+        syn::Data::Union(_) => panic!("Cannot derive Notification on union!"),
+        syn::Data::Struct(s) => &s.fields,
+    };
+    if !ast.generics.params.is_empty() {
+        panic!("Cannot derive Notification with generics!");
+    }
+
+    let attr = ast
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("aykroyd"))
+        .unwrap();
+
+    let (channel, json) = {
+        let mut channel = None;
+        let mut json = false;
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("channel") {
+                let value = meta.value()?;
+                let channel_name: syn::LitStr = value.parse()?;
+                channel = Some(channel_name);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("json") {
+                json = true;
+                return Ok(());
+            }
+
+            Err(meta.error("unknown meta path"))
+        })
+        .unwrap();
+
+        match channel {
+            Some(channel) => (channel, json),
+            None => panic!("unable to find channel"),
+        }
+    };
+
+    let (to_payload, from_payload) = if json {
+        (
+            quote!(::aykroyd::notify::to_json(self)),
+            quote!(::aykroyd::notify::from_json(payload)),
+        )
+    } else {
+        match fields.iter().collect::<Vec<_>>()[..] {
+            [] => {
+                let value = match fields {
+                    syn::Fields::Named(_) => quote!(#name {}),
+                    syn::Fields::Unnamed(_) => quote!(#name()),
+                    syn::Fields::Unit => quote!(#name),
+                };
+                (
+                    quote!(::std::result::Result::Ok(::std::string::String::new())),
+                    quote! {
+                        let _ = payload;
+                        ::std::result::Result::Ok(#value)
+                    },
+                )
+            }
+            [field] => {
+                let member = member(0, field);
+                let value = match &field.ident {
+                    Some(ident) => quote!(#name { #ident: value }),
+                    None => quote!(#name(value)),
+                };
+                (
+                    quote!(::std::result::Result::Ok(::std::string::ToString::to_string(&self.#member))),
+                    quote!(::aykroyd::notify::from_text(payload).map(|value| #value)),
+                )
+            }
+            _ => panic!("Notification with more than one field must use the json attribute"),
+        }
+    };
+
+    let body = quote! {
+        #[automatically_derived]
+        impl ::aykroyd::notify::Notification for #name {
+            const CHANNEL: &'static str = #channel;
+
+            fn to_payload(&self) -> ::std::result::Result<::std::string::String, ::aykroyd::notify::Error> {
+                #to_payload
+            }
+
+            fn from_payload(payload: &str) -> ::std::result::Result<Self, ::aykroyd::notify::Error> {
+                #from_payload
+            }
+        }
+    };
+    body.into()
+}
+
 fn select_from_columns_delegate(attrs: &[syn::Attribute]) -> Delegate {
     for attr in attrs {
         if attr.path().is_ident("aykroyd") {
@@ -466,6 +1516,10 @@ fn impl_from_row(key: Key, name: &syn::Ident) -> proc_macro2::TokenStream {
         Key::Index => (quote!(FromColumnsIndexed), quote!(ColumnsIndexed)),
         Key::Name => (quote!(FromColumnsNamed), quote!(ColumnsNamed)),
     };
+    let (describe_ty, describe_args) = match key {
+        Key::Index => (quote!(DescribeColumnsIndexed), quote!()),
+        Key::Name => (quote!(DescribeColumnsNamed), quote!("")),
+    };
 
     quote! {
         #[automatically_derived]
@@ -482,6 +1536,17 @@ fn impl_from_row(key: Key, name: &syn::Ident) -> proc_macro2::TokenStream {
                 )
             }
         }
+
+        #[automatically_derived]
+        impl<C> ::aykroyd::verify::DescribeRow<C> for #name
+        where
+            C: ::aykroyd::verify::ClientTypes,
+            Self: ::aykroyd::verify::#describe_ty<C>,
+        {
+            fn describe_row() -> Vec<::aykroyd::verify::Expected<C>> {
+                <Self as ::aykroyd::verify::#describe_ty<C>>::describe_columns(#describe_args)
+            }
+        }
     }
 }
 
@@ -492,6 +1557,8 @@ fn impl_from_columns(
     fields: &[&syn::Field],
 ) -> proc_macro2::TokenStream {
     let mut wheres = vec![];
+    let mut describe_wheres = vec![];
+    let mut describes = vec![];
     let mut num_const = 0;
     let mut plus_nesteds = vec![];
     let mut field_puts = vec![];
@@ -502,6 +1569,13 @@ fn impl_from_columns(
         {
             use Delegate::*;
             use Key::*;
+            let describe = match (key, delegate) {
+                (_, FromColumn) => quote!(::aykroyd::verify::ColumnType),
+                (Index, FromColumns) => quote!(::aykroyd::verify::DescribeColumnsIndexed),
+                (Name, FromColumns) => quote!(::aykroyd::verify::DescribeColumnsNamed),
+            };
+            describe_wheres.push(quote!(#ty: #describe<C>));
+
             let delegate = match (key, delegate) {
                 (Index, FromColumn) => quote!(::aykroyd::client::FromColumnIndexed),
                 (Index, FromColumns) => quote!(::aykroyd::row::FromColumnsIndexed),
@@ -516,7 +1590,7 @@ fn impl_from_columns(
                 Delegate::FromColumn => quote!(get),
                 Delegate::FromColumns => quote!(get_nested),
             };
-            let key = match key {
+            let column = match key {
                 Key::Index => {
                     // TODO: explicit index
                     let num_const = syn::LitInt::new(
@@ -546,14 +1620,39 @@ fn impl_from_columns(
                 }
             };
             field_puts.push(match &field.ident {
-                Some(field_name) => quote!(#field_name: columns.#get_method(#key)?),
-                None => quote!(columns.#get_method(#key)?),
+                Some(field_name) => quote!(#field_name: columns.#get_method(#column)?),
+                None => quote!(columns.#get_method(#column)?),
+            });
+
+            describes.push(match (key, delegate) {
+                (Key::Index, Delegate::FromColumn) => quote! {
+                    columns.push(::aykroyd::verify::Expected::column::<#ty>());
+                },
+                (Key::Index, Delegate::FromColumns) => quote! {
+                    columns.extend(
+                        <#ty as ::aykroyd::verify::DescribeColumnsIndexed<C>>::describe_columns(),
+                    );
+                },
+                (Key::Name, Delegate::FromColumn) => quote! {
+                    columns.push(
+                        ::aykroyd::verify::Expected::column::<#ty>()
+                            .named(format!("{}{}", prefix, #column)),
+                    );
+                },
+                (Key::Name, Delegate::FromColumns) => quote! {
+                    columns.extend(
+                        <#ty as ::aykroyd::verify::DescribeColumnsNamed<C>>::describe_columns(
+                            &format!("{}{}", prefix, #column),
+                        ),
+                    );
+                },
             });
         }
 
         match delegate {
             Delegate::FromColumn => num_const += 1,
-            Delegate::FromColumns => plus_nesteds.push(quote!(+ <#ty as ::aykroyd::row::FromColumnsIndexed<C>>::NUM_COLUMNS)),
+            Delegate::FromColumns => plus_nesteds
+                .push(quote!(+ <#ty as ::aykroyd::row::FromColumnsIndexed<C>>::NUM_COLUMNS)),
         }
     }
 
@@ -576,6 +1675,12 @@ fn impl_from_columns(
         Key::Name => quote!(),
     };
 
+    let (describe_ty, describe_args) = match key {
+        Key::Index => (quote!(DescribeColumnsIndexed), quote!()),
+        Key::Name if describes.is_empty() => (quote!(DescribeColumnsNamed), quote!(_prefix: &str)),
+        Key::Name => (quote!(DescribeColumnsNamed), quote!(prefix: &str)),
+    };
+
     quote! {
         #[automatically_derived]
         impl<C> ::aykroyd::row::#trait_ty<C> for #name
@@ -591,5 +1696,19 @@ fn impl_from_columns(
                 Ok(#name #field_list)
             }
         }
+
+        #[automatically_derived]
+        impl<C> ::aykroyd::verify::#describe_ty<C> for #name
+        where
+            C: ::aykroyd::verify::ClientTypes,
+            #(#describe_wheres),*
+        {
+            fn describe_columns(#describe_args) -> Vec<::aykroyd::verify::Expected<C>> {
+                #[allow(unused_mut)]
+                let mut columns = vec![];
+                #(#describes)*
+                columns
+            }
+        }
     }
 }
@@ -0,0 +1,1235 @@
+//! A client for whichever database a URL names.
+//!
+//! When the database isn't known until runtime, say a command-line tool
+//! that takes a connection URL, [`Client::connect`] picks the backend
+//! from the URL scheme:
+//!
+//! * `postgres://` or `postgresql://`, with the `postgres` feature,
+//! * `mysql://`, with the `mysql` feature, and
+//! * `sqlite:` followed by a path, or `sqlite::memory:`, with the
+//!   `rusqlite` feature.
+//!
+//! Parameters and columns are converted to and from a common [`Value`],
+//! so a query derived once works with every backend:
+#![cfg_attr(
+    all(feature = "derive", feature = "rusqlite"),
+    doc = r##"
+
+```
+# use aykroyd::{FromRow, QueryOne, Statement};
+# use aykroyd::any::Client;
+#[derive(Statement)]
+#[aykroyd(text = "CREATE TABLE todos (id INTEGER PRIMARY KEY, label TEXT NOT NULL)")]
+struct CreateTodos;
+
+#[derive(Statement)]
+#[aykroyd(text = "INSERT INTO todos (id, label) VALUES ($1, $2)")]
+struct InsertTodo<'a>(i32, &'a str);
+
+#[derive(FromRow)]
+struct Todo {
+    id: i32,
+    label: String,
+}
+
+#[derive(QueryOne)]
+#[aykroyd(row(Todo), text = "SELECT id, label FROM todos WHERE id = $1")]
+struct GetTodo(i32);
+
+# fn main() -> Result<(), aykroyd::any::Error> {
+let mut client = Client::connect("sqlite::memory:")?;
+client.execute(&CreateTodos)?;
+client.execute(&InsertTodo(1, "get milk"))?;
+
+let todo = client.query_one(&GetTodo(1))?;
+assert_eq!("get milk", todo.label);
+# Ok(())
+# }
+```
+"##
+)]
+//!
+//! Write placeholders PostgreSQL-style, as `$1`, and they're rewritten
+//! for each backend.  The query text still has to be SQL that every
+//! backend understands.
+//!
+//! Values are limited to what every backend has in common: null,
+//! booleans, integers, floats, text and bytes.  Convert other types in
+//! the query text, with a cast or a function.  For PostgreSQL, cast
+//! parameters through text, as in `$1::text::uuid`, and columns to
+//! text, as in `id::text`.
+
+use std::any::type_name;
+use std::sync::Arc;
+
+use crate::client::{FromColumnIndexed, FromColumnNamed, ToParam};
+use crate::combinator::ReturningId;
+use crate::comment::Comments;
+use crate::hook::{Hook, Hooks};
+use crate::list::{pieces, ParamList, Piece, ToParamList};
+use crate::query::{Returning, StaticQueryText};
+use crate::stats::Stats;
+use crate::{builder, debug, error, trace, QueryRows, QueryRowsOne, Statement};
+
+/// The type of errors from a `Client`.
+pub type Error = error::Error<BackendError>;
+
+/// An error from one of the backends.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BackendError {
+    #[cfg(feature = "mysql")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mysql")))]
+    Mysql(mysql::Error),
+    #[cfg(feature = "postgres")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
+    Postgres(postgres::Error),
+    #[cfg(feature = "rusqlite")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rusqlite")))]
+    Sqlite(rusqlite::Error),
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "mysql")]
+            BackendError::Mysql(err) => err.fmt(f),
+            #[cfg(feature = "postgres")]
+            BackendError::Postgres(err) => err.fmt(f),
+            #[cfg(feature = "rusqlite")]
+            BackendError::Sqlite(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+#[cfg(feature = "mysql")]
+impl From<mysql::Error> for BackendError {
+    fn from(err: mysql::Error) -> Self {
+        BackendError::Mysql(err)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<postgres::Error> for BackendError {
+    fn from(err: postgres::Error) -> Self {
+        BackendError::Postgres(err)
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl From<rusqlite::Error> for BackendError {
+    fn from(err: rusqlite::Error) -> Self {
+        BackendError::Sqlite(err)
+    }
+}
+
+/// A parameter or column value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+/// A type that can be converted to a [`Value`].
+pub trait ToValue {
+    fn to_value(&self) -> Value;
+}
+
+/// A type that can be converted from a [`Value`].
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, Error>;
+}
+
+fn mismatch<T>(value: &Value) -> Error {
+    Error::from_column_str(
+        format!("cannot convert {value:?} to {}", type_name::<T>()),
+        None,
+    )
+}
+
+impl ToValue for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        Ok(value.clone())
+    }
+}
+
+impl ToValue for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+/// MySQL and SQLite store booleans as integers, so those are accepted.
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Bool(value) => Ok(*value),
+            Value::Int(value) => Ok(*value != 0),
+            _ => Err(mismatch::<Self>(value)),
+        }
+    }
+}
+
+macro_rules! impl_int_value {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl ToValue for $ty {
+                fn to_value(&self) -> Value {
+                    Value::Int((*self).into())
+                }
+            }
+
+            impl FromValue for $ty {
+                fn from_value(value: &Value) -> Result<Self, Error> {
+                    match value {
+                        Value::Int(int) => (*int).try_into().map_err(|_| mismatch::<Self>(value)),
+                        _ => Err(mismatch::<Self>(value)),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_int_value!(i8, i16, i32, i64, u8, u16, u32);
+
+macro_rules! impl_float_value {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl ToValue for $ty {
+                fn to_value(&self) -> Value {
+                    Value::Float((*self).into())
+                }
+            }
+
+            impl FromValue for $ty {
+                fn from_value(value: &Value) -> Result<Self, Error> {
+                    match value {
+                        Value::Float(float) => Ok(*float as $ty),
+                        Value::Int(int) => Ok(*int as $ty),
+                        _ => Err(mismatch::<Self>(value)),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_float_value!(f32, f64);
+
+impl ToValue for str {
+    fn to_value(&self) -> Value {
+        Value::Text(self.into())
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        Value::Text(self.clone())
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Text(text) => Ok(text.clone()),
+            Value::Bytes(bytes) => {
+                String::from_utf8(bytes.clone()).map_err(|_| mismatch::<Self>(value))
+            }
+            _ => Err(mismatch::<Self>(value)),
+        }
+    }
+}
+
+impl ToValue for [u8] {
+    fn to_value(&self) -> Value {
+        Value::Bytes(self.into())
+    }
+}
+
+impl ToValue for Vec<u8> {
+    fn to_value(&self) -> Value {
+        Value::Bytes(self.clone())
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Bytes(bytes) => Ok(bytes.clone()),
+            Value::Text(text) => Ok(text.clone().into_bytes()),
+            _ => Err(mismatch::<Self>(value)),
+        }
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => value.to_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for &T {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+/// A result row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    columns: Arc<[String]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// The names of the columns.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The values of the columns, in order.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// The value of the column at the given index.
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.values.get(index)
+    }
+
+    /// The value of the column with the given name.
+    pub fn get_named(&self, name: &str) -> Option<&Value> {
+        let index = self.columns.iter().position(|column| column == name)?;
+        self.values.get(index)
+    }
+}
+
+impl<T: FromValue> FromColumnIndexed<Client> for T {
+    fn from_column(row: &Row, index: usize) -> Result<Self, Error> {
+        let value = row
+            .get(index)
+            .ok_or_else(|| Error::from_column_str(format!("unknown column {}", index), None))?;
+        T::from_value(value)
+    }
+}
+
+impl<T: FromValue> FromColumnNamed<Client> for T {
+    fn from_column(row: &Row, name: &str) -> Result<Self, Error> {
+        let value = row
+            .get_named(name)
+            .ok_or_else(|| Error::from_column_str(format!("unknown column {}", name), None))?;
+        T::from_value(value)
+    }
+}
+
+impl<T: ToValue> ToParam<Client> for T {
+    fn to_param(&self) -> Value {
+        self.to_value()
+    }
+}
+
+impl<L> ToParamList<Client> for L
+where
+    L: ParamList,
+    L::Item: ToValue,
+{
+    fn to_params(&self) -> Vec<Value> {
+        self.items().iter().map(ToParam::to_param).collect()
+    }
+}
+
+/// Which database a `Client` is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backend {
+    #[cfg(feature = "mysql")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mysql")))]
+    Mysql,
+    #[cfg(feature = "postgres")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
+    Postgres,
+    #[cfg(feature = "rusqlite")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rusqlite")))]
+    Sqlite,
+}
+
+#[allow(clippy::large_enum_variant)]
+enum Inner {
+    #[cfg(feature = "mysql")]
+    Mysql(crate::mysql::Client),
+    #[cfg(feature = "postgres")]
+    Postgres(crate::postgres::Client),
+    #[cfg(feature = "rusqlite")]
+    Sqlite(crate::rusqlite::Client),
+}
+
+/// A client for any of the enabled backends.
+///
+/// Hooks, stats and comments set on a backend client before wrapping it
+/// aren't used, set them on this client instead.
+pub struct Client {
+    inner: Inner,
+    hooks: Hooks,
+}
+
+impl crate::client::Client for Client {
+    type Row<'a> = Row;
+    type Param<'a> = Value;
+    type Error = BackendError;
+}
+
+impl builder::Placeholder for Client {
+    fn placeholder(index: usize) -> String {
+        format!("${index}")
+    }
+}
+
+impl debug::Quote for Client {}
+
+#[cfg(feature = "mysql")]
+impl From<crate::mysql::Client> for Client {
+    fn from(client: crate::mysql::Client) -> Self {
+        Client {
+            inner: Inner::Mysql(client),
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<crate::postgres::Client> for Client {
+    fn from(mut client: crate::postgres::Client) -> Self {
+        *client.hooks_mut() = Hooks::default();
+        Client {
+            inner: Inner::Postgres(client),
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl From<crate::rusqlite::Client> for Client {
+    fn from(client: crate::rusqlite::Client) -> Self {
+        Client {
+            inner: Inner::Sqlite(client),
+            hooks: Hooks::default(),
+        }
+    }
+}
+
+impl Client {
+    /// Connect to the database named by the URL.
+    ///
+    /// See the [module docs](crate::any) for the URLs supported.
+    pub fn connect(url: &str) -> Result<Self, Error> {
+        let scheme = url.split_once(':').map_or("", |(scheme, _)| scheme);
+        match scheme {
+            #[cfg(feature = "mysql")]
+            "mysql" => crate::mysql::Client::new(url)
+                .map(Client::from)
+                .map_err(|err| err.map_inner(BackendError::from)),
+            #[cfg(feature = "postgres")]
+            "postgres" | "postgresql" => crate::postgres::Client::connect(url, postgres::NoTls)
+                .map(Client::from)
+                .map_err(|err| err.map_inner(BackendError::from)),
+            #[cfg(feature = "rusqlite")]
+            "sqlite" => {
+                let path = url["sqlite:".len()..].trim_start_matches("//");
+                let client = match path {
+                    "" | ":memory:" => crate::rusqlite::Client::open_in_memory(),
+                    path => crate::rusqlite::Client::open(path),
+                };
+                client
+                    .map(Client::from)
+                    .map_err(|err| err.map_inner(BackendError::from))
+            }
+            _ => Err(Error::connect_str(
+                format!("unsupported database URL scheme {scheme:?}"),
+                None,
+            )),
+        }
+    }
+
+    /// Which database this client is connected to.
+    pub fn backend(&self) -> Backend {
+        match self.inner {
+            #[cfg(feature = "mysql")]
+            Inner::Mysql(_) => Backend::Mysql,
+            #[cfg(feature = "postgres")]
+            Inner::Postgres(_) => Backend::Postgres,
+            #[cfg(feature = "rusqlite")]
+            Inner::Sqlite(_) => Backend::Sqlite,
+        }
+    }
+
+    /// Add a hook to run around each query, on this client and its transactions.
+    ///
+    /// See the [`hook`](crate::hook) module for details.
+    pub fn add_hook<H: Hook + 'static>(&mut self, hook: H) {
+        self.hooks.add(hook);
+    }
+
+    /// Collect statistics for each query, on this client and its transactions.
+    ///
+    /// See the [`stats`](crate::stats) module for details.
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.hooks.set_stats(stats);
+    }
+
+    /// The statistics collected for this client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.hooks.stats()
+    }
+
+    /// Append a comment to each query, on this client and its transactions.
+    ///
+    /// See the [`comment`](crate::comment) module for details.
+    pub fn set_comments(&mut self, comments: Comments) {
+        #[cfg(feature = "postgres")]
+        #[allow(irrefutable_let_patterns)]
+        if let Inner::Postgres(client) = &mut self.inner {
+            client.hooks_mut().set_comments(comments.clone());
+        }
+        self.hooks.set_comments(comments);
+    }
+
+    /// The OpenTelemetry name of the database, for tracing spans.
+    fn db_system(&self) -> &'static str {
+        match self.inner {
+            #[cfg(feature = "mysql")]
+            Inner::Mysql(_) => "mysql",
+            #[cfg(feature = "postgres")]
+            Inner::Postgres(_) => "postgresql",
+            #[cfg(feature = "rusqlite")]
+            Inner::Sqlite(_) => "sqlite",
+        }
+    }
+
+    pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        let span = trace::Span::new(self.db_system(), "prepare", type_name::<S>(), S::QUERY_TEXT);
+        span.in_scope(|| {
+            let query_text = self.annotate(type_name::<S>(), S::QUERY_TEXT.into());
+            let (query_text, _) = self.rewrite(&query_text);
+            match &mut self.inner {
+                #[cfg(feature = "mysql")]
+                Inner::Mysql(client) => {
+                    use mysql::prelude::Queryable;
+                    client.as_mut().prep(query_text).map_err(prepare)?;
+                }
+                #[cfg(feature = "postgres")]
+                Inner::Postgres(client) => {
+                    client
+                        .prepare_internal(Some(type_name::<S>()), query_text)
+                        .map_err(|err| err.map_inner(BackendError::from))?;
+                }
+                #[cfg(feature = "rusqlite")]
+                Inner::Sqlite(client) => {
+                    client
+                        .as_mut()
+                        .prepare_cached(&query_text)
+                        .map_err(prepare)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub fn query<Q: QueryRows<Self, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let (params, query_text, span) = self.hooks.start(self.db_system(), "query", query)?;
+        let params = params.unwrap_or_default();
+        span.run(
+            |rows| rows.len() as u64,
+            || {
+                let rows = self.rows(type_name::<Q>(), &query_text, &params)?;
+                query.map_rows(&rows)
+            },
+        )
+    }
+
+    /// Executes a query which returns a single row, returning it.
+    ///
+    /// It's an error if the query does not return exactly one row.
+    pub fn query_one<Q: QueryRowsOne<Self, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let (params, query_text, span) = self.hooks.start(self.db_system(), "query_one", query)?;
+        let params = params.unwrap_or_default();
+        span.run(
+            |_| 1,
+            || {
+                let rows = self.rows(type_name::<Q>(), &query_text, &params)?;
+                match rows.as_slice() {
+                    [row] => query.map_row(row),
+                    [] => Err(Error::query_str("query returned no rows", None)),
+                    _ => Err(Error::query_str("query returned more than one row", None)),
+                }
+            },
+        )
+    }
+
+    /// Executes a query which returns zero or one rows, returning it.
+    ///
+    /// It's an error if the query returns more than one row.
+    pub fn query_opt<Q: QueryRowsOne<Self, M>, M>(
+        &mut self,
+        query: &Q,
+    ) -> Result<Option<Q::Row>, Error> {
+        let (params, query_text, span) = self.hooks.start(self.db_system(), "query_opt", query)?;
+        let params = params.unwrap_or_default();
+        span.run(
+            |row| row.is_some() as u64,
+            || {
+                let rows = self.rows(type_name::<Q>(), &query_text, &params)?;
+                match rows.as_slice() {
+                    [] => Ok(None),
+                    [row] => query.map_row(row).map(Some),
+                    _ => Err(Error::query_str("query returned more than one row", None)),
+                }
+            },
+        )
+    }
+
+    pub fn execute<S: Statement<Self>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) =
+            self.hooks.start(self.db_system(), "execute", statement)?;
+        let params = params.unwrap_or_default();
+        span.run(
+            |rows_affected| *rows_affected,
+            || self.rows_affected(type_name::<S>(), &query_text, &params),
+        )
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// On PostgreSQL and SQLite this adds a `RETURNING` clause, and on
+    /// MySQL it's the last `AUTO_INCREMENT` value generated.  See
+    /// [`Returning`](crate::query::Returning) for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Self> + Returning,
+        S::Id: FromValue,
+    {
+        #[cfg(feature = "mysql")]
+        if matches!(self.inner, Inner::Mysql(_)) {
+            self.execute(statement)?;
+            let id = self
+                .last_insert_id()
+                .ok_or_else(|| Error::query_str("no id was generated", None))?;
+            let id = i64::try_from(id)
+                .map_err(|_| Error::from_column_str(format!("id {id} is out of range"), None))?;
+            return S::Id::from_value(&Value::Int(id));
+        }
+
+        self.query_one(&ReturningId::new(statement))
+    }
+
+    /// The last `AUTO_INCREMENT` value generated on MySQL, if any.
+    #[cfg(feature = "mysql")]
+    fn last_insert_id(&mut self) -> Option<u64> {
+        match &mut self.inner {
+            Inner::Mysql(client) => Some(client.as_mut().last_insert_id()).filter(|id| *id != 0),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Begins a new database transaction.
+    ///
+    /// The transaction will roll back by default - use the `commit` method to commit it.
+    pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        self.batch("BEGIN")?;
+        Ok(Transaction {
+            client: self,
+            done: false,
+        })
+    }
+
+    /// Rewrite PostgreSQL-style placeholders for the backend.
+    ///
+    /// Returns the new text and, if the backend's placeholders are
+    /// positional, the index of the parameter for each of them.
+    fn rewrite(&self, text: &str) -> (String, Vec<usize>) {
+        let mut rewritten = String::with_capacity(text.len());
+        #[cfg_attr(not(feature = "mysql"), allow(unused_mut))]
+        let mut positional = vec![];
+        for piece in pieces(text) {
+            match piece {
+                Piece::Text(text) => rewritten.push_str(text),
+                Piece::Param(param) => match self.inner {
+                    #[cfg(feature = "mysql")]
+                    Inner::Mysql(_) => {
+                        rewritten.push('?');
+                        positional.push(param.index);
+                    }
+                    #[cfg(feature = "postgres")]
+                    Inner::Postgres(_) => rewritten.push_str(&format!("${}", param.index)),
+                    #[cfg(feature = "rusqlite")]
+                    Inner::Sqlite(_) => rewritten.push_str(&format!("?{}", param.index)),
+                },
+            }
+        }
+        (rewritten, positional)
+    }
+
+    /// Rewrite the query text for the backend, with the parameters in
+    /// the order it expects.
+    fn bind(&self, text: &str, params: &[Value]) -> Result<(String, Vec<Value>), Error> {
+        let (text, positional) = self.rewrite(text);
+        if positional.is_empty() {
+            return Ok((text, params.to_vec()));
+        }
+
+        let params = positional
+            .iter()
+            .map(|index| {
+                params.get(index.wrapping_sub(1)).cloned().ok_or_else(|| {
+                    Error::query_str(format!("no value for parameter ${index}"), None)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((text, params))
+    }
+
+    /// Append the comments for the query.
+    ///
+    /// The PostgreSQL client appends them itself when it prepares the
+    /// statement, so that its cache is keyed by the text without them.
+    fn annotate(&self, query_name: &str, text: String) -> String {
+        match self.inner {
+            #[cfg(feature = "postgres")]
+            Inner::Postgres(_) => text,
+            #[allow(unreachable_patterns)]
+            _ => self.hooks.annotate(query_name, text),
+        }
+    }
+
+    fn rows(
+        &mut self,
+        query_name: &str,
+        query_text: &str,
+        params: &[Value],
+    ) -> Result<Vec<Row>, Error> {
+        let query_text = self.annotate(query_name, query_text.into());
+        let (query_text, params) = self.bind(&query_text, params)?;
+        match &mut self.inner {
+            #[cfg(feature = "mysql")]
+            Inner::Mysql(client) => mysql_rows(client, &query_text, &params),
+            #[cfg(feature = "postgres")]
+            Inner::Postgres(client) => postgres_rows(client, query_name, query_text, &params),
+            #[cfg(feature = "rusqlite")]
+            Inner::Sqlite(client) => sqlite_rows(client, &query_text, &params),
+        }
+    }
+
+    fn rows_affected(
+        &mut self,
+        query_name: &str,
+        query_text: &str,
+        params: &[Value],
+    ) -> Result<u64, Error> {
+        let query_text = self.annotate(query_name, query_text.into());
+        let (query_text, params) = self.bind(&query_text, params)?;
+        match &mut self.inner {
+            #[cfg(feature = "mysql")]
+            Inner::Mysql(client) => {
+                use mysql::prelude::Queryable;
+
+                let conn = client.as_mut();
+                let statement = conn.prep(query_text).map_err(prepare)?;
+                conn.exec_drop(&statement, mysql_params(&params))
+                    .map_err(query)?;
+                Ok(conn.affected_rows())
+            }
+            #[cfg(feature = "postgres")]
+            Inner::Postgres(client) => {
+                let statement = client
+                    .prepare_internal(Some(query_name), query_text)
+                    .map_err(|err| err.map_inner(BackendError::from))?;
+                let params = postgres_params(&statement, &params)?;
+                let params: Vec<_> = params.iter().map(AsRef::as_ref).collect();
+                client.as_mut().execute(&statement, &params).map_err(query)
+            }
+            #[cfg(feature = "rusqlite")]
+            Inner::Sqlite(client) => {
+                let mut statement = client
+                    .as_mut()
+                    .prepare_cached(&query_text)
+                    .map_err(prepare)?;
+                let rows_affected = statement
+                    .execute(rusqlite::params_from_iter(params))
+                    .map_err(query)?;
+                Ok(rows_affected.try_into().unwrap_or_default())
+            }
+        }
+    }
+
+    /// Run transaction control statements.
+    fn batch(&mut self, text: &'static str) -> Result<(), Error> {
+        let span = trace::Span::transaction(self.db_system(), text);
+        span.in_scope(|| match &mut self.inner {
+            #[cfg(feature = "mysql")]
+            Inner::Mysql(client) => {
+                use mysql::prelude::Queryable;
+                client.as_mut().query_drop(text).map_err(transaction)
+            }
+            #[cfg(feature = "postgres")]
+            Inner::Postgres(client) => client.as_mut().batch_execute(text).map_err(transaction),
+            #[cfg(feature = "rusqlite")]
+            Inner::Sqlite(client) => client.as_mut().execute_batch(text).map_err(transaction),
+        })
+    }
+}
+
+/// A database transaction.
+///
+/// It rolls back when dropped, unless committed.
+pub struct Transaction<'a> {
+    client: &'a mut Client,
+    done: bool,
+}
+
+impl<'a> Transaction<'a> {
+    /// The statistics collected for the client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.client.stats()
+    }
+
+    /// Consumes the transaction, committing all changes made within it.
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.done = true;
+        self.client.batch("COMMIT")
+    }
+
+    /// Rolls the transaction back, discarding all changes made within it.
+    pub fn rollback(mut self) -> Result<(), Error> {
+        self.done = true;
+        self.client.batch("ROLLBACK")
+    }
+
+    pub fn query<Q: QueryRows<Client, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        self.client.query(query)
+    }
+
+    pub fn query_one<Q: QueryRowsOne<Client, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        self.client.query_one(query)
+    }
+
+    pub fn query_opt<Q: QueryRowsOne<Client, M>, M>(
+        &mut self,
+        query: &Q,
+    ) -> Result<Option<Q::Row>, Error> {
+        self.client.query_opt(query)
+    }
+
+    pub fn execute<S: Statement<Client>>(&mut self, statement: &S) -> Result<u64, Error> {
+        self.client.execute(statement)
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Client::insert`] for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Client> + Returning,
+        S::Id: FromValue,
+    {
+        self.client.insert(statement)
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.client.batch("ROLLBACK");
+        }
+    }
+}
+
+#[cfg(any(feature = "mysql", feature = "rusqlite"))]
+fn prepare<E: Into<BackendError>>(err: E) -> Error {
+    let err = err.into();
+    Error::prepare_str(err.to_string(), Some(err))
+}
+
+fn query<E: Into<BackendError>>(err: E) -> Error {
+    let err = err.into();
+    Error::query_str(err.to_string(), Some(err))
+}
+
+#[cfg(any(feature = "postgres", feature = "rusqlite"))]
+fn from_column<E: Into<BackendError>>(err: E) -> Error {
+    let err = err.into();
+    Error::from_column_str(err.to_string(), Some(err))
+}
+
+fn transaction<E: Into<BackendError>>(err: E) -> Error {
+    let err = err.into();
+    Error::transaction_str(err.to_string(), Some(err))
+}
+
+#[cfg(feature = "mysql")]
+fn mysql_params(params: &[Value]) -> mysql::Params {
+    if params.is_empty() {
+        return mysql::Params::Empty;
+    }
+
+    let params = params
+        .iter()
+        .map(|value| match value {
+            Value::Null => mysql::Value::NULL,
+            Value::Bool(value) => mysql::Value::Int((*value).into()),
+            Value::Int(value) => mysql::Value::Int(*value),
+            Value::Float(value) => mysql::Value::Double(*value),
+            Value::Text(value) => mysql::Value::Bytes(value.clone().into_bytes()),
+            Value::Bytes(value) => mysql::Value::Bytes(value.clone()),
+        })
+        .collect();
+    mysql::Params::Positional(params)
+}
+
+#[cfg(feature = "mysql")]
+fn mysql_rows(
+    client: &mut crate::mysql::Client,
+    query_text: &str,
+    params: &[Value],
+) -> Result<Vec<Row>, Error> {
+    use mysql::prelude::Queryable;
+
+    /// The character set of binary strings.
+    const BINARY: u16 = 63;
+
+    let conn = client.as_mut();
+    let statement = conn.prep(query_text).map_err(prepare)?;
+    let rows: Vec<mysql::Row> = conn.exec(&statement, mysql_params(params)).map_err(query)?;
+
+    let columns: Arc<[String]> = statement
+        .columns()
+        .iter()
+        .map(|column| column.name_str().into_owned())
+        .collect();
+
+    let value = |column: &mysql::Column, value: &mysql::Value| match value {
+        mysql::Value::NULL => Value::Null,
+        mysql::Value::Bytes(bytes) if column.character_set() == BINARY => {
+            Value::Bytes(bytes.clone())
+        }
+        mysql::Value::Bytes(bytes) => match String::from_utf8(bytes.clone()) {
+            Ok(text) => Value::Text(text),
+            Err(err) => Value::Bytes(err.into_bytes()),
+        },
+        mysql::Value::Int(int) => Value::Int(*int),
+        mysql::Value::UInt(int) => match i64::try_from(*int) {
+            Ok(int) => Value::Int(int),
+            Err(_) => Value::Text(int.to_string()),
+        },
+        mysql::Value::Float(float) => Value::Float((*float).into()),
+        mysql::Value::Double(float) => Value::Float(*float),
+        mysql::Value::Date(year, month, day, hour, minute, second, micros) => Value::Text(format!(
+            "{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}.{micros:06}"
+        )),
+        mysql::Value::Time(negative, days, hours, minute, second, micros) => {
+            let sign = if *negative { "-" } else { "" };
+            let hours = *days * 24 + u32::from(*hours);
+            Value::Text(format!(
+                "{sign}{hours:02}:{minute:02}:{second:02}.{micros:06}"
+            ))
+        }
+    };
+
+    Ok(rows
+        .iter()
+        .map(|row| Row {
+            columns: columns.clone(),
+            values: row
+                .columns_ref()
+                .iter()
+                .enumerate()
+                .map(|(index, column)| row.as_ref(index).map_or(Value::Null, |v| value(column, v)))
+                .collect(),
+        })
+        .collect())
+}
+
+/// Convert parameters to the types the statement expects.
+#[cfg(feature = "postgres")]
+fn postgres_params(
+    statement: &postgres::Statement,
+    params: &[Value],
+) -> Result<Vec<Box<dyn postgres::types::ToSql + Sync>>, Error> {
+    use postgres::types::Type;
+
+    fn param<T>(value: &Value) -> Result<Box<dyn postgres::types::ToSql + Sync>, Error>
+    where
+        T: FromValue + postgres::types::ToSql + Sync + 'static,
+    {
+        let value = Option::<T>::from_value(value)
+            .map_err(|err| Error::query_str(err.to_string(), None))?;
+        Ok(Box::new(value))
+    }
+
+    statement
+        .params()
+        .iter()
+        .zip(params)
+        .map(|(ty, value)| match *ty {
+            Type::BOOL => param::<bool>(value),
+            Type::INT2 => param::<i16>(value),
+            Type::INT4 => param::<i32>(value),
+            Type::INT8 => param::<i64>(value),
+            Type::OID => param::<u32>(value),
+            Type::FLOAT4 => param::<f32>(value),
+            Type::FLOAT8 => param::<f64>(value),
+            Type::BYTEA => param::<Vec<u8>>(value),
+            _ => param::<String>(value),
+        })
+        .collect()
+}
+
+#[cfg(feature = "postgres")]
+fn postgres_rows(
+    client: &mut crate::postgres::Client,
+    query_name: &str,
+    query_text: String,
+    params: &[Value],
+) -> Result<Vec<Row>, Error> {
+    use postgres::types::Type;
+
+    let statement = client
+        .prepare_internal(Some(query_name), query_text)
+        .map_err(|err| err.map_inner(BackendError::from))?;
+    let params = postgres_params(&statement, params)?;
+    let params: Vec<_> = params.iter().map(AsRef::as_ref).collect();
+    let rows = client.as_mut().query(&statement, &params).map_err(query)?;
+
+    let columns: Arc<[String]> = statement
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect();
+
+    fn get<'a, T, F>(row: &'a postgres::Row, index: usize, f: F) -> Result<Value, Error>
+    where
+        T: postgres::types::FromSql<'a>,
+        F: FnOnce(T) -> Value,
+    {
+        let value: Option<T> = row.try_get(index).map_err(from_column)?;
+        Ok(value.map_or(Value::Null, f))
+    }
+
+    rows.iter()
+        .map(|row| {
+            let values = statement
+                .columns()
+                .iter()
+                .enumerate()
+                .map(|(index, column)| match *column.type_() {
+                    Type::BOOL => get(row, index, Value::Bool),
+                    Type::INT2 => get(row, index, |int: i16| Value::Int(int.into())),
+                    Type::INT4 => get(row, index, |int: i32| Value::Int(int.into())),
+                    Type::INT8 => get(row, index, Value::Int),
+                    Type::OID => get(row, index, |int: u32| Value::Int(int.into())),
+                    Type::FLOAT4 => get(row, index, |float: f32| Value::Float(float.into())),
+                    Type::FLOAT8 => get(row, index, Value::Float),
+                    Type::BYTEA => get(row, index, Value::Bytes),
+                    _ => get(row, index, Value::Text),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Row {
+                columns: columns.clone(),
+                values,
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "rusqlite")]
+impl rusqlite::types::ToSql for Value {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        use rusqlite::types::{ToSqlOutput, ValueRef};
+
+        Ok(match self {
+            Value::Null => ToSqlOutput::Borrowed(ValueRef::Null),
+            Value::Bool(value) => ToSqlOutput::Borrowed(ValueRef::Integer((*value).into())),
+            Value::Int(value) => ToSqlOutput::Borrowed(ValueRef::Integer(*value)),
+            Value::Float(value) => ToSqlOutput::Borrowed(ValueRef::Real(*value)),
+            Value::Text(value) => ToSqlOutput::Borrowed(ValueRef::Text(value.as_bytes())),
+            Value::Bytes(value) => ToSqlOutput::Borrowed(ValueRef::Blob(value)),
+        })
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+fn sqlite_rows(
+    client: &mut crate::rusqlite::Client,
+    query_text: &str,
+    params: &[Value],
+) -> Result<Vec<Row>, Error> {
+    use rusqlite::types::ValueRef;
+
+    let mut statement = client
+        .as_mut()
+        .prepare_cached(query_text)
+        .map_err(prepare)?;
+    let columns: Arc<[String]> = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+    let mut rows = statement
+        .query(rusqlite::params_from_iter(params))
+        .map_err(query)?;
+
+    let mut result = vec![];
+    while let Some(row) = rows.next().map_err(query)? {
+        let values = (0..columns.len())
+            .map(|index| {
+                Ok(match row.get_ref(index).map_err(from_column)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(int) => Value::Int(int),
+                    ValueRef::Real(float) => Value::Float(float),
+                    ValueRef::Text(text) => Value::Text(String::from_utf8_lossy(text).into_owned()),
+                    ValueRef::Blob(bytes) => Value::Bytes(bytes.into()),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+        result.push(Row {
+            columns: columns.clone(),
+            values,
+        });
+    }
+    Ok(result)
+}
+
+#[cfg(all(test, feature = "derive", feature = "rusqlite"))]
+mod test {
+    use super::*;
+    use crate::list::InList;
+    use crate::{FromRow, Query, QueryOne};
+
+    #[derive(Statement)]
+    #[aykroyd(
+        text = "CREATE TABLE test_any (id INTEGER PRIMARY KEY, label TEXT NOT NULL, done BOOLEAN, data BLOB)"
+    )]
+    struct CreateTodos;
+
+    #[derive(Statement)]
+    #[aykroyd(text = "INSERT INTO test_any (id, label, done, data) VALUES ($1, $2, $3, $4)")]
+    struct InsertTodo<'a>(i32, &'a str, Option<bool>, &'a [u8]);
+
+    #[derive(FromRow)]
+    struct Todo {
+        id: i32,
+        label: String,
+        done: Option<bool>,
+        data: Vec<u8>,
+    }
+
+    #[derive(Query)]
+    #[aykroyd(
+        row(Todo),
+        text = "SELECT id, label, done, data FROM test_any WHERE id IN $1 AND label <> $2 ORDER BY id"
+    )]
+    struct GetTodos<'a> {
+        #[aykroyd(expand)]
+        ids: InList<'a, i32>,
+        not: &'a str,
+    }
+
+    #[derive(QueryOne)]
+    #[aykroyd(row((i64,)), text = "SELECT count(*) FROM test_any")]
+    struct CountTodos;
+
+    #[derive(QueryOne)]
+    #[aykroyd(row((String,)), text = "SELECT label FROM test_any ORDER BY id")]
+    struct GetLabel;
+
+    #[derive(Statement)]
+    #[aykroyd(returning(id: i64), text = "INSERT INTO test_any (label) VALUES ($1)")]
+    struct AddTodo<'a>(&'a str);
+
+    #[test]
+    fn end_to_end() {
+        let mut client = Client::connect("sqlite::memory:").unwrap();
+        assert_eq!(Backend::Sqlite, client.backend());
+
+        client.execute(&CreateTodos).unwrap();
+        client
+            .execute(&InsertTodo(1, "milk", Some(true), b"\x01"))
+            .unwrap();
+        client.execute(&InsertTodo(2, "eggs", None, b"")).unwrap();
+
+        let mut txn = client.transaction().unwrap();
+        txn.execute(&InsertTodo(3, "bread", Some(false), b""))
+            .unwrap();
+        txn.rollback().unwrap();
+        assert_eq!((2,), client.query_one(&CountTodos).unwrap());
+
+        let todos = client
+            .query(&GetTodos {
+                ids: InList(&[1, 2, 3]),
+                not: "eggs",
+            })
+            .unwrap();
+        assert_eq!(1, todos.len());
+        assert_eq!(1, todos[0].id);
+        assert_eq!("milk", todos[0].label);
+        assert_eq!(Some(true), todos[0].done);
+        assert_eq!(vec![1], todos[0].data);
+    }
+
+    #[test]
+    fn one_row() {
+        use crate::combinator::{Count, Exists};
+
+        let mut client = Client::connect("sqlite::memory:").unwrap();
+        client.execute(&CreateTodos).unwrap();
+
+        assert!(client.query_one(&GetLabel).is_err());
+        assert!(!client.query_one(&Exists::new(GetLabel)).unwrap());
+        assert_eq!(None, client.query_opt(&GetLabel).unwrap());
+
+        let id = client.insert(&AddTodo("milk")).unwrap();
+        assert_eq!(1, id);
+        assert_eq!(("milk".to_string(),), client.query_one(&GetLabel).unwrap());
+        assert_eq!(1, client.query_one(&Count::new(GetLabel)).unwrap());
+        assert!(client.query_one(&Exists::new(GetLabel)).unwrap());
+
+        let mut txn = client.transaction().unwrap();
+        assert_eq!(2, txn.insert(&AddTodo("eggs")).unwrap());
+        txn.commit().unwrap();
+
+        let err = client.query_one(&GetLabel).unwrap_err();
+        assert_eq!(crate::error::ErrorKind::Query, err.kind());
+        assert!(client.query_opt(&GetLabel).is_err());
+    }
+
+    #[test]
+    fn unsupported() {
+        let err = Client::connect("oracle://localhost").err().unwrap();
+        assert_eq!(crate::error::ErrorKind::Connect, err.kind());
+    }
+}
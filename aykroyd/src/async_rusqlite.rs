@@ -0,0 +1,563 @@
+//! Async Sqlite bindings.
+//!
+//! SQLite has no async interface, so this [`Client`] owns a
+//! [`rusqlite` client](crate::rusqlite::Client) on a thread of its own.
+//! Queries and statements are sent to that thread over a channel, run
+//! there with the usual cached prepared statements, and their rows are
+//! converted there too, so only the results come back.
+//!
+//! Since the query is moved to the connection's thread, it must own its
+//! parameters: use `String` rather than `&str`.  The same query types
+//! work with either client.
+#![cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::{Query, Statement};
+# use aykroyd::async_rusqlite::Client;
+#[derive(Statement)]
+#[aykroyd(text = "CREATE TABLE todos (id INTEGER PRIMARY KEY, label TEXT NOT NULL)")]
+struct CreateTodos;
+
+#[derive(Statement)]
+#[aykroyd(text = "INSERT INTO todos (label) VALUES ($1)")]
+struct InsertTodo(String);
+
+#[derive(Query)]
+#[aykroyd(row((i32, String)), text = "SELECT id, label FROM todos")]
+struct GetAllTodos;
+
+# async fn run() -> Result<(), aykroyd::async_rusqlite::Error> {
+let mut client = Client::open_in_memory().await?;
+client.execute(CreateTodos).await?;
+client.execute(InsertTodo("get things done".into())).await?;
+
+let todos = client.query(GetAllTodos).await?;
+assert_eq!(1, todos.len());
+# Ok(())
+# }
+# tokio::runtime::Runtime::new().unwrap().block_on(run()).unwrap();
+```
+"##
+)]
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::bulk::InsertValues;
+use crate::client::{ExecuteResult, FromColumnIndexed};
+use crate::comment::Comments;
+use crate::hook::Hook;
+use crate::query::{Returning, StaticQueryText, ToParams};
+use crate::rusqlite::Client as SyncClient;
+use crate::stats::Stats;
+use crate::{bulk, trace, QueryRows, QueryRowsOne, Statement};
+
+pub type Error = crate::rusqlite::Error;
+
+/// The OpenTelemetry name of the database, for tracing spans.
+const DB_SYSTEM: &str = "sqlite";
+
+/// Work to run on the connection's thread.
+type Job = Box<dyn FnOnce(&mut SyncClient) + Send>;
+
+/// An async SQLite client, running its connection on a background thread.
+///
+/// The thread stops when the client is dropped.
+pub struct Client {
+    jobs: mpsc::UnboundedSender<Job>,
+    stats: Option<Stats>,
+}
+
+impl From<rusqlite::Connection> for Client {
+    fn from(connection: rusqlite::Connection) -> Self {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        let client = SyncClient::from(connection);
+        std::thread::spawn(move || run(client, receiver));
+        Client { jobs, stats: None }
+    }
+}
+
+/// The connection's thread: run each job in turn, until the client is gone.
+fn run(mut client: SyncClient, mut jobs: mpsc::UnboundedReceiver<Job>) {
+    while let Some(job) = jobs.blocking_recv() {
+        job(&mut client);
+    }
+}
+
+fn stopped() -> Error {
+    Error::connect_str("the connection thread has stopped", None)
+}
+
+impl Client {
+    /// Open a database on a new background thread.
+    pub async fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_owned();
+        Client::start(move || SyncClient::open(path)).await
+    }
+
+    /// Open an in-memory database on a new background thread.
+    pub async fn open_in_memory() -> Result<Self, Error> {
+        Client::start(SyncClient::open_in_memory).await
+    }
+
+    async fn start<F>(open: F) -> Result<Self, Error>
+    where
+        F: FnOnce() -> Result<SyncClient, Error> + Send + 'static,
+    {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        let (opened, result) = oneshot::channel();
+        std::thread::Builder::new()
+            .name("aykroyd-sqlite".into())
+            .spawn(move || match open() {
+                Ok(client) => {
+                    let _ = opened.send(Ok(()));
+                    run(client, receiver);
+                }
+                Err(error) => {
+                    let _ = opened.send(Err(error));
+                }
+            })
+            .map_err(|error| Error::connect_str(error.to_string(), None))?;
+        result.await.map_err(|_| stopped())??;
+        Ok(Client { jobs, stats: None })
+    }
+
+    /// Run a function on the connection's thread, without waiting for it.
+    ///
+    /// Jobs run in the order they are sent, so this will be done before
+    /// anything sent later.
+    fn send<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut SyncClient) + Send + 'static,
+    {
+        #[cfg(feature = "tracing")]
+        let span = tracing::Span::current();
+        self.jobs
+            .send(Box::new(move |client| {
+                #[cfg(feature = "tracing")]
+                let _entered = span.enter();
+                f(client)
+            }))
+            .map_err(|_| stopped())
+    }
+
+    /// Run a function on the connection's thread, and wait for the result.
+    async fn call<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut SyncClient) -> Result<T, Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.send(move |client| {
+            let _ = sender.send(f(client));
+        })?;
+        receiver.await.map_err(|_| stopped())?
+    }
+
+    /// Run a hook around each query, on this client and its transactions.
+    ///
+    /// See the [`hook`](crate::hook) module for details.
+    pub fn add_hook<H: Hook + 'static>(&mut self, hook: H) {
+        let _ = self.send(move |client| client.add_hook(hook));
+    }
+
+    /// Collect statistics for each query, on this client and its transactions.
+    ///
+    /// See the [`stats`](crate::stats) module for details.
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.stats = Some(stats.clone());
+        let _ = self.send(move |client| client.set_stats(stats));
+    }
+
+    /// The statistics collected for this client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    /// Append a comment to each query, on this client and its transactions.
+    ///
+    /// See the [`comment`](crate::comment) module for details.
+    pub fn set_comments(&mut self, comments: Comments) {
+        let _ = self.send(move |client| client.set_comments(comments));
+    }
+
+    pub async fn query<Q, M>(&mut self, query: Q) -> Result<Vec<Q::Row>, Error>
+    where
+        Q: QueryRows<SyncClient, M> + Send + 'static,
+        Q::Row: Send + 'static,
+    {
+        self.call(move |client| client.query(&query)).await
+    }
+
+    pub async fn query_one<Q, M>(&mut self, query: Q) -> Result<Q::Row, Error>
+    where
+        Q: QueryRowsOne<SyncClient, M> + Send + 'static,
+        Q::Row: Send + 'static,
+    {
+        self.call(move |client| client.query_one(&query)).await
+    }
+
+    pub async fn query_opt<Q, M>(&mut self, query: Q) -> Result<Option<Q::Row>, Error>
+    where
+        Q: QueryRowsOne<SyncClient, M> + Send + 'static,
+        Q::Row: Send + 'static,
+    {
+        self.call(move |client| client.query_opt(&query)).await
+    }
+
+    pub async fn execute<S>(&mut self, statement: S) -> Result<u64, Error>
+    where
+        S: Statement<SyncClient> + Send + 'static,
+    {
+        self.call(move |client| client.execute(&statement)).await
+    }
+
+    /// Executes a statement, returning the number of rows affected and
+    /// the rowid of the last row inserted.
+    pub async fn execute_returning_id<S>(&mut self, statement: S) -> Result<ExecuteResult, Error>
+    where
+        S: Statement<SyncClient> + Send + 'static,
+    {
+        self.call(move |client| client.execute_returning_id(&statement))
+            .await
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub async fn insert<S>(&mut self, statement: S) -> Result<S::Id, Error>
+    where
+        S: Statement<SyncClient> + Returning + Send + 'static,
+        S::Id: FromColumnIndexed<SyncClient> + Send + 'static,
+    {
+        self.call(move |client| client.insert(&statement)).await
+    }
+
+    pub async fn prepare<S: StaticQueryText + 'static>(&mut self) -> Result<(), Error> {
+        self.call(|client| client.prepare::<S>()).await
+    }
+
+    /// Prepares every query in the [`registry`](crate::registry).
+    ///
+    /// Call this after connecting to catch invalid query text at
+    /// startup rather than first use.  It also warms the statement cache,
+    /// but that only holds 16 statements unless you raise it with
+    /// `set_prepared_statement_cache_capacity`.
+    /// Every registered query is prepared, including those of other
+    /// crates, so they should all be meant for this database.  If not,
+    /// use [`prepare_all_matching`](Self::prepare_all_matching).
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub async fn prepare_all(&mut self) -> Result<(), Error> {
+        self.call(|client| client.prepare_all()).await
+    }
+
+    /// Prepares the queries in the [`registry`](crate::registry) that
+    /// match the filter.
+    ///
+    /// Use this when not every registered query is meant for this
+    /// database, e.g. `|query| query.in_module("my_app::sqlite")`.
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub async fn prepare_all_matching<F>(&mut self, filter: F) -> Result<(), Error>
+    where
+        F: FnMut(&crate::registry::QueryInfo) -> bool + Send + 'static,
+    {
+        self.call(|client| client.prepare_all_matching(filter))
+            .await
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit, all in one transaction.  Returns the total number of rows
+    /// affected.  See the [`bulk`](crate::bulk) module for details.
+    pub async fn insert_many<S>(&mut self, rows: Vec<S>) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<SyncClient> + Send + 'static,
+    {
+        self.call(move |client| client.insert_many(&rows)).await
+    }
+
+    /// Begin a transaction.
+    ///
+    /// The transaction is rolled back if it's dropped without a commit.
+    pub async fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        // If this future is dropped once BEGIN is sent, the transaction
+        // still rolls back.
+        let mut txn = Transaction {
+            client: self,
+            done: false,
+        };
+        let begin = txn.client.call(|client| {
+            client
+                .as_mut()
+                .execute_batch("BEGIN DEFERRED")
+                .map_err(Error::transaction)
+        });
+        if let Err(error) = begin.await {
+            txn.done = true;
+            return Err(error);
+        }
+        Ok(txn)
+    }
+}
+
+pub struct Transaction<'a> {
+    client: &'a mut Client,
+    done: bool,
+}
+
+impl<'a> Transaction<'a> {
+    /// The statistics collected for the client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.client.stats()
+    }
+
+    async fn finish(mut self, operation: &'static str) -> Result<(), Error> {
+        self.done = true;
+        self.client
+            .call(move |client| {
+                let span = trace::Span::transaction(DB_SYSTEM, operation);
+                span.in_scope(|| {
+                    let conn = client.as_mut();
+                    let result = conn
+                        .execute_batch(&operation.to_uppercase())
+                        .map_err(Error::transaction);
+                    // A failed COMMIT (say, SQLITE_BUSY) leaves the
+                    // transaction open, and Drop won't roll it back.
+                    if result.is_err() && !conn.is_autocommit() {
+                        let _ = conn.execute_batch("ROLLBACK");
+                    }
+                    result
+                })
+            })
+            .await
+    }
+
+    pub async fn commit(self) -> Result<(), Error> {
+        self.finish("commit").await
+    }
+
+    pub async fn rollback(self) -> Result<(), Error> {
+        self.finish("rollback").await
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit.  Returns the total number of rows affected.
+    pub async fn insert_many<S>(&mut self, rows: Vec<S>) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<SyncClient> + Send + 'static,
+    {
+        self.client
+            .call(move |client| {
+                let mut rows_affected = 0;
+                for chunk in bulk::chunks::<SyncClient, S>(&rows) {
+                    rows_affected += client.execute(&chunk)?;
+                }
+                Ok(rows_affected)
+            })
+            .await
+    }
+
+    pub async fn query<Q, M>(&mut self, query: Q) -> Result<Vec<Q::Row>, Error>
+    where
+        Q: QueryRows<SyncClient, M> + Send + 'static,
+        Q::Row: Send + 'static,
+    {
+        self.client.query(query).await
+    }
+
+    pub async fn query_one<Q, M>(&mut self, query: Q) -> Result<Q::Row, Error>
+    where
+        Q: QueryRowsOne<SyncClient, M> + Send + 'static,
+        Q::Row: Send + 'static,
+    {
+        self.client.query_one(query).await
+    }
+
+    pub async fn query_opt<Q, M>(&mut self, query: Q) -> Result<Option<Q::Row>, Error>
+    where
+        Q: QueryRowsOne<SyncClient, M> + Send + 'static,
+        Q::Row: Send + 'static,
+    {
+        self.client.query_opt(query).await
+    }
+
+    pub async fn execute<S>(&mut self, statement: S) -> Result<u64, Error>
+    where
+        S: Statement<SyncClient> + Send + 'static,
+    {
+        self.client.execute(statement).await
+    }
+
+    pub async fn execute_returning_id<S>(&mut self, statement: S) -> Result<ExecuteResult, Error>
+    where
+        S: Statement<SyncClient> + Send + 'static,
+    {
+        self.client.execute_returning_id(statement).await
+    }
+
+    pub async fn insert<S>(&mut self, statement: S) -> Result<S::Id, Error>
+    where
+        S: Statement<SyncClient> + Returning + Send + 'static,
+        S::Id: FromColumnIndexed<SyncClient> + Send + 'static,
+    {
+        self.client.insert(statement).await
+    }
+
+    pub async fn prepare<S: StaticQueryText + 'static>(&mut self) -> Result<(), Error> {
+        self.client.prepare::<S>().await
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if !self.done {
+            // Jobs run in order, so this is done before the next query.
+            let _ = self.client.send(|client| {
+                let _ = client.as_mut().execute_batch("ROLLBACK");
+            });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use super::*;
+    use crate::Query;
+
+    #[derive(Statement)]
+    #[aykroyd(
+        text = "CREATE TABLE test_async_rusqlite (id INTEGER PRIMARY KEY AUTOINCREMENT, label TEXT NOT NULL)"
+    )]
+    struct CreateTodos;
+
+    #[derive(Statement)]
+    #[aykroyd(
+        insert_values,
+        text = "INSERT INTO test_async_rusqlite (label) VALUES ($1)"
+    )]
+    struct InsertTodo(String);
+
+    #[derive(Query)]
+    #[aykroyd(row((i32, String)), text = "SELECT id, label FROM test_async_rusqlite")]
+    struct GetAllTodos;
+
+    #[tokio::test]
+    async fn end_to_end_memory() {
+        use crate::combinator::{Count, Exists};
+
+        const TODO_TEXT: &str = "get things done, please!";
+
+        let mut client = Client::open_in_memory().await.unwrap();
+
+        client.execute(CreateTodos).await.unwrap();
+        client.prepare::<GetAllTodos>().await.unwrap();
+        assert!(!client.query_one(Exists::new(GetAllTodos)).await.unwrap());
+
+        client.execute(InsertTodo(TODO_TEXT.into())).await.unwrap();
+
+        let todos = client.query(GetAllTodos).await.unwrap();
+        assert_eq!(1, todos.len());
+        assert_eq!(TODO_TEXT, todos[0].1);
+
+        let txn_text = "rolled back".to_string();
+        {
+            let mut txn = client.transaction().await.unwrap();
+            txn.execute(InsertTodo(txn_text.clone())).await.unwrap();
+            assert_eq!(2, txn.query(GetAllTodos).await.unwrap().len());
+        }
+        assert_eq!(1, client.query(GetAllTodos).await.unwrap().len());
+
+        let mut txn = client.transaction().await.unwrap();
+        let rows = vec![InsertTodo("a".into()), InsertTodo("b".into())];
+        assert_eq!(2, txn.insert_many(rows).await.unwrap());
+        txn.commit().await.unwrap();
+        assert_eq!(3, client.query(GetAllTodos).await.unwrap().len());
+        assert_eq!(3, client.query_one(Count::new(GetAllTodos)).await.unwrap());
+        assert!(client.query_one(Exists::new(GetAllTodos)).await.unwrap());
+
+        let result = client
+            .execute_returning_id(InsertTodo("c".into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            ExecuteResult {
+                rows_affected: 1,
+                last_insert_id: Some(4)
+            },
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_commit_failed() {
+        let mut client = Client::open_in_memory().await.unwrap();
+        client
+            .call(|client| {
+                client
+                    .as_mut()
+                    .execute_batch(
+                        "PRAGMA foreign_keys = ON;
+                        CREATE TABLE parent (id INTEGER PRIMARY KEY);
+                        CREATE TABLE child (
+                            parent_id INTEGER REFERENCES parent (id)
+                                DEFERRABLE INITIALLY DEFERRED
+                        );",
+                    )
+                    .map_err(Error::query)
+            })
+            .await
+            .unwrap();
+
+        // The foreign key is only checked on COMMIT, which fails.
+        let txn = client.transaction().await.unwrap();
+        txn.client
+            .call(|client| {
+                client
+                    .as_mut()
+                    .execute_batch("INSERT INTO child (parent_id) VALUES (1)")
+                    .map_err(Error::query)
+            })
+            .await
+            .unwrap();
+        assert!(txn.commit().await.is_err());
+
+        let autocommit = client
+            .call(|client| Ok(client.as_mut().is_autocommit()))
+            .await
+            .unwrap();
+        assert!(autocommit);
+
+        let txn = client.transaction().await.unwrap();
+        txn.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn transaction_cancelled() {
+        let mut client = Client::open_in_memory().await.unwrap();
+
+        // Hold up the connection's thread, so BEGIN is still waiting.
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        client
+            .send(move |_| {
+                let _ = wait.recv();
+            })
+            .unwrap();
+
+        // Poll once, sending BEGIN, then drop the future.
+        let begin = tokio::time::timeout(std::time::Duration::ZERO, client.transaction());
+        assert!(begin.await.is_err());
+        drop(release);
+
+        let autocommit = client
+            .call(|client| Ok(client.as_mut().is_autocommit()))
+            .await
+            .unwrap();
+        assert!(autocommit);
+    }
+}
@@ -0,0 +1,410 @@
+//! A builder for dynamic queries.
+//!
+//! Most queries are best written as a struct with constant text, but
+//! sometimes the shape of a query depends on runtime input: a search
+//! with optional filters, say.  The [`Builder`] composes a base query
+//! with conditions and parameters, and produces a value that can be
+//! passed to a client's `query` or `execute` methods like any other.
+//!
+//! Write each parameter in the query text as `?`, and the builder
+//! numbers them in the style of the database client.  To write a
+//! literal question mark outside of a quoted string, like PostgreSQL's
+//! `jsonb` operators, use `??`.  Databases that read a bare `?` as a
+//! parameter, like MySQL and SQLite, have no such operator, so the
+//! builder panics rather than write one.
+//!
+//! Each condition is wrapped in parentheses before they're combined
+//! with `AND`, so a condition using `OR` keeps its meaning.
+//!
+//! ```
+//! # use aykroyd::client::{Client, ToParam};
+//! # use aykroyd::FromRow;
+//! use aykroyd::builder::{Builder, BuiltQuery, Placeholder};
+//!
+//! # #[derive(Debug)]
+//! # struct Customer;
+//! # impl<C: Client> FromRow<C> for Customer {
+//! #     fn from_row(_row: &C::Row<'_>) -> Result<Self, aykroyd::Error<C::Error>> {
+//! #         Ok(Customer)
+//! #     }
+//! # }
+//! fn search_customers<'a, C>(
+//!     name: Option<&'a str>,
+//!     min_age: Option<i32>,
+//! ) -> BuiltQuery<'a, C, Customer>
+//! where
+//!     C: Placeholder,
+//!     Customer: FromRow<C>,
+//!     &'a str: ToParam<C>,
+//!     i32: ToParam<C>,
+//!     i64: ToParam<C>,
+//! {
+//!     Builder::new("SELECT id, name, age FROM customers")
+//!         .filter_opt("name = ?", name)
+//!         .filter_opt("age >= ?", min_age)
+//!         .order_by("name")
+//!         .limit(20)
+//!         .build_query()
+//! }
+//! ```
+//!
+//! The same combination of conditions always produces the same query
+//! text, so dynamic queries still make use of the statement cache.
+
+use crate::client::{Client, ToParam};
+use crate::query::{QueryText, ToParams};
+use crate::{FromRow, Query, QueryOne, Statement};
+
+/// How a database client writes query parameters.
+pub trait Placeholder: Client {
+    /// The placeholder for the parameter at `index`, counting from one.
+    fn placeholder(index: usize) -> String;
+
+    /// Is a bare `?` in the query text an ordinary character, rather
+    /// than a parameter?  Only then can the builder write one for `??`.
+    fn literal_question_mark() -> bool {
+        false
+    }
+}
+
+type Param<'a, C> = Box<dyn ToParam<C> + Sync + 'a>;
+
+/// A builder for dynamic queries.
+///
+/// See the [module docs](crate::builder) for more details.
+pub struct Builder<'a, C: Client> {
+    base: String,
+    conditions: Vec<String>,
+    order_by: Vec<String>,
+    limit: Option<Param<'a, C>>,
+    offset: Option<Param<'a, C>>,
+    params: Vec<Param<'a, C>>,
+    condition_params: Vec<Param<'a, C>>,
+}
+
+impl<'a, C: Client> Builder<'a, C> {
+    /// Start a query with the given text, without any `WHERE` clause.
+    pub fn new<S: Into<String>>(base: S) -> Self {
+        Builder {
+            base: base.into(),
+            conditions: vec![],
+            order_by: vec![],
+            limit: None,
+            offset: None,
+            params: vec![],
+            condition_params: vec![],
+        }
+    }
+
+    /// Bind the next parameter of the base query.
+    pub fn bind<T: ToParam<C> + Sync + 'a>(mut self, value: T) -> Self {
+        self.params.push(Box::new(value));
+        self
+    }
+
+    /// Add a condition to the `WHERE` clause.
+    ///
+    /// Conditions are each wrapped in parentheses and combined with
+    /// `AND`.  Use [`bind_where`](Self::bind_where) for any parameters of
+    /// the condition.
+    pub fn and_where<S: Into<String>>(mut self, condition: S) -> Self {
+        self.conditions.push(condition.into());
+        self
+    }
+
+    /// Bind the next parameter of the `WHERE` clause.
+    pub fn bind_where<T: ToParam<C> + Sync + 'a>(mut self, value: T) -> Self {
+        self.condition_params.push(Box::new(value));
+        self
+    }
+
+    /// Add a condition with a single parameter to the `WHERE` clause.
+    pub fn filter<S, T>(self, condition: S, value: T) -> Self
+    where
+        S: Into<String>,
+        T: ToParam<C> + Sync + 'a,
+    {
+        self.and_where(condition).bind_where(value)
+    }
+
+    /// Add a condition with a single parameter, if there is a value.
+    pub fn filter_opt<S, T>(self, condition: S, value: Option<T>) -> Self
+    where
+        S: Into<String>,
+        T: ToParam<C> + Sync + 'a,
+    {
+        match value {
+            Some(value) => self.filter(condition, value),
+            None => self,
+        }
+    }
+
+    /// Add a term to the `ORDER BY` clause.
+    pub fn order_by<S: Into<String>>(mut self, term: S) -> Self {
+        self.order_by.push(term.into());
+        self
+    }
+
+    /// Limit the number of rows returned.
+    pub fn limit(mut self, limit: i64) -> Self
+    where
+        i64: ToParam<C>,
+    {
+        self.limit = Some(Box::new(limit));
+        self
+    }
+
+    /// Skip some rows of the result.
+    pub fn offset(mut self, offset: i64) -> Self
+    where
+        i64: ToParam<C>,
+    {
+        self.offset = Some(Box::new(offset));
+        self
+    }
+}
+
+impl<'a, C: Placeholder> Builder<'a, C> {
+    /// Finish building a statement.
+    ///
+    /// # Panics
+    ///
+    /// If the text has a `??` and the database reads a bare `?` as a
+    /// parameter.
+    pub fn build(self) -> Built<'a, C> {
+        let mut text = self.base;
+        let mut params = self.params;
+
+        if !self.conditions.is_empty() {
+            let conditions: Vec<_> = self
+                .conditions
+                .iter()
+                .map(|condition| format!("({condition})"))
+                .collect();
+            text.push_str(" WHERE ");
+            text.push_str(&conditions.join(" AND "));
+            params.extend(self.condition_params);
+        }
+        if !self.order_by.is_empty() {
+            text.push_str(" ORDER BY ");
+            text.push_str(&self.order_by.join(", "));
+        }
+        if let Some(limit) = self.limit {
+            text.push_str(" LIMIT ?");
+            params.push(limit);
+        }
+        if let Some(offset) = self.offset {
+            text.push_str(" OFFSET ?");
+            params.push(offset);
+        }
+
+        Built {
+            text: number_placeholders::<C>(&text),
+            params,
+        }
+    }
+
+    /// Finish building a query returning rows of type `R`.
+    pub fn build_query<R: FromRow<C>>(self) -> BuiltQuery<'a, C, R> {
+        BuiltQuery {
+            built: self.build(),
+            row: std::marker::PhantomData,
+        }
+    }
+}
+
+/// Replace each `?` outside of a quoted string with a numbered placeholder.
+fn number_placeholders<C: Placeholder>(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut quote = None;
+    let mut index = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                result.push(c);
+            }
+            (Some(q), _) if q == c => {
+                quote = None;
+                result.push(c);
+            }
+            (None, '?') if chars.peek() == Some(&'?') => {
+                assert!(
+                    C::literal_question_mark(),
+                    "`??` in {text:?} would be read as a parameter by {}",
+                    std::any::type_name::<C>(),
+                );
+                chars.next();
+                result.push('?');
+            }
+            (None, '?') => {
+                index += 1;
+                result.push_str(&C::placeholder(index));
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// A statement made by a [`Builder`].
+pub struct Built<'a, C: Client> {
+    text: String,
+    params: Vec<Param<'a, C>>,
+}
+
+impl<'a, C: Client> QueryText for Built<'a, C> {
+    fn query_text(&self) -> String {
+        self.text.clone()
+    }
+}
+
+impl<'a, C: Client> ToParams<C> for Built<'a, C> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        if self.params.is_empty() {
+            None
+        } else {
+            Some(self.params.iter().map(|param| param.to_param()).collect())
+        }
+    }
+}
+
+impl<'a, C: Client> Statement<C> for Built<'a, C> {}
+
+/// A query made by a [`Builder`].
+///
+/// This implements `QueryOne` as well as `Query`, it's up to you
+/// to be sure the query returns at most one row if you use it that way.
+pub struct BuiltQuery<'a, C: Client, R> {
+    built: Built<'a, C>,
+    row: std::marker::PhantomData<fn() -> R>,
+}
+
+impl<'a, C: Client, R> QueryText for BuiltQuery<'a, C, R> {
+    fn query_text(&self) -> String {
+        self.built.query_text()
+    }
+}
+
+impl<'a, C: Client, R> ToParams<C> for BuiltQuery<'a, C, R> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.built.to_params()
+    }
+}
+
+impl<'a, C: Client, R: FromRow<C>> Query<C> for BuiltQuery<'a, C, R> {
+    type Row = R;
+}
+
+impl<'a, C: Client, R: FromRow<C>> QueryOne<C> for BuiltQuery<'a, C, R> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::sync_client::TestClient;
+
+    fn render(built: &Built<'_, TestClient>) -> (String, Vec<String>) {
+        let params = built
+            .to_params()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|param| param.to_param())
+            .collect();
+        (built.query_text(), params)
+    }
+
+    #[test]
+    fn no_conditions() {
+        let built = Builder::<TestClient>::new("SELECT id FROM customers").build();
+        assert_eq!(("SELECT id FROM customers".into(), vec![]), render(&built));
+    }
+
+    #[test]
+    fn conditions() {
+        let name: Option<String> = None;
+        let built = Builder::<TestClient>::new("SELECT id FROM customers JOIN orders ON total > ?")
+            .bind(100)
+            .filter("age >= ?", 21)
+            .filter_opt("name = ?", name)
+            .and_where("city IN (?, ?)")
+            .bind_where(String::from("Paris"))
+            .bind_where(String::from("Rome"))
+            .order_by("age DESC")
+            .order_by("id")
+            .limit(10)
+            .offset(20)
+            .build();
+
+        assert_eq!(
+            (
+                "SELECT id FROM customers JOIN orders ON total > $1 \
+                    WHERE (age >= $2) AND (city IN ($3, $4)) ORDER BY age DESC, id \
+                    LIMIT $5 OFFSET $6"
+                    .into(),
+                vec![
+                    "100".into(),
+                    "21".into(),
+                    "Paris".into(),
+                    "Rome".into(),
+                    "10".into(),
+                    "20".into(),
+                ],
+            ),
+            render(&built),
+        );
+    }
+
+    #[test]
+    fn quoted() {
+        let built = Builder::<TestClient>::new("SELECT '?', \"?\", data ?? 'key' FROM t")
+            .filter("id = ?", 1)
+            .build();
+        assert_eq!(
+            "SELECT '?', \"?\", data ? 'key' FROM t WHERE (id = $1)",
+            built.query_text(),
+        );
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    #[should_panic(expected = "would be read as a parameter")]
+    fn escaped_question_mark() {
+        Builder::<crate::rusqlite::Client>::new("SELECT data ?? 'key' FROM t").build();
+    }
+
+    #[test]
+    fn or_condition() {
+        let built = Builder::<TestClient>::new("SELECT id FROM customers")
+            .and_where("age < ? OR age > ?")
+            .bind_where(18)
+            .bind_where(65)
+            .filter("city = ?", String::from("Paris"))
+            .build();
+        assert_eq!(
+            "SELECT id FROM customers WHERE (age < $1 OR age > $2) AND (city = $3)",
+            built.query_text(),
+        );
+    }
+
+    #[test]
+    fn query() {
+        let query = Builder::<TestClient>::new("SELECT name FROM customers")
+            .filter("id = ?", 3)
+            .build_query::<(String,)>();
+
+        let mut client = TestClient::new();
+        client.push_query_result(Ok(vec![]));
+        client.query(&query).unwrap();
+
+        let records = client.records();
+        assert_eq!(1, records.len());
+        assert_eq!(
+            "SELECT name FROM customers WHERE (id = $1)",
+            records[0].text
+        );
+        assert_eq!(Some(vec!["3".into()]), records[0].params);
+    }
+}
@@ -0,0 +1,183 @@
+//! Bulk inserts.
+//!
+//! Inserting many rows one statement at a time takes a round trip to
+//! the database for each row.  Instead, derive `Statement` with the
+//! `insert_values` attribute, and pass a slice of rows to a client's
+//! `insert_many` method.  The `VALUES` tuple is repeated to insert as
+//! many rows per statement as the database's parameter limit allows,
+//! and the statements are run in a single transaction.
+#![cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::Statement;
+#[derive(Statement)]
+#[aykroyd(insert_values, text = "
+    INSERT INTO customers (first_name, last_name) VALUES ($1, $2)
+")]
+struct InsertCustomer<'a> {
+    first_name: &'a str,
+    last_name: &'a str,
+}
+```
+"##
+)]
+//!
+//! ```ignore
+//! let customers = vec![
+//!     InsertCustomer { first_name: "Luke", last_name: "Skywalker" },
+//!     InsertCustomer { first_name: "Leia", last_name: "Organa" },
+//! ];
+//! let rows_affected = client.insert_many(&customers)?;
+//! ```
+//!
+//! Only the `VALUES` tuple may have parameters, since the rest of the
+//! statement is shared by every row.
+
+use crate::builder::Placeholder;
+use crate::client::Client;
+use crate::list::{pieces, Piece};
+use crate::query::{QueryText, ToParams};
+use crate::Statement;
+
+/// An insert statement with a `VALUES` tuple that can be repeated.
+///
+/// Don't implement this trait directly, use the `insert_values`
+/// attribute of the `Statement` derive macro.
+pub trait InsertValues {
+    /// The text before the tuple, ending with `VALUES`.
+    const PREFIX: &'static str;
+    /// The tuple of values for one row.
+    const TUPLE: &'static str;
+    /// The text after the tuple.
+    const SUFFIX: &'static str;
+    /// The number of parameters for each row.
+    const PARAMS: usize;
+}
+
+/// The maximum number of parameters in one statement.
+pub trait ParamLimit: Placeholder {
+    fn param_limit() -> usize;
+}
+
+/// A statement inserting a chunk of rows.
+pub struct Chunk<'a, S> {
+    text: String,
+    rows: &'a [S],
+}
+
+impl<'a, S> QueryText for Chunk<'a, S> {
+    fn query_text(&self) -> String {
+        self.text.clone()
+    }
+}
+
+impl<'a, C: Client, S: ToParams<C>> ToParams<C> for Chunk<'a, S> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        let params: Vec<_> = self
+            .rows
+            .iter()
+            .flat_map(|row| row.to_params().unwrap_or_default())
+            .collect();
+        if params.is_empty() {
+            None
+        } else {
+            Some(params)
+        }
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        let mut params = vec![];
+        for row in self.rows {
+            params.extend(row.redacted_params()?);
+        }
+        Some(params)
+    }
+}
+
+impl<'a, C: Client, S: ToParams<C>> Statement<C> for Chunk<'a, S> {}
+
+/// Split the rows into statements that fit within the parameter limit.
+pub fn chunks<C, S>(rows: &[S]) -> impl Iterator<Item = Chunk<'_, S>>
+where
+    C: ParamLimit,
+    S: InsertValues,
+{
+    let size = match S::PARAMS {
+        0 => rows.len(),
+        params => C::param_limit() / params,
+    };
+    rows.chunks(size.max(1)).map(|rows| Chunk {
+        text: values_text::<C, S>(rows.len()),
+        rows,
+    })
+}
+
+/// The statement text to insert a number of rows.
+fn values_text<C: Placeholder, S: InsertValues>(rows: usize) -> String {
+    let mut text = String::from(S::PREFIX);
+    for row in 0..rows {
+        if row > 0 {
+            text.push_str(", ");
+        }
+        for piece in pieces(S::TUPLE) {
+            match piece {
+                Piece::Text(tuple) => text.push_str(tuple),
+                Piece::Param(param) => {
+                    text.push_str(&C::placeholder(row * S::PARAMS + param.index));
+                }
+            }
+        }
+    }
+    text.push_str(S::SUFFIX);
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::ToParam;
+    use crate::test::sync_client::TestClient;
+
+    struct InsertPair(i32, i32);
+
+    impl InsertValues for InsertPair {
+        const PREFIX: &'static str = "INSERT INTO pairs (a, b) VALUES ";
+        const TUPLE: &'static str = "($1, lower($2))";
+        const SUFFIX: &'static str = " ON CONFLICT DO NOTHING";
+        const PARAMS: usize = 2;
+    }
+
+    impl ToParams<TestClient> for InsertPair {
+        fn to_params(&self) -> Option<Vec<<TestClient as Client>::Param<'_>>> {
+            Some(vec![
+                ToParam::<TestClient>::to_param(&self.0),
+                ToParam::<TestClient>::to_param(&self.1),
+            ])
+        }
+    }
+
+    #[test]
+    fn chunked() {
+        let rows: Vec<_> = (0..5).map(|i| InsertPair(i, i * 10)).collect();
+        let chunks: Vec<_> = chunks::<TestClient, _>(&rows).collect();
+
+        let texts: Vec<_> = chunks.iter().map(QueryText::query_text).collect();
+        assert_eq!(
+            vec![
+                "INSERT INTO pairs (a, b) VALUES ($1, lower($2)), ($3, lower($4)) ON CONFLICT DO NOTHING",
+                "INSERT INTO pairs (a, b) VALUES ($1, lower($2)), ($3, lower($4)) ON CONFLICT DO NOTHING",
+                "INSERT INTO pairs (a, b) VALUES ($1, lower($2)) ON CONFLICT DO NOTHING",
+            ],
+            texts,
+        );
+
+        let params: Vec<String> = ToParams::<TestClient>::to_params(&chunks[1])
+            .unwrap()
+            .into_iter()
+            .map(|param| param.to_param())
+            .collect();
+        assert_eq!(vec!["2", "20", "3", "30"], params);
+    }
+}
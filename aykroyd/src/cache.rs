@@ -0,0 +1,78 @@
+//! A bounded cache of prepared statements.
+//!
+//! Query text isn't always constant: each length of a list parameter,
+//! and each query from the [`builder`](crate::builder), has its own text.
+//! So that these can't grow the cache without limit, the least recently
+//! used statement is dropped (and closed) to make room for a new one.
+
+use std::collections::HashMap;
+
+/// The most statements to keep prepared on one connection.
+pub(crate) const CAPACITY: usize = 256;
+
+pub(crate) struct Statements<S> {
+    statements: HashMap<String, (S, u64)>,
+    clock: u64,
+}
+
+impl<S> Default for Statements<S> {
+    fn default() -> Self {
+        Statements {
+            statements: HashMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<S: Clone> Statements<S> {
+    /// The statement prepared for the text, if it's cached.
+    pub(crate) fn get(&mut self, text: &str) -> Option<S> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.statements.get_mut(text).map(|(statement, used)| {
+            *used = clock;
+            statement.clone()
+        })
+    }
+
+    /// Cache a statement, dropping the least recently used if full.
+    pub(crate) fn insert(&mut self, text: String, statement: S) {
+        if self.statements.len() >= CAPACITY && !self.statements.contains_key(&text) {
+            let oldest = self
+                .statements
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(text, _)| text.clone());
+            if let Some(oldest) = oldest {
+                self.statements.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.statements.insert(text, (statement, self.clock));
+    }
+
+    #[cfg(test)]
+    pub(crate) fn texts(&self) -> impl Iterator<Item = &String> {
+        self.statements.keys()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bounded() {
+        let mut statements = Statements::default();
+        for index in 0..CAPACITY {
+            statements.insert(format!("SELECT {index}"), index);
+        }
+        assert_eq!(Some(0), statements.get("SELECT 0"));
+
+        statements.insert("SELECT 'new'".into(), CAPACITY);
+        assert_eq!(CAPACITY, statements.texts().count());
+        assert_eq!(Some(0), statements.get("SELECT 0"));
+        assert_eq!(None, statements.get("SELECT 1"));
+        assert_eq!(Some(CAPACITY), statements.get("SELECT 'new'"));
+    }
+}
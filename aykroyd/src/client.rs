@@ -30,6 +30,10 @@ pub trait Client: Sized {
 
     /// The type of database errors.
     type Error;
+
+    /// Whether a [list parameter](crate::list) is bound as a single
+    /// array, rather than expanded to a parameter for each item.
+    const ARRAY_PARAMS: bool = false;
 }
 
 /// A type that can be retrieved from a database column by index.
@@ -56,6 +60,21 @@ pub trait ToParam<C: Client> {
     fn to_param(&self) -> C::Param<'_>;
 }
 
+/// The outcome of a statement, with the id of an inserted row.
+///
+/// This is returned by `execute_returning_id` on the MySQL and SQLite
+/// clients, which track the last id generated on the connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecuteResult {
+    /// The number of rows affected.
+    pub rows_affected: u64,
+
+    /// The id generated for the last row inserted: an `AUTO_INCREMENT`
+    /// value on MySQL, or the rowid on SQLite.  This is `None` if no id
+    /// has been generated on the connection.
+    pub last_insert_id: Option<i64>,
+}
+
 pub mod specification {
     //! The `aykroyd` client specification.
     //!
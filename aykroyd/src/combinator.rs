@@ -14,7 +14,8 @@ struct Tree {
     name: String,
 }
 ```
-"##)]
+"##
+)]
 //!
 //! Then you might have a few different ways you
 //! could query for your data.
@@ -39,7 +40,8 @@ struct GetTreesOver(f32);
 ")]
 struct GetTreesNamed<'a>(&'a str);
 ```
-"##)]
+"##
+)]
 //!
 //! You might then find yourself in a situation where
 //! you want to hold on to a `Tree` query, but you don't
@@ -106,14 +108,44 @@ struct GetTreesNamed<'a>(&'a str);
 //! // Run a different query returning trees named "Bob".
 //! query_and_log(&mut client, Either::Right(GetTreesNamed("Bob")));
 //! ```
+//!
+//! For more than two alternatives, rather than nesting `Either`, you
+//! can derive `Query` (or `QueryOne` or `Statement`) on an enum.  Each
+//! variant has its own query text and parameter fields, and they all
+//! share the `row` type.
+#![cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::{FromRow, Query};
+# #[derive(FromRow)] struct Tree;
+#[derive(Query)]
+#[aykroyd(row(Tree))]
+enum GetTrees<'a> {
+    #[aykroyd(text = "SELECT name, height, leaves FROM trees WHERE height > $1")]
+    Over(f32),
+    #[aykroyd(text = "SELECT name, height, leaves FROM trees WHERE name = $1")]
+    Named(&'a str),
+    #[aykroyd(text = "SELECT name, height, leaves FROM trees")]
+    All,
+}
+```
+"##
+)]
 
-use crate::client::Client;
-use crate::query::{QueryText, ToParams};
-use crate::{FromRow, Query, QueryOne, Statement};
+use crate::builder::Placeholder;
+use crate::client::{Client, FromColumnIndexed, ToParam};
+use crate::debug::{Quote, ToLiteral, ToLiterals};
+use crate::error::Error;
+use crate::plan::{Dialect, PlanRow};
+use crate::query::{QueryText, Returning, ToParams};
+use crate::{FromRow, Query, QueryOne, QueryRows, QueryRowsOne, Statement};
 
 /// A query that could be one of two options.
 ///
-/// See the [module docs](crate::combinator) for more details.
+/// See the [module docs](crate::combinator) for more details,
+/// including deriving queries on enums for more options.
 pub enum Either<A, B> {
     Left(A),
     Right(B),
@@ -126,6 +158,13 @@ impl<A: QueryText, B: QueryText> QueryText for Either<A, B> {
             Either::Right(b) => b.query_text(),
         }
     }
+
+    fn query_text_for<C: Client>(&self) -> String {
+        match self {
+            Either::Left(a) => a.query_text_for::<C>(),
+            Either::Right(b) => b.query_text_for::<C>(),
+        }
+    }
 }
 
 impl<C, A, B> ToParams<C> for Either<A, B>
@@ -140,6 +179,33 @@ where
             Either::Right(b) => b.to_params(),
         }
     }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        match self {
+            Either::Left(a) => a.redacted_params(),
+            Either::Right(b) => b.redacted_params(),
+        }
+    }
+}
+
+impl<C, A, B> ToLiterals<C> for Either<A, B>
+where
+    C: Quote,
+    A: ToLiterals<C>,
+    B: ToLiterals<C>,
+{
+    fn to_literals(&self) -> Vec<String> {
+        match self {
+            Either::Left(a) => a.to_literals(),
+            Either::Right(b) => b.to_literals(),
+        }
+    }
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        match self {
+            Either::Left(a) => a.to_literals_unredacted(),
+            Either::Right(b) => b.to_literals_unredacted(),
+        }
+    }
 }
 
 impl<C, A, B> Statement<C> for Either<A, B>
@@ -158,6 +224,8 @@ where
     B: Query<C, Row = R>,
 {
     type Row = R;
+
+    const PRIMARY: bool = A::PRIMARY || B::PRIMARY;
 }
 
 impl<C, R, A, B> QueryOne<C> for Either<A, B>
@@ -169,12 +237,1153 @@ where
 {
 }
 
+/// The [`QueryRows`] parameter for [`MapRow`] and [`TryMapRow`], given
+/// that of the inner query.
+pub struct Mapped<M>(std::marker::PhantomData<fn() -> M>);
+
+/// A query with its rows converted by a function.
+///
+/// This keeps the text and parameters of the inner query, passing each
+/// row through the function, so you can produce a domain type without
+/// defining a new `FromRow` implementation.
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::query::{StaticQueryText, ToParams};
+/// # use aykroyd::{FromRow, Query};
+/// # struct GetTreeNames;
+/// # impl StaticQueryText for GetTreeNames {
+/// #     const QUERY_TEXT: &'static str = "SELECT name FROM trees";
+/// # }
+/// # impl<C: Client> ToParams<C> for GetTreeNames {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// # impl<C: Client> Query<C> for GetTreeNames where (String,): FromRow<C> {
+/// #     type Row = (String,);
+/// # }
+/// use aykroyd::combinator::MapRow;
+///
+/// struct TreeName(String);
+///
+/// let query = MapRow::new(GetTreeNames, |(name,)| TreeName(name));
+/// ```
+///
+/// For a conversion that can fail, use [`TryMapRow`].
+#[derive(Debug, Clone, Copy)]
+pub struct MapRow<Q, F> {
+    query: Q,
+    f: F,
+}
+
+impl<Q, F> MapRow<Q, F> {
+    /// Map the rows of `query` with the function `f`.
+    pub fn new(query: Q, f: F) -> Self {
+        MapRow { query, f }
+    }
+
+    /// Get the inner query back.
+    pub fn into_inner(self) -> Q {
+        self.query
+    }
+}
+
+impl<Q: QueryText, F> QueryText for MapRow<Q, F> {
+    fn query_text(&self) -> String {
+        self.query.query_text()
+    }
+
+    fn query_text_for<C: Client>(&self) -> String {
+        self.query.query_text_for::<C>()
+    }
+}
+
+impl<C, Q, F> ToParams<C> for MapRow<Q, F>
+where
+    C: Client,
+    Q: ToParams<C>,
+    F: Sync,
+{
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.query.to_params()
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        self.query.redacted_params()
+    }
+}
+
+impl<C: Quote, Q: ToLiterals<C>, F> ToLiterals<C> for MapRow<Q, F> {
+    fn to_literals(&self) -> Vec<String> {
+        self.query.to_literals()
+    }
+
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        self.query.to_literals_unredacted()
+    }
+}
+
+impl<C, M, Q, F, R> QueryRows<C, Mapped<M>> for MapRow<Q, F>
+where
+    C: Client,
+    Q: QueryRows<C, M>,
+    F: Fn(Q::Row) -> R + Sync,
+{
+    type Row = R;
+
+    const PRIMARY: bool = Q::PRIMARY;
+
+    fn map_row(&self, row: &C::Row<'_>) -> Result<R, Error<C::Error>> {
+        self.query.map_row(row).map(&self.f)
+    }
+}
+
+impl<C, M, Q, F, R> QueryRowsOne<C, Mapped<M>> for MapRow<Q, F>
+where
+    C: Client,
+    Q: QueryRowsOne<C, M>,
+    F: Fn(Q::Row) -> R + Sync,
+{
+}
+
+/// A query with its rows converted by a function that can fail.
+///
+/// Like [`MapRow`], except the function returns a `Result`, so it
+/// can reject rows with an error such as
+/// [`Error::from_column_str`](crate::Error::from_column_str).
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::query::{StaticQueryText, ToParams};
+/// # use aykroyd::{FromRow, Query};
+/// # struct GetTreeHeights;
+/// # impl StaticQueryText for GetTreeHeights {
+/// #     const QUERY_TEXT: &'static str = "SELECT height FROM trees";
+/// # }
+/// # impl<C: Client> ToParams<C> for GetTreeHeights {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// # impl<C: Client> Query<C> for GetTreeHeights where (f32,): FromRow<C> {
+/// #     type Row = (f32,);
+/// # }
+/// use aykroyd::combinator::{Mapped, TryMapRow};
+/// use aykroyd::{Error, FromRows, QueryRows};
+///
+/// struct Height(f32);
+///
+/// fn get_tree_heights<C: Client>() -> impl QueryRows<C, Mapped<FromRows>, Row = Height>
+/// where
+///     (f32,): FromRow<C>,
+/// {
+///     TryMapRow::new(GetTreeHeights, |(height,): (f32,)| {
+///         if height < 0.0 {
+///             return Err(Error::from_column_str("negative height", None));
+///         }
+///         Ok(Height(height))
+///     })
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TryMapRow<Q, F> {
+    query: Q,
+    f: F,
+}
+
+impl<Q, F> TryMapRow<Q, F> {
+    /// Map the rows of `query` with the fallible function `f`.
+    pub fn new(query: Q, f: F) -> Self {
+        TryMapRow { query, f }
+    }
+
+    /// Get the inner query back.
+    pub fn into_inner(self) -> Q {
+        self.query
+    }
+}
+
+impl<Q: QueryText, F> QueryText for TryMapRow<Q, F> {
+    fn query_text(&self) -> String {
+        self.query.query_text()
+    }
+
+    fn query_text_for<C: Client>(&self) -> String {
+        self.query.query_text_for::<C>()
+    }
+}
+
+impl<C, Q, F> ToParams<C> for TryMapRow<Q, F>
+where
+    C: Client,
+    Q: ToParams<C>,
+    F: Sync,
+{
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.query.to_params()
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        self.query.redacted_params()
+    }
+}
+
+impl<C: Quote, Q: ToLiterals<C>, F> ToLiterals<C> for TryMapRow<Q, F> {
+    fn to_literals(&self) -> Vec<String> {
+        self.query.to_literals()
+    }
+
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        self.query.to_literals_unredacted()
+    }
+}
+
+impl<C, M, Q, F, R> QueryRows<C, Mapped<M>> for TryMapRow<Q, F>
+where
+    C: Client,
+    Q: QueryRows<C, M>,
+    F: Fn(Q::Row) -> Result<R, Error<C::Error>> + Sync,
+{
+    type Row = R;
+
+    const PRIMARY: bool = Q::PRIMARY;
+
+    fn map_row(&self, row: &C::Row<'_>) -> Result<R, Error<C::Error>> {
+        self.query.map_row(row).and_then(&self.f)
+    }
+}
+
+impl<C, M, Q, F, R> QueryRowsOne<C, Mapped<M>> for TryMapRow<Q, F>
+where
+    C: Client,
+    Q: QueryRowsOne<C, M>,
+    F: Fn(Q::Row) -> Result<R, Error<C::Error>> + Sync,
+{
+}
+
+/// One page of results, with the cursor for the next page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T, N> {
+    /// The rows on this page.
+    pub items: Vec<T>,
+    /// Where the next page starts, if there is one.
+    pub next: Option<N>,
+}
+
+fn param_count<C: Client, Q: ToParams<C>>(query: &Q) -> usize {
+    query.to_params().map_or(0, |params| params.len())
+}
+
+/// A query paged with `LIMIT` and `OFFSET`.
+///
+/// The inner query is wrapped in a subquery, so it shouldn't have its
+/// own limit.  The rows are sorted by the `order` terms on the outer
+/// query, since databases needn't keep the order of a subquery.  These
+/// must name result columns of the inner query, and should identify a
+/// row uniquely, or rows that sort the same may move between pages.
+/// The extra parameters come after those of the inner query, which is
+/// why this is specific to a client type `C`.
+///
+/// One more row than the limit is requested, so that [`page`](Self::page)
+/// can tell whether there is a next page.
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::query::{StaticQueryText, ToParams};
+/// # use aykroyd::{FromRow, Query};
+/// # struct GetTrees;
+/// # impl StaticQueryText for GetTrees {
+/// #     const QUERY_TEXT: &'static str = "SELECT name FROM trees";
+/// # }
+/// # impl<C: Client> ToParams<C> for GetTrees {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// # impl<C: Client> Query<C> for GetTrees where (String,): FromRow<C> {
+/// #     type Row = (String,);
+/// # }
+/// # struct DbConn;
+/// # impl DbConn {
+/// #     fn query<Q>(&mut self, _: &Q) -> Result<Vec<(String,)>, String> {
+/// #         Ok(vec![])
+/// #     }
+/// # }
+/// # let mut client = DbConn;
+/// # struct Db;
+/// use aykroyd::combinator::Paginate;
+///
+/// let query = Paginate::<_, Db>::new(GetTrees, &["name"], 20).offset(40);
+/// let rows = client.query(&query)?;
+/// let page = query.page(rows);
+/// # Ok::<(), String>(())
+/// ```
+pub struct Paginate<Q, C> {
+    query: Q,
+    order: Vec<String>,
+    limit: i64,
+    fetch: i64,
+    offset: i64,
+    client: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<Q, C> Paginate<Q, C> {
+    /// Page `query` sorted by the `order` terms, such as `"name"` or
+    /// `"height DESC"`, with at most `limit` rows per page.
+    pub fn new(query: Q, order: &[&str], limit: i64) -> Self {
+        Paginate {
+            query,
+            order: order.iter().map(|term| term.to_string()).collect(),
+            limit,
+            fetch: limit.saturating_add(1),
+            offset: 0,
+            client: std::marker::PhantomData,
+        }
+    }
+
+    /// Start the page after skipping `offset` rows.
+    pub fn offset(mut self, offset: i64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Split the rows into a page and the offset of the next page.
+    pub fn page<R>(&self, mut rows: Vec<R>) -> Page<R, i64> {
+        let limit = usize::try_from(self.limit).unwrap_or_default();
+        let next = if rows.len() > limit {
+            rows.truncate(limit);
+            Some(self.offset.saturating_add(self.limit))
+        } else {
+            None
+        };
+        Page { items: rows, next }
+    }
+}
+
+impl<Q, C> QueryText for Paginate<Q, C>
+where
+    C: Placeholder,
+    Q: QueryText + ToParams<C>,
+{
+    fn query_text(&self) -> String {
+        let count = param_count(&self.query);
+        format!(
+            "SELECT * FROM ({}) AS page ORDER BY {} LIMIT {} OFFSET {}",
+            self.query.query_text_for::<C>(),
+            self.order.join(", "),
+            C::placeholder(count + 1),
+            C::placeholder(count + 2),
+        )
+    }
+}
+
+impl<Q, C> ToParams<C> for Paginate<Q, C>
+where
+    C: Client,
+    Q: ToParams<C>,
+    i64: ToParam<C>,
+{
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        let mut params = self.query.to_params().unwrap_or_default();
+        params.push(self.fetch.to_param());
+        params.push(self.offset.to_param());
+        Some(params)
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        let mut params = self.query.redacted_params()?;
+        params.push(self.fetch.to_string());
+        params.push(self.offset.to_string());
+        Some(params)
+    }
+}
+
+impl<Q, C> ToLiterals<C> for Paginate<Q, C>
+where
+    C: Quote,
+    Q: ToLiterals<C>,
+    i64: ToLiteral<C>,
+{
+    fn to_literals(&self) -> Vec<String> {
+        let mut literals = self.query.to_literals();
+        literals.push(self.fetch.to_literal());
+        literals.push(self.offset.to_literal());
+        literals
+    }
+
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        let mut literals = self.query.to_literals_unredacted();
+        literals.push(self.fetch.to_literal());
+        literals.push(self.offset.to_literal());
+        literals
+    }
+}
+
+impl<Q, C> Query<C> for Paginate<Q, C>
+where
+    C: Placeholder,
+    Q: Query<C>,
+    i64: ToParam<C>,
+{
+    type Row = Q::Row;
+
+    const PRIMARY: bool = Q::PRIMARY;
+}
+
+/// The value of the sort key for [`Keyset`] paging.
+///
+/// This is implemented for tuples of parameters, one for each
+/// key column.
+pub trait KeysetKey<C: Client>: Sync {
+    fn to_params(&self) -> Vec<C::Param<'_>>;
+
+    /// Each value with `Debug`, for diagnostics.
+    fn describe(&self) -> Vec<String>;
+}
+
+macro_rules! impl_tuple_keyset_key {
+    (
+        $(
+            $name:ident $index:tt
+        ),+
+        $(,)?
+    ) => {
+        impl<
+            C,
+            $(
+                $name,
+            )+
+        > KeysetKey<C> for ($($name,)+)
+        where
+            C: Client,
+            $(
+                $name: ToParam<C> + std::fmt::Debug + Sync,
+            )+
+        {
+            fn to_params(&self) -> Vec<C::Param<'_>> {
+                vec![$(self.$index.to_param(),)+]
+            }
+
+            fn describe(&self) -> Vec<String> {
+                vec![$(format!("{:?}", self.$index),)+]
+            }
+        }
+
+        impl<
+            C,
+            $(
+                $name,
+            )+
+        > ToLiterals<C> for ($($name,)+)
+        where
+            C: Quote,
+            $(
+                $name: ToLiteral<C>,
+            )+
+        {
+            fn to_literals(&self) -> Vec<String> {
+                vec![$(self.$index.to_literal(),)+]
+            }
+        }
+    };
+}
+
+impl_tuple_keyset_key!(T0 0);
+impl_tuple_keyset_key!(T0 0, T1 1);
+impl_tuple_keyset_key!(T0 0, T1 1, T2 2);
+impl_tuple_keyset_key!(T0 0, T1 1, T2 2, T3 3);
+
+/// A query paged by seeking past the last key seen.
+///
+/// Rows are sorted by the key columns, and each page starts after the
+/// key of the last row of the page before, using a row comparison
+/// `WHERE (a, b) > ($1, $2)`.  Unlike [`Paginate`], the database
+/// doesn't need to skip over earlier rows, and rows inserted while
+/// paging don't shift the pages.
+///
+/// The inner query is wrapped in a subquery, so the key columns must be
+/// among its result columns, and it shouldn't have its own limit.
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::query::{StaticQueryText, ToParams};
+/// # use aykroyd::{FromRow, Query};
+/// # struct GetTrees;
+/// # impl StaticQueryText for GetTrees {
+/// #     const QUERY_TEXT: &'static str = "SELECT id, name FROM trees";
+/// # }
+/// # impl<C: Client> ToParams<C> for GetTrees {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// # impl<C: Client> Query<C> for GetTrees where (i32, String): FromRow<C> {
+/// #     type Row = (i32, String);
+/// # }
+/// # struct DbConn;
+/// # impl DbConn {
+/// #     fn query<Q>(&mut self, _: &Q) -> Result<Vec<(i32, String)>, String> {
+/// #         Ok(vec![])
+/// #     }
+/// # }
+/// # let mut client = DbConn;
+/// # struct Db;
+/// # let last_id = None;
+/// use aykroyd::combinator::Keyset;
+///
+/// let mut query = Keyset::<_, (i32,), Db>::new(GetTrees, &["id"], 20);
+/// if let Some(last_id) = last_id {
+///     query = query.after((last_id,));
+/// }
+///
+/// let rows = client.query(&query)?;
+/// let page = query.page(rows, |(id, _name)| (*id,));
+/// # Ok::<(), String>(())
+/// ```
+pub struct Keyset<Q, K, C> {
+    query: Q,
+    columns: Vec<String>,
+    after: Option<K>,
+    descending: bool,
+    limit: i64,
+    fetch: i64,
+    client: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<Q, K, C> Keyset<Q, K, C> {
+    /// Page `query` by the key `columns`, with at most `limit` rows per page.
+    pub fn new(query: Q, columns: &[&str], limit: i64) -> Self {
+        Keyset {
+            query,
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            after: None,
+            descending: false,
+            limit,
+            fetch: limit.saturating_add(1),
+            client: std::marker::PhantomData,
+        }
+    }
+
+    /// Start the page after the row with this key.
+    pub fn after(mut self, key: K) -> Self {
+        self.after = Some(key);
+        self
+    }
+
+    /// Page in descending key order.
+    pub fn descending(mut self) -> Self {
+        self.descending = true;
+        self
+    }
+
+    /// Split the rows into a page and the key to start the next page.
+    pub fn page<R, F>(&self, mut rows: Vec<R>, key: F) -> Page<R, K>
+    where
+        F: FnOnce(&R) -> K,
+    {
+        let limit = usize::try_from(self.limit).unwrap_or_default();
+        let next = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(key)
+        } else {
+            None
+        };
+        Page { items: rows, next }
+    }
+}
+
+impl<Q, K, C> QueryText for Keyset<Q, K, C>
+where
+    C: Placeholder,
+    Q: QueryText + ToParams<C>,
+    K: KeysetKey<C>,
+{
+    fn query_text(&self) -> String {
+        let mut index = param_count(&self.query);
+        let columns = self.columns.join(", ");
+
+        let mut text = format!(
+            "SELECT * FROM ({}) AS page",
+            self.query.query_text_for::<C>()
+        );
+        if let Some(after) = &self.after {
+            let placeholders: Vec<_> = after
+                .to_params()
+                .iter()
+                .map(|_| {
+                    index += 1;
+                    C::placeholder(index)
+                })
+                .collect();
+            let op = if self.descending { "<" } else { ">" };
+            text.push_str(&format!(
+                " WHERE ({columns}) {op} ({})",
+                placeholders.join(", ")
+            ));
+        }
+
+        let order: Vec<_> = self
+            .columns
+            .iter()
+            .map(|column| match self.descending {
+                true => format!("{column} DESC"),
+                false => column.clone(),
+            })
+            .collect();
+        text.push_str(&format!(
+            " ORDER BY {} LIMIT {}",
+            order.join(", "),
+            C::placeholder(index + 1),
+        ));
+        text
+    }
+}
+
+impl<Q, K, C> ToParams<C> for Keyset<Q, K, C>
+where
+    C: Client,
+    Q: ToParams<C>,
+    K: KeysetKey<C>,
+    i64: ToParam<C>,
+{
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        let mut params = self.query.to_params().unwrap_or_default();
+        if let Some(after) = &self.after {
+            params.extend(after.to_params());
+        }
+        params.push(self.fetch.to_param());
+        Some(params)
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        let mut params = self.query.redacted_params()?;
+        if let Some(after) = &self.after {
+            params.extend(after.describe());
+        }
+        params.push(self.fetch.to_string());
+        Some(params)
+    }
+}
+
+impl<Q, K, C> ToLiterals<C> for Keyset<Q, K, C>
+where
+    C: Quote,
+    Q: ToLiterals<C>,
+    K: ToLiterals<C>,
+    i64: ToLiteral<C>,
+{
+    fn to_literals(&self) -> Vec<String> {
+        let mut literals = self.query.to_literals();
+        if let Some(after) = &self.after {
+            literals.extend(after.to_literals());
+        }
+        literals.push(self.fetch.to_literal());
+        literals
+    }
+
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        let mut literals = self.query.to_literals_unredacted();
+        if let Some(after) = &self.after {
+            literals.extend(after.to_literals());
+        }
+        literals.push(self.fetch.to_literal());
+        literals
+    }
+}
+
+impl<Q, K, C> Query<C> for Keyset<Q, K, C>
+where
+    C: Placeholder,
+    Q: Query<C>,
+    K: KeysetKey<C>,
+    i64: ToParam<C>,
+{
+    type Row = Q::Row;
+
+    const PRIMARY: bool = Q::PRIMARY;
+}
+
+/// The [`QueryRows`] parameter for the combinators that decode their
+/// own rows: [`ExplainAnalyze`] and [`ReturningId`], and [`Explain`] of
+/// a statement.
+pub enum Decoded {}
+
+/// The number of rows a query would return.
+///
+/// This wraps the inner query as a subquery, `SELECT count(*) FROM (...)`,
+/// with the same parameters.  Every supported database accepts this
+/// text, so unlike [`Explain`] it doesn't depend on the client.
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::query::{StaticQueryText, ToParams};
+/// # struct GetTrees;
+/// # impl StaticQueryText for GetTrees {
+/// #     const QUERY_TEXT: &'static str = "SELECT name FROM trees";
+/// # }
+/// # impl<C: Client> ToParams<C> for GetTrees {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// use aykroyd::combinator::Count;
+/// use aykroyd::query::QueryText;
+///
+/// let query = Count::new(GetTrees);
+/// assert_eq!(
+///     "SELECT count(*) FROM (SELECT name FROM trees) AS sub",
+///     query.query_text(),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Count<Q>(Q);
+
+impl<Q> Count<Q> {
+    /// Count the rows of `query`.
+    pub fn new(query: Q) -> Self {
+        Count(query)
+    }
+
+    /// Get the inner query back.
+    pub fn into_inner(self) -> Q {
+        self.0
+    }
+}
+
+impl<Q: QueryText> QueryText for Count<Q> {
+    fn query_text(&self) -> String {
+        format!("SELECT count(*) FROM ({}) AS sub", self.0.query_text())
+    }
+
+    fn query_text_for<C: Client>(&self) -> String {
+        format!(
+            "SELECT count(*) FROM ({}) AS sub",
+            self.0.query_text_for::<C>()
+        )
+    }
+}
+
+impl<C: Client, Q: ToParams<C>> ToParams<C> for Count<Q> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.0.to_params()
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        self.0.redacted_params()
+    }
+}
+
+impl<C: Quote, Q: ToLiterals<C>> ToLiterals<C> for Count<Q> {
+    fn to_literals(&self) -> Vec<String> {
+        self.0.to_literals()
+    }
+
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        self.0.to_literals_unredacted()
+    }
+}
+
+impl<C, Q> Query<C> for Count<Q>
+where
+    C: Client,
+    Q: Query<C>,
+    i64: FromColumnIndexed<C>,
+{
+    type Row = i64;
+
+    const PRIMARY: bool = Q::PRIMARY;
+}
+
+impl<C, Q> QueryOne<C> for Count<Q>
+where
+    C: Client,
+    Q: Query<C>,
+    i64: FromColumnIndexed<C>,
+{
+}
+
+/// Whether a query would return any rows.
+///
+/// This wraps the inner query as `SELECT EXISTS (...)`, with the same
+/// parameters.  Every supported database accepts this text, though
+/// MySQL and SQLite return an integer rather than a boolean, which
+/// their clients convert.
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::query::{StaticQueryText, ToParams};
+/// # struct GetTrees;
+/// # impl StaticQueryText for GetTrees {
+/// #     const QUERY_TEXT: &'static str = "SELECT name FROM trees";
+/// # }
+/// # impl<C: Client> ToParams<C> for GetTrees {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// use aykroyd::combinator::Exists;
+/// use aykroyd::query::QueryText;
+///
+/// let query = Exists::new(GetTrees);
+/// assert_eq!("SELECT EXISTS (SELECT name FROM trees)", query.query_text());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Exists<Q>(Q);
+
+impl<Q> Exists<Q> {
+    /// Check for any rows of `query`.
+    pub fn new(query: Q) -> Self {
+        Exists(query)
+    }
+
+    /// Get the inner query back.
+    pub fn into_inner(self) -> Q {
+        self.0
+    }
+}
+
+impl<Q: QueryText> QueryText for Exists<Q> {
+    fn query_text(&self) -> String {
+        format!("SELECT EXISTS ({})", self.0.query_text())
+    }
+
+    fn query_text_for<C: Client>(&self) -> String {
+        format!("SELECT EXISTS ({})", self.0.query_text_for::<C>())
+    }
+}
+
+impl<C: Client, Q: ToParams<C>> ToParams<C> for Exists<Q> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.0.to_params()
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        self.0.redacted_params()
+    }
+}
+
+impl<C: Quote, Q: ToLiterals<C>> ToLiterals<C> for Exists<Q> {
+    fn to_literals(&self) -> Vec<String> {
+        self.0.to_literals()
+    }
+
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        self.0.to_literals_unredacted()
+    }
+}
+
+impl<C, Q> Query<C> for Exists<Q>
+where
+    C: Client,
+    Q: Query<C>,
+    bool: FromColumnIndexed<C>,
+{
+    type Row = bool;
+
+    const PRIMARY: bool = Q::PRIMARY;
+}
+
+impl<C, Q> QueryOne<C> for Exists<Q>
+where
+    C: Client,
+    Q: Query<C>,
+    bool: FromColumnIndexed<C>,
+{
+}
+
+/// The plan the database would use to run a query.
+///
+/// This asks for the plan in a form that can be parsed: `EXPLAIN (FORMAT
+/// JSON)` on PostgreSQL, `EXPLAIN FORMAT=JSON` on MySQL, and `EXPLAIN
+/// QUERY PLAN` on SQLite.  Collect the rows into a [`Plan`](crate::plan::Plan) to inspect
+/// it.  Both queries and statements can be explained.
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::plan::{Dialect, PlanRow};
+/// # use aykroyd::query::{StaticQueryText, ToParams};
+/// # struct GetTrees;
+/// # impl StaticQueryText for GetTrees {
+/// #     const QUERY_TEXT: &'static str = "SELECT name FROM trees WHERE height > 12";
+/// # }
+/// # impl<C: Client> ToParams<C> for GetTrees {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// # struct DbConn;
+/// # impl DbConn {
+/// #     fn query<Q>(&mut self, _: &Q) -> Result<Vec<PlanRow>, String> {
+/// #         Ok(vec![])
+/// #     }
+/// # }
+/// # let mut client = DbConn;
+/// # struct Db;
+/// # impl Client for Db {
+/// #     type Row<'a> = ();
+/// #     type Param<'a> = ();
+/// #     type Error = ();
+/// # }
+/// # impl Dialect for Db {
+/// #     fn explain_text(query_text: &str, _analyze: bool) -> String {
+/// #         format!("EXPLAIN {query_text}")
+/// #     }
+/// #     fn plan_row(_row: &()) -> Result<PlanRow, aykroyd::Error<()>> {
+/// #         unimplemented!()
+/// #     }
+/// # }
+/// use aykroyd::combinator::Explain;
+/// use aykroyd::plan::Plan;
+///
+/// let query = Explain::<_, Db>::new(GetTrees);
+/// let plan = Plan::from_rows(client.query(&query)?);
+/// assert!(!plan.full_scans().contains(&"trees"));
+/// # Ok::<(), String>(())
+/// ```
+pub struct Explain<Q, C> {
+    query: Q,
+    client: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<Q, C> Explain<Q, C> {
+    /// Explain `query` without running it.
+    pub fn new(query: Q) -> Self {
+        Explain {
+            query,
+            client: std::marker::PhantomData,
+        }
+    }
+
+    /// Run the query to include actual timings and row counts.
+    ///
+    /// This really does run the query, so be careful explaining
+    /// statements that modify data.  SQLite doesn't support this, and
+    /// reports the usual plan.
+    pub fn analyze(self) -> ExplainAnalyze<Q, C> {
+        ExplainAnalyze {
+            query: self.query,
+            client: std::marker::PhantomData,
+        }
+    }
+
+    /// Get the inner query back.
+    pub fn into_inner(self) -> Q {
+        self.query
+    }
+}
+
+impl<Q: QueryText, C: Dialect> QueryText for Explain<Q, C> {
+    fn query_text(&self) -> String {
+        C::explain_text(&self.query.query_text_for::<C>(), false)
+    }
+}
+
+impl<Q: ToParams<C>, C: Client> ToParams<C> for Explain<Q, C> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.query.to_params()
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        self.query.redacted_params()
+    }
+}
+
+/// The [`QueryRows`] parameter for [`Explain`] of a query, given that
+/// of the query.
+pub struct Explained<M>(std::marker::PhantomData<fn() -> M>);
+
+impl<C, M, Q> QueryRows<C, Explained<M>> for Explain<Q, C>
+where
+    C: Dialect,
+    Q: QueryRows<C, M>,
+{
+    type Row = PlanRow;
+
+    const PRIMARY: bool = Q::PRIMARY;
+
+    fn map_row(&self, row: &C::Row<'_>) -> Result<PlanRow, Error<C::Error>> {
+        C::plan_row(row)
+    }
+}
+
+impl<Q, C> QueryRows<C, Decoded> for Explain<Q, C>
+where
+    C: Dialect,
+    Q: Statement<C>,
+{
+    type Row = PlanRow;
+
+    /// A statement always runs on the primary.
+    const PRIMARY: bool = true;
+
+    fn map_row(&self, row: &C::Row<'_>) -> Result<PlanRow, Error<C::Error>> {
+        C::plan_row(row)
+    }
+}
+
+/// The plan of a query, with the actual timings and row counts of
+/// running it.
+///
+/// Use [`Explain::analyze`] to make one.  Since the query really runs,
+/// this always runs on the primary.
+pub struct ExplainAnalyze<Q, C> {
+    query: Q,
+    client: std::marker::PhantomData<fn() -> C>,
+}
+
+impl<Q, C> ExplainAnalyze<Q, C> {
+    /// Get the inner query back.
+    pub fn into_inner(self) -> Q {
+        self.query
+    }
+}
+
+impl<Q: QueryText, C: Dialect> QueryText for ExplainAnalyze<Q, C> {
+    fn query_text(&self) -> String {
+        C::explain_text(&self.query.query_text_for::<C>(), true)
+    }
+}
+
+impl<Q: ToParams<C>, C: Client> ToParams<C> for ExplainAnalyze<Q, C> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.query.to_params()
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        self.query.redacted_params()
+    }
+}
+
+impl<Q, C> QueryRows<C, Decoded> for ExplainAnalyze<Q, C>
+where
+    C: Dialect,
+    Q: QueryText + ToParams<C> + Sync,
+{
+    type Row = PlanRow;
+
+    /// Analyzing runs the query, which might modify data.
+    const PRIMARY: bool = true;
+
+    fn map_row(&self, row: &C::Row<'_>) -> Result<PlanRow, Error<C::Error>> {
+        C::plan_row(row)
+    }
+}
+
+/// The id generated by an insert statement, as a query.
+///
+/// This appends `RETURNING` and the id column to the statement, for
+/// the databases that support it, with the same parameters.  The
+/// clients' `insert` methods use this, so you shouldn't often need it.
+///
+/// ```
+/// # use aykroyd::client::Client;
+/// # use aykroyd::query::{Returning, StaticQueryText, ToParams};
+/// # struct InsertTree;
+/// # impl StaticQueryText for InsertTree {
+/// #     const QUERY_TEXT: &'static str = "INSERT INTO trees (name) VALUES ('oak')";
+/// # }
+/// # impl<C: Client> ToParams<C> for InsertTree {
+/// #     fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+/// #         None
+/// #     }
+/// # }
+/// # impl Returning for InsertTree {
+/// #     type Id = i64;
+/// #     const ID_COLUMN: &'static str = "id";
+/// # }
+/// use aykroyd::combinator::ReturningId;
+/// use aykroyd::query::QueryText;
+///
+/// let query = ReturningId::new(&InsertTree);
+/// assert_eq!(
+///     "INSERT INTO trees (name) VALUES ('oak') RETURNING id",
+///     query.query_text(),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct ReturningId<'a, S>(&'a S);
+
+impl<'a, S> ReturningId<'a, S> {
+    /// Return the id generated by `statement`.
+    pub fn new(statement: &'a S) -> Self {
+        ReturningId(statement)
+    }
+}
+
+impl<S> Clone for ReturningId<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for ReturningId<'_, S> {}
+
+impl<S: QueryText + Returning> QueryText for ReturningId<'_, S> {
+    fn query_text(&self) -> String {
+        format!("{} RETURNING {}", self.0.query_text(), S::ID_COLUMN)
+    }
+
+    fn query_text_for<C: Client>(&self) -> String {
+        format!(
+            "{} RETURNING {}",
+            self.0.query_text_for::<C>(),
+            S::ID_COLUMN
+        )
+    }
+}
+
+impl<C: Client, S: ToParams<C>> ToParams<C> for ReturningId<'_, S> {
+    fn to_params(&self) -> Option<Vec<C::Param<'_>>> {
+        self.0.to_params()
+    }
+
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        self.0.redacted_params()
+    }
+}
+
+impl<C: Quote, S: ToLiterals<C>> ToLiterals<C> for ReturningId<'_, S> {
+    fn to_literals(&self) -> Vec<String> {
+        self.0.to_literals()
+    }
+
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        self.0.to_literals_unredacted()
+    }
+}
+
+impl<C, S> QueryRows<C, Decoded> for ReturningId<'_, S>
+where
+    C: Client,
+    S: Statement<C> + Returning,
+    S::Id: FromColumnIndexed<C>,
+{
+    type Row = S::Id;
+
+    /// An insert always runs on the primary.
+    const PRIMARY: bool = true;
+
+    fn map_row(&self, row: &C::Row<'_>) -> Result<S::Id, Error<C::Error>> {
+        S::Id::from_column(row, 0)
+    }
+}
+
+impl<C, S> QueryRowsOne<C, Decoded> for ReturningId<'_, S>
+where
+    C: Client,
+    S: Statement<C> + Returning,
+    S::Id: FromColumnIndexed<C>,
+{
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::client::ToParam;
     use crate::query::StaticQueryText;
     use crate::test::sync_client::{self, TestClient};
+    use crate::FromRow;
 
     #[test]
     fn query_text() {
@@ -369,4 +1578,262 @@ mod test {
         test::<Either<A, B>>(&Either::Left(A), "A");
         test::<Either<A, B>>(&Either::Right(B), "B");
     }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_enum_statement() {
+        #[derive(crate::Statement)]
+        enum Cleanup {
+            #[aykroyd(text = "DELETE FROM trees WHERE height < $1")]
+            Short(i32),
+            #[aykroyd(text = "DELETE FROM trees")]
+            All,
+        }
+
+        let mut client = TestClient::new();
+        client.execute(&Cleanup::Short(3)).unwrap();
+        client.execute(&Cleanup::All).unwrap();
+
+        let records = client.records();
+        assert_eq!(2, records.len());
+        assert_eq!("DELETE FROM trees WHERE height < $1", records[0].text);
+        assert_eq!(Some(vec!["3".to_string()]), records[0].params);
+        assert_eq!("DELETE FROM trees", records[1].text);
+        assert_eq!(None, records[1].params);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_enum_field_names() {
+        #[derive(crate::Statement)]
+        enum Rename {
+            #[aykroyd(text = "UPDATE trees SET name = $1 WHERE height = $2")]
+            Named { params: String, literals: i32 },
+        }
+
+        let statement = Rename::Named {
+            params: "Bob".into(),
+            literals: 12,
+        };
+        let mut client = TestClient::new();
+        client.execute(&statement).unwrap();
+
+        let records = client.records();
+        assert_eq!(
+            Some(vec!["Bob".to_string(), "12".to_string()]),
+            records[0].params
+        );
+        assert_eq!(
+            Some(vec!["\"Bob\"".to_string(), "12".to_string()]),
+            ToParams::<TestClient>::redacted_params(&statement),
+        );
+        assert_eq!(
+            "UPDATE trees SET name = 'Bob' WHERE height = 12",
+            crate::debug::render::<TestClient>(&statement),
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_enum_query() {
+        #[derive(crate::QueryOne)]
+        #[aykroyd(row((String,)))]
+        enum GetTree {
+            #[aykroyd(text = "
+                SELECT name FROM trees WHERE height > $1
+            ")]
+            Over(i32),
+            #[aykroyd(text = "SELECT name FROM trees WHERE name = $1 AND height = $2")]
+            Named { name: String, height: i32 },
+        }
+
+        let query = GetTree::Named {
+            name: "Bob".into(),
+            height: 12,
+        };
+        let params = ToParams::<TestClient>::to_params(&query).unwrap();
+        let params: Vec<_> = params.iter().map(|param| param.to_param()).collect();
+        assert_eq!(vec!["Bob", "12"], params);
+
+        let mut client = TestClient::new();
+        client.push_query_result(Ok(vec![]));
+        client.query(&GetTree::Over(12)).unwrap();
+        client.push_query_opt_result(Ok(None));
+        client.query_opt(&query).unwrap();
+
+        let records = client.records();
+        assert_eq!(2, records.len());
+        assert_eq!("SELECT name FROM trees WHERE height > $1", records[0].text);
+        assert_eq!(
+            "SELECT name FROM trees WHERE name = $1 AND height = $2",
+            records[1].text,
+        );
+
+        let registered = crate::registry::queries()
+            .filter(|query| query.name().contains("GetTree::"))
+            .count();
+        assert_eq!(2, registered);
+    }
+
+    #[test]
+    fn map_row() {
+        struct A;
+        impl ToParams<TestClient> for A {
+            fn to_params(&self) -> Option<Vec<<TestClient as Client>::Param<'_>>> {
+                None
+            }
+        }
+        impl StaticQueryText for A {
+            const QUERY_TEXT: &'static str = "A";
+        }
+        impl Query<TestClient> for A {
+            type Row = (String,);
+        }
+        impl QueryOne<TestClient> for A {}
+
+        fn row(value: &str) -> sync_client::RowInner {
+            sync_client::RowInner {
+                names: vec!["name".into()],
+                values: vec![value.into()],
+            }
+        }
+
+        let query = MapRow::new(A, |(name,): (String,)| name.len());
+
+        let mut client = TestClient::new();
+        client.push_query_result(Ok(vec![row("a"), row("abc")]));
+        assert_eq!(vec![1, 3], client.query(&query).unwrap());
+        client.push_query_one_result(Ok(row("ab")));
+        assert_eq!(2, client.query_one(&query).unwrap());
+        client.push_query_opt_result(Ok(Some(row("abcd"))));
+        assert_eq!(Some(4), client.query_opt(&query).unwrap());
+
+        let records = client.records();
+        assert_eq!(3, records.len());
+        assert!(records.iter().all(|record| record.text == "A"));
+
+        let query = TryMapRow::new(A, |(name,): (String,)| {
+            name.parse::<i32>()
+                .map_err(|_| crate::Error::from_column_str("not a number", None))
+        });
+
+        client.push_query_result(Ok(vec![row("1"), row("2")]));
+        assert_eq!(vec![1, 2], client.query(&query).unwrap());
+        client.push_query_one_result(Ok(row("x")));
+        let err = client.query_one(&query).unwrap_err();
+        assert_eq!(crate::error::ErrorKind::FromColumn, err.kind());
+    }
+
+    struct Trees;
+    impl StaticQueryText for Trees {
+        const QUERY_TEXT: &'static str = "SELECT id, name FROM trees WHERE height > $1";
+    }
+    impl ToParams<TestClient> for Trees {
+        fn to_params(&self) -> Option<Vec<<TestClient as Client>::Param<'_>>> {
+            Some(vec![ToParam::<TestClient>::to_param(&12)])
+        }
+
+        fn redacted_params(&self) -> Option<Vec<String>> {
+            Some(vec!["12".into()])
+        }
+    }
+    impl ToLiterals<TestClient> for Trees {
+        fn to_literals(&self) -> Vec<String> {
+            vec!["12".into()]
+        }
+    }
+    impl Query<TestClient> for Trees {
+        type Row = (String, String);
+    }
+
+    fn params<Q: ToParams<TestClient>>(query: &Q) -> Vec<String> {
+        let params = query.to_params().unwrap_or_default();
+        params.iter().map(|param| param.to_param()).collect()
+    }
+
+    #[test]
+    fn paginate() {
+        let query = Paginate::new(Trees, &["name DESC", "id"], 2).offset(4);
+        assert_eq!(
+            "SELECT * FROM (SELECT id, name FROM trees WHERE height > $1) AS page \
+                ORDER BY name DESC, id LIMIT $2 OFFSET $3",
+            query.query_text(),
+        );
+        assert_eq!(vec!["12", "3", "4"], params(&query));
+
+        let page = query.page(vec![1, 2, 3]);
+        assert_eq!(vec![1, 2], page.items);
+        assert_eq!(Some(6), page.next);
+
+        let page = query.page(vec![1, 2]);
+        assert_eq!(vec![1, 2], page.items);
+        assert_eq!(None, page.next);
+
+        let query = Paginate::<_, TestClient>::new(Trees, &["id"], i64::MAX);
+        assert_eq!(vec!["12", &i64::MAX.to_string(), "0"], params(&query));
+    }
+
+    #[test]
+    fn keyset() {
+        let query = Keyset::<_, (String, i32), TestClient>::new(Trees, &["name", "id"], 2);
+        assert_eq!(
+            "SELECT * FROM (SELECT id, name FROM trees WHERE height > $1) AS page \
+                ORDER BY name, id LIMIT $2",
+            query.query_text(),
+        );
+        assert_eq!(vec!["12", "3"], params(&query));
+
+        let query = query.after(("Bob".into(), 7)).descending();
+        assert_eq!(
+            "SELECT * FROM (SELECT id, name FROM trees WHERE height > $1) AS page \
+                WHERE (name, id) < ($2, $3) ORDER BY name DESC, id DESC LIMIT $4",
+            query.query_text(),
+        );
+        assert_eq!(vec!["12", "Bob", "7", "3"], params(&query));
+        assert_eq!(
+            Some(vec!["12".into(), "\"Bob\"".into(), "7".into(), "3".into()]),
+            query.redacted_params(),
+        );
+        assert_eq!(
+            "SELECT * FROM (SELECT id, name FROM trees WHERE height > 12) AS page \
+                WHERE (name, id) < ('Bob', 7) ORDER BY name DESC, id DESC LIMIT 3",
+            crate::debug::render(&query),
+        );
+
+        let page = query.page(vec![(1, "c"), (2, "b"), (3, "a")], |row| {
+            (row.1.to_string(), row.0)
+        });
+        assert_eq!(vec![(1, "c"), (2, "b")], page.items);
+        assert_eq!(Some(("b".into(), 2)), page.next);
+    }
+
+    #[test]
+    fn count_and_exists() {
+        fn row(value: &str) -> sync_client::RowInner {
+            sync_client::RowInner {
+                names: vec!["value".into()],
+                values: vec![value.into()],
+            }
+        }
+
+        let mut client = TestClient::new();
+        client.push_query_one_result(Ok(row("42")));
+        assert_eq!(42, client.query_one(&Count::new(Trees)).unwrap());
+        client.push_query_one_result(Ok(row("true")));
+        let query = Either::<Exists<Trees>, Exists<Trees>>::Left(Exists::new(Trees));
+        assert!(client.query_one(&query).unwrap());
+
+        let records = client.records();
+        assert_eq!(2, records.len());
+        assert_eq!(
+            "SELECT count(*) FROM (SELECT id, name FROM trees WHERE height > $1) AS sub",
+            records[0].text,
+        );
+        assert_eq!(Some(vec!["12".to_string()]), records[0].params);
+        assert_eq!(
+            "SELECT EXISTS (SELECT id, name FROM trees WHERE height > $1)",
+            records[1].text,
+        );
+        assert_eq!(Some(vec!["12".to_string()]), records[1].params);
+    }
 }
@@ -0,0 +1,287 @@
+//! Query annotations, in the style of sqlcommenter.
+//!
+//! Give a client [`Comments`] with `set_comments`, and each query sent
+//! to the server has a comment appended naming the Rust type it came
+//! from, along with any other tags you like:
+//!
+//! ```sql
+//! SELECT * FROM customers WHERE id = $1 /*application='billing',query='GetCustomerById'*/
+//! ```
+//!
+//! Server-side tools like `pg_stat_statements` and the MySQL slow-query
+//! log can then attribute load to the code that caused it.
+//!
+//! ```
+//! use aykroyd::comment::Comments;
+//!
+//! let comments = Comments::new()
+//!     .tag("application", "billing")
+//!     .context(|| vec![("route", current_route())]);
+//! // client.set_comments(comments);
+//! # fn current_route() -> String { "/customers/:id".into() }
+//! ```
+//!
+//! The comment is part of the query text, and the clients cache their
+//! prepared statements by text, so it's made of fixed tags: the query
+//! name and those added with `tag`.  The tags that change on each call,
+//! those looked up with `context` and the `span_id`, would prepare a
+//! new statement every time.  So when there are any, the PostgreSQL and
+//! MySQL clients prepare the statement just for that call, outside the
+//! cache.  That costs a round trip, so only add them where knowing the
+//! caller is worth it.
+//!
+//! SQLite and DuckDB run in-process, so there's no server log for the
+//! per-call tags to reach, and their clients leave them out.  So does
+//! the [`any`](crate::any) client, whatever it's connected to.
+
+use std::sync::Arc;
+
+/// The comments to append to each query.
+#[derive(Clone, Default)]
+pub struct Comments {
+    tags: Vec<(String, String)>,
+    context: Option<Arc<Context>>,
+    #[cfg(feature = "tracing")]
+    span_id: bool,
+}
+
+type Context = dyn Fn() -> Vec<(&'static str, String)> + Send + Sync;
+
+impl Comments {
+    /// Comments with the name of the query type.
+    pub fn new() -> Self {
+        Comments::default()
+    }
+
+    /// Add a tag to every comment.
+    pub fn tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// Add tags looked up each time a query is run.
+    ///
+    /// Queries are prepared for each call when there are any, see the
+    /// [module docs](crate::comment).
+    pub fn context<F>(mut self, context: F) -> Self
+    where
+        F: Fn() -> Vec<(&'static str, String)> + Send + Sync + 'static,
+    {
+        self.context = Some(Arc::new(context));
+        self
+    }
+
+    /// Add the id of the current `tracing` span as `span_id`.
+    ///
+    /// This is the id the subscriber gave the span in this process,
+    /// which it may reuse once the span closes, not a distributed trace
+    /// id.  It's enough to find the span in the process's own logs.
+    ///
+    /// Like the `context`, this changes on each call, so turning it on
+    /// turns off statement caching for queries run inside a span: the
+    /// PostgreSQL and MySQL clients prepare them for each call, at the
+    /// cost of a round trip.
+    #[cfg(feature = "tracing")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+    pub fn span_id(mut self) -> Self {
+        self.span_id = true;
+        self
+    }
+
+    /// The tags that change on each call.
+    fn per_call(&self) -> Vec<(&'static str, String)> {
+        #[cfg_attr(not(feature = "tracing"), allow(unused_mut))]
+        let mut tags = match &self.context {
+            Some(context) => context(),
+            None => vec![],
+        };
+        #[cfg(feature = "tracing")]
+        if self.span_id {
+            if let Some(id) = tracing::Span::current().id() {
+                tags.push(("span_id", format!("{:016x}", id.into_u64())));
+            }
+        }
+        tags
+    }
+
+    /// Append the comment with the fixed tags, for a statement to cache.
+    ///
+    /// Text that already ends with a comment is left as-is.
+    pub(crate) fn annotate(&self, query_name: &str, text: String) -> String {
+        self.append(query_name, text, &[])
+    }
+
+    /// Append the comment with the per-call tags too, if there are any
+    /// this time.
+    pub(crate) fn annotate_once(&self, query_name: &str, text: &str) -> Option<String> {
+        if is_commented(text) {
+            return None;
+        }
+
+        let per_call = self.per_call();
+        if per_call.is_empty() {
+            return None;
+        }
+        Some(self.append(query_name, text.into(), &per_call))
+    }
+
+    fn append(&self, query_name: &str, mut text: String, per_call: &[(&str, String)]) -> String {
+        if is_commented(&text) {
+            return text;
+        }
+
+        let mut tags: Vec<(&str, &str)> = vec![("query", short_name(query_name))];
+        tags.extend(self.tags.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        tags.extend(per_call.iter().map(|(k, v)| (*k, v.as_str())));
+        tags.sort_by(|a, b| a.0.cmp(b.0));
+
+        text.push_str(" /*");
+        for (index, (key, value)) in tags.into_iter().enumerate() {
+            if index > 0 {
+                text.push(',');
+            }
+            text.push_str(&encode(key));
+            text.push_str("='");
+            text.push_str(&encode(value));
+            text.push('\'');
+        }
+        text.push_str("*/");
+        text
+    }
+}
+
+impl std::fmt::Debug for Comments {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut debug = f.debug_struct("Comments");
+        debug
+            .field("tags", &self.tags)
+            .field("context", &self.context.is_some());
+        #[cfg(feature = "tracing")]
+        debug.field("span_id", &self.span_id);
+        debug.finish()
+    }
+}
+
+/// Does the text already end with a comment?
+fn is_commented(text: &str) -> bool {
+    text.trim_end().ends_with("*/")
+}
+
+/// The name of a type, without its module path or generics.
+fn short_name(type_name: &str) -> &str {
+    let path = type_name.split('<').next().unwrap_or(type_name);
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// URL-encode a key or value, which also keeps quotes and the end of the
+/// comment out of it.
+fn encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn annotate() {
+        let comments = Comments::new()
+            .tag("application", "billing")
+            .context(|| vec![("route", "/customers/:id".into())]);
+        assert_eq!(
+            "SELECT 1 /*application='billing',query='GetCustomerById'*/",
+            comments.annotate("app::queries::GetCustomerById<'_>", "SELECT 1".into()),
+        );
+    }
+
+    #[test]
+    fn annotate_once() {
+        let comments = Comments::new()
+            .tag("application", "billing")
+            .context(|| vec![("route", "/customers/:id".into())]);
+        assert_eq!(
+            Some("SELECT 1 /*application='billing',query='GetCustomerById',route='%2Fcustomers%2F%3Aid'*/".into()),
+            comments.annotate_once("GetCustomerById", "SELECT 1"),
+        );
+        assert_eq!(None, Comments::new().annotate_once("GetOne", "SELECT 1"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn span_id() {
+        use std::sync::Mutex;
+
+        /// A subscriber that knows only which span was entered last.
+        #[derive(Default)]
+        struct Current(Mutex<Option<&'static tracing::Metadata<'static>>>);
+
+        impl tracing::Subscriber for Current {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+                *self.0.lock().unwrap() = Some(span.metadata());
+                tracing::span::Id::from_u64(0xabc)
+            }
+
+            fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+            fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+            fn event(&self, _: &tracing::Event<'_>) {}
+
+            fn enter(&self, _: &tracing::span::Id) {}
+
+            fn exit(&self, _: &tracing::span::Id) {}
+
+            fn current_span(&self) -> tracing_core::span::Current {
+                match *self.0.lock().unwrap() {
+                    Some(metadata) => tracing_core::span::Current::new(
+                        tracing::span::Id::from_u64(0xabc),
+                        metadata,
+                    ),
+                    None => tracing_core::span::Current::none(),
+                }
+            }
+        }
+
+        let comments = Comments::new().span_id();
+        assert_eq!(None, comments.annotate_once("GetOne", "SELECT 1"));
+
+        tracing::subscriber::with_default(Current::default(), || {
+            let span = tracing::info_span!("request");
+            let _entered = span.enter();
+            assert_eq!(
+                Some("SELECT 1 /*query='GetOne',span_id='0000000000000abc'*/".into()),
+                comments.annotate_once("GetOne", "SELECT 1"),
+            );
+        });
+    }
+
+    #[test]
+    fn already_commented() {
+        let text = "SELECT 1 /* hand-written */";
+        assert_eq!(text, Comments::new().annotate("GetOne", text.into()));
+        let comments = Comments::new().context(|| vec![("route", "/".into())]);
+        assert_eq!(None, comments.annotate_once("GetOne", text));
+    }
+
+    #[test]
+    fn escape() {
+        let comments = Comments::new().tag("note", "it's */ done");
+        assert_eq!(
+            "SELECT 1 /*note='it%27s%20%2A%2F%20done',query='GetOne'*/",
+            comments.annotate("GetOne", "SELECT 1".into()),
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! PostgreSQL's `COPY`, for loading and unloading many rows.
+//!
+//! Both PostgreSQL clients have a `copy_in` method, which loads a slice
+//! of rows into a table, and a `copy_out` method, which reads every row
+//! of a query.  The data is sent in the binary format, so the column
+//! types are needed: we get them by preparing a `SELECT` of the same
+//! columns, and the rows are converted with the same [`ToParams`] and
+//! [`FromRow`] implementations as any other query.
+#![cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::{FromRow, Query, Statement};
+#[derive(Statement)]
+#[aykroyd(text = "INSERT INTO customers (first_name, last_name) VALUES ($1, $2)")]
+struct Customer<'a> {
+    first_name: &'a str,
+    last_name: &'a str,
+}
+
+#[derive(FromRow)]
+#[aykroyd(by_index)]
+struct CustomerName {
+    first_name: String,
+    last_name: String,
+}
+
+#[derive(Query)]
+#[aykroyd(row(CustomerName), text = "SELECT first_name, last_name FROM customers")]
+struct GetCustomerNames;
+```
+"##
+)]
+//!
+//! ```ignore
+//! let customers = vec![
+//!     Customer { first_name: "Luke", last_name: "Skywalker" },
+//!     Customer { first_name: "Leia", last_name: "Organa" },
+//! ];
+//! let rows_copied = client.copy_in("customers (first_name, last_name)", &customers)?;
+//!
+//! let names = client.copy_out(&GetCustomerNames)?;
+//! ```
+//!
+//! The table may be followed by a list of columns, otherwise the rows
+//! must have a value for every column of the table, in order.  The
+//! binary format has no column names, so the rows read by `copy_out`
+//! must load their columns by index, and the query can't have any
+//! parameters.
+//!
+//! [`ToParams`]: crate::query::ToParams
+//! [`FromRow`]: crate::FromRow
+
+use tokio_postgres::binary_copy::BinaryCopyOutRow;
+use tokio_postgres::types::{FromSqlOwned, ToSql, Type};
+
+use crate::client::{FromColumnIndexed, ToParam};
+use crate::error;
+
+type Error = error::Error<tokio_postgres::Error>;
+
+/// Rows in the binary copy format.
+///
+/// This isn't a client you can connect to, rather the type of the rows
+/// produced by `copy_out`.  Queries for `copy_out` implement `Query` for
+/// this type, which the derive macros do for you.
+pub struct Binary;
+
+impl crate::client::Client for Binary {
+    type Row<'a> = BinaryCopyOutRow;
+    type Param<'a> = &'a (dyn ToSql + Sync);
+    type Error = tokio_postgres::Error;
+}
+
+impl<T> FromColumnIndexed<Binary> for T
+where
+    T: FromSqlOwned,
+{
+    fn from_column(row: &BinaryCopyOutRow, index: usize) -> Result<Self, Error> {
+        row.try_get(index).map_err(Error::from_column)
+    }
+}
+
+impl<T> ToParam<Binary> for T
+where
+    T: ToSql + Sync,
+{
+    fn to_param(&self) -> &(dyn ToSql + Sync) {
+        self
+    }
+}
+
+/// The statement to copy rows into a table.
+pub(crate) fn copy_in_text(table: &str) -> String {
+    format!("COPY {table} FROM STDIN (FORMAT binary)")
+}
+
+/// The statement to copy the rows of a query out.
+pub(crate) fn copy_out_text(query_text: &str) -> String {
+    format!("COPY ({query_text}) TO STDOUT (FORMAT binary)")
+}
+
+/// A query of the columns to copy into, to look up their types.
+pub(crate) fn select_text(table: &str) -> String {
+    match table.split_once('(') {
+        Some((table, columns)) => {
+            let columns = columns.trim_end().trim_end_matches(')');
+            format!("SELECT {} FROM {}", columns.trim(), table.trim())
+        }
+        None => format!("SELECT * FROM {}", table.trim()),
+    }
+}
+
+/// The types of the columns of a prepared statement.
+pub(crate) fn column_types(statement: &tokio_postgres::Statement) -> Vec<Type> {
+    statement
+        .columns()
+        .iter()
+        .map(|column| column.type_().clone())
+        .collect()
+}
+
+/// Check that a row has a value for each column.
+///
+/// The binary copy writer panics otherwise.
+pub(crate) fn check_row(types: &[Type], params: &[&(dyn ToSql + Sync)]) -> Result<(), Error> {
+    if params.len() == types.len() {
+        Ok(())
+    } else {
+        Err(Error::query_str(
+            format!(
+                "expected {} columns to copy, found {}",
+                types.len(),
+                params.len(),
+            ),
+            None,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn select() {
+        assert_eq!("SELECT * FROM todos", select_text("todos"));
+        assert_eq!(
+            "SELECT label, done FROM public.todos",
+            select_text("public.todos (label, done)"),
+        );
+        assert_eq!(
+            "COPY todos (label) FROM STDIN (FORMAT binary)",
+            copy_in_text("todos (label)"),
+        );
+        assert_eq!(
+            "COPY (SELECT label FROM todos) TO STDOUT (FORMAT binary)",
+            copy_out_text("SELECT label FROM todos"),
+        );
+    }
+}
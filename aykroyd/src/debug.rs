@@ -0,0 +1,262 @@
+//! Render queries with their parameters inlined, for debugging.
+//!
+//! The parameters of a query are opaque to us once they're converted for
+//! a database client, so this uses a parallel trait, [`ToLiteral`], to
+//! write each parameter as an SQL literal in the client's dialect.  The
+//! derive macros for `Statement`, `Query` and `QueryOne` implement
+//! [`ToLiterals`] when every field has a literal form.
+#![cfg_attr(
+    all(feature = "derive", feature = "rusqlite"),
+    doc = r##"
+
+```
+# use aykroyd::Query;
+# use aykroyd::rusqlite::Client;
+#[derive(Query)]
+#[aykroyd(row((i32, String)), text = "SELECT id, label FROM todos WHERE label = $1 AND done = $2")]
+struct GetTodos<'a>(&'a str, bool);
+
+let text = aykroyd::debug::render::<Client>(&GetTodos("don't forget", false));
+assert_eq!("SELECT id, label FROM todos WHERE label = 'don''t forget' AND done = 0", text);
+```
+"##
+)]
+//!
+//! The result is meant to be pasted into a database shell.  Don't run it
+//! from code: always pass parameters separately for that.
+
+use crate::client::Client;
+use crate::list::{pieces, Piece};
+use crate::query::QueryText;
+
+/// How a database client writes literal values.
+///
+/// The defaults are standard SQL.
+pub trait Quote: Client {
+    /// Quote a string.
+    fn quote_str(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    /// Quote a byte string.
+    fn quote_bytes(value: &[u8]) -> String {
+        let hex: String = value.iter().map(|byte| format!("{byte:02X}")).collect();
+        format!("X'{hex}'")
+    }
+
+    /// Write a boolean.
+    fn quote_bool(value: bool) -> String {
+        match value {
+            true => "TRUE".into(),
+            false => "FALSE".into(),
+        }
+    }
+}
+
+/// A parameter that can be written as a literal.
+pub trait ToLiteral<C: Quote> {
+    fn to_literal(&self) -> String;
+}
+
+/// A query or statement whose parameters can be written as literals.
+///
+/// This is generated by the derive macros for `Query`, `QueryOne`,
+/// and `Statement`.
+pub trait ToLiterals<C: Quote> {
+    /// The literal for each parameter, in order.
+    ///
+    /// Fields marked `#[aykroyd(sensitive)]` are written as a comment,
+    /// `/* <redacted> */`, in place of their value.
+    fn to_literals(&self) -> Vec<String>;
+
+    /// The literal for each parameter, in order, including the
+    /// sensitive ones.
+    fn to_literals_unredacted(&self) -> Vec<String> {
+        self.to_literals()
+    }
+}
+
+/// The text of a query, with its parameters inlined as literals.
+///
+/// Placeholders without a parameter are left as they are.  Sensitive
+/// parameters are masked, so the result is safe to log; use
+/// [`render_unredacted`] to see them.
+pub fn render<C: Quote>(query: &(impl QueryText + ToLiterals<C>)) -> String {
+    inline(&query.query_text(), &query.to_literals())
+}
+
+/// The text of a query, with all its parameters inlined as literals,
+/// including the sensitive ones.
+///
+/// Keep this out of logs.
+pub fn render_unredacted<C: Quote>(query: &(impl QueryText + ToLiterals<C>)) -> String {
+    inline(&query.query_text(), &query.to_literals_unredacted())
+}
+
+fn inline(text: &str, literals: &[String]) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    for piece in pieces(text) {
+        match piece {
+            Piece::Text(text) => rendered.push_str(text),
+            Piece::Param(param) => match literals.get(param.index.wrapping_sub(1)) {
+                Some(literal) => rendered.push_str(literal),
+                None => match param.prefix {
+                    Some(prefix) => rendered.push_str(&format!("{prefix}{}", param.index)),
+                    None => rendered.push('?'),
+                },
+            },
+        }
+    }
+    rendered
+}
+
+macro_rules! impl_number_literal {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl<C: Quote> ToLiteral<C> for $ty {
+                fn to_literal(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )+
+    };
+}
+
+impl_number_literal!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_float_literal {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl<C: Quote> ToLiteral<C> for $ty {
+                fn to_literal(&self) -> String {
+                    if self.is_finite() {
+                        format!("{self:?}")
+                    } else {
+                        C::quote_str(&self.to_string())
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_float_literal!(f32, f64);
+
+impl<C: Quote> ToLiteral<C> for bool {
+    fn to_literal(&self) -> String {
+        C::quote_bool(*self)
+    }
+}
+
+impl<C: Quote> ToLiteral<C> for str {
+    fn to_literal(&self) -> String {
+        C::quote_str(self)
+    }
+}
+
+impl<C: Quote> ToLiteral<C> for String {
+    fn to_literal(&self) -> String {
+        C::quote_str(self)
+    }
+}
+
+impl<C: Quote> ToLiteral<C> for [u8] {
+    fn to_literal(&self) -> String {
+        C::quote_bytes(self)
+    }
+}
+
+impl<C: Quote> ToLiteral<C> for Vec<u8> {
+    fn to_literal(&self) -> String {
+        C::quote_bytes(self)
+    }
+}
+
+impl<C: Quote, T: ToLiteral<C>> ToLiteral<C> for Option<T> {
+    fn to_literal(&self) -> String {
+        match self {
+            Some(value) => value.to_literal(),
+            None => "NULL".into(),
+        }
+    }
+}
+
+impl<C: Quote, T: ToLiteral<C> + ?Sized> ToLiteral<C> for &T {
+    fn to_literal(&self) -> String {
+        (**self).to_literal()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query::StaticQueryText;
+    use crate::test::sync_client::TestClient;
+
+    impl Quote for TestClient {}
+
+    struct GetTodos<'a>(&'a str, Option<i32>, &'a [u8]);
+
+    impl StaticQueryText for GetTodos<'_> {
+        const QUERY_TEXT: &'static str =
+            "SELECT * FROM todos WHERE label = $1 AND '$2' <> $2 AND tag = $3 AND x = $4";
+    }
+
+    impl ToLiterals<TestClient> for GetTodos<'_> {
+        fn to_literals(&self) -> Vec<String> {
+            vec![
+                ToLiteral::<TestClient>::to_literal(&self.0),
+                ToLiteral::<TestClient>::to_literal(&self.1),
+                ToLiteral::<TestClient>::to_literal(&self.2),
+            ]
+        }
+    }
+
+    #[test]
+    fn render() {
+        assert_eq!(
+            "SELECT * FROM todos WHERE label = 'it''s' AND '$2' <> NULL AND tag = X'CAFE' AND x = $4",
+            super::render::<TestClient>(&GetTodos("it's", None, &[0xCA, 0xFE])),
+        );
+
+        let count = crate::combinator::Count::new(GetTodos("a", Some(1), &[]));
+        assert_eq!(
+            "SELECT count(*) FROM (SELECT * FROM todos WHERE label = 'a' AND '$2' <> 1 AND tag = X'' AND x = $4) AS sub",
+            super::render::<TestClient>(&count),
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn sensitive() {
+        #[derive(crate::Statement)]
+        #[aykroyd(text = "UPDATE users SET password_hash = $2 WHERE email = $1")]
+        struct SetPassword<'a> {
+            email: &'a str,
+            #[aykroyd(sensitive)]
+            password_hash: &'a str,
+        }
+
+        let statement = SetPassword {
+            email: "dan@example.com",
+            password_hash: "$argon2id$v=19$...",
+        };
+        assert_eq!(
+            "UPDATE users SET password_hash = /* <redacted> */ WHERE email = 'dan@example.com'",
+            super::render::<TestClient>(&statement),
+        );
+        assert_eq!(
+            "UPDATE users SET password_hash = '$argon2id$v=19$...' WHERE email = 'dan@example.com'",
+            super::render_unredacted::<TestClient>(&statement),
+        );
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!("-3", ToLiteral::<TestClient>::to_literal(&-3i64));
+        assert_eq!("1.5", ToLiteral::<TestClient>::to_literal(&1.5f64));
+        assert_eq!("'NaN'", ToLiteral::<TestClient>::to_literal(&f64::NAN));
+        assert_eq!("TRUE", ToLiteral::<TestClient>::to_literal(&true));
+        assert_eq!("'a'", ToLiteral::<TestClient>::to_literal(&Some("a")));
+    }
+}
@@ -0,0 +1,505 @@
+//! DuckDB bindings.
+
+use std::any::type_name;
+
+use crate::bulk::InsertValues;
+use crate::client::{FromColumnIndexed, FromColumnNamed, ToParam};
+use crate::combinator::ReturningId;
+use crate::comment::Comments;
+use crate::hook::{Hook, Hooks};
+use crate::list::{ParamList, ToParamList};
+use crate::query::{Returning, StaticQueryText, ToParams};
+use crate::stats::Stats;
+use crate::{builder, bulk, debug, error, trace, QueryRows, QueryRowsOne, Statement};
+
+pub type Error = error::Error<duckdb::Error>;
+
+/// The OpenTelemetry name of the database, for tracing spans.
+const DB_SYSTEM: &str = "duckdb";
+
+impl<T> FromColumnIndexed<Client> for T
+where
+    T: duckdb::types::FromSql,
+{
+    fn from_column(row: &duckdb::Row, index: usize) -> Result<Self, Error> {
+        row.get(index).map_err(Error::from_column)
+    }
+}
+
+impl<T> FromColumnNamed<Client> for T
+where
+    T: duckdb::types::FromSql,
+{
+    fn from_column(row: &duckdb::Row, name: &str) -> Result<Self, Error> {
+        row.get(name).map_err(Error::from_column)
+    }
+}
+
+impl<T> ToParam<Client> for T
+where
+    T: duckdb::types::ToSql,
+{
+    fn to_param(&self) -> &dyn duckdb::types::ToSql {
+        self
+    }
+}
+
+impl<L> ToParamList<Client> for L
+where
+    L: ParamList,
+    L::Item: duckdb::types::ToSql,
+{
+    fn to_params(&self) -> Vec<&dyn duckdb::types::ToSql> {
+        self.items().iter().map(ToParam::to_param).collect()
+    }
+}
+
+pub struct Client(duckdb::Connection, Hooks);
+
+impl crate::client::Client for Client {
+    type Row<'a> = duckdb::Row<'a>;
+    type Param<'a> = &'a dyn duckdb::types::ToSql;
+    type Error = duckdb::Error;
+}
+
+impl builder::Placeholder for Client {
+    fn placeholder(index: usize) -> String {
+        format!("${index}")
+    }
+}
+
+impl debug::Quote for Client {}
+
+/// DuckDB has no fixed limit, so this just keeps each statement a
+/// reasonable size.
+impl bulk::ParamLimit for Client {
+    fn param_limit() -> usize {
+        32766
+    }
+}
+
+impl AsMut<duckdb::Connection> for Client {
+    fn as_mut(&mut self) -> &mut duckdb::Connection {
+        &mut self.0
+    }
+}
+
+impl AsRef<duckdb::Connection> for Client {
+    fn as_ref(&self) -> &duckdb::Connection {
+        &self.0
+    }
+}
+
+impl From<duckdb::Connection> for Client {
+    fn from(inner: duckdb::Connection) -> Self {
+        Client(inner, Hooks::default())
+    }
+}
+
+impl Client {
+    /// Add a hook to run around each query, on this client and its transactions.
+    ///
+    /// See the [`hook`](crate::hook) module for details.
+    pub fn add_hook<H: Hook + 'static>(&mut self, hook: H) {
+        self.1.add(hook);
+    }
+
+    /// Collect statistics for each query, on this client and its transactions.
+    ///
+    /// See the [`stats`](crate::stats) module for details.
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.1.set_stats(stats);
+    }
+
+    /// The statistics collected for this client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.1.stats()
+    }
+
+    /// Append a comment to each query, on this client and its transactions.
+    ///
+    /// See the [`comment`](crate::comment) module for details.
+    pub fn set_comments(&mut self, comments: Comments) {
+        self.1.set_comments(comments);
+    }
+
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        duckdb::Connection::open(path)
+            .map(Client::from)
+            .map_err(Error::connect)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Error> {
+        duckdb::Connection::open_in_memory()
+            .map(Client::from)
+            .map_err(Error::connect)
+    }
+
+    pub fn query<Q: QueryRows<Self, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |rows| rows.len() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(self.as_mut(), &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                let mut result = vec![];
+                while let Some(row) = rows.next().map_err(Error::query)? {
+                    result.push(query.map_row(row)?);
+                }
+
+                Ok(result)
+            },
+        )
+    }
+
+    pub fn query_one<Q: QueryRowsOne<Self, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_one", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |_| 1,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(self.as_mut(), &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .ok_or_else(|| Error::query(duckdb::Error::QueryReturnedNoRows))
+                    .and_then(|row| query.map_row(row))
+            },
+        )
+    }
+
+    pub fn query_opt<Q: QueryRowsOne<Self, M>, M>(
+        &mut self,
+        query: &Q,
+    ) -> Result<Option<Q::Row>, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_opt", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |row| row.is_some() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(self.as_mut(), &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .map(|row| query.map_row(row))
+                    .transpose()
+            },
+        )
+    }
+
+    pub fn execute<S: Statement<Self>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "execute", statement)?;
+        let query_text = self.1.annotate(type_name::<S>(), query_text);
+        span.run(
+            |rows_affected| *rows_affected,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(self.as_mut(), &query_text)
+                    .map_err(Error::prepare)?;
+
+                let rows_affected = statement.execute(params).map_err(Error::query)?;
+
+                Ok(rows_affected.try_into().unwrap_or_default())
+            },
+        )
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Self> + Returning,
+        S::Id: FromColumnIndexed<Self>,
+    {
+        self.query_one(&ReturningId::new(statement))
+    }
+
+    pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        let query_text = self.1.annotate(type_name::<S>(), S::QUERY_TEXT.into());
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), &query_text);
+        span.in_scope(|| {
+            self.as_mut()
+                .prepare_cached(&query_text)
+                .map_err(Error::prepare)?;
+            Ok(())
+        })
+    }
+
+    /// Prepares every query in the [`registry`](crate::registry).
+    ///
+    /// Call this after connecting to catch invalid query text at
+    /// startup rather than first use.  It also warms the statement cache,
+    /// but that only holds 16 statements unless you raise it with
+    /// `set_prepared_statement_cache_capacity`.
+    /// Every registered query is prepared, including those of other
+    /// crates, so they should all be meant for this database.  If not,
+    /// use [`prepare_all_matching`](Self::prepare_all_matching).
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub fn prepare_all(&mut self) -> Result<(), Error> {
+        self.prepare_all_matching(|_| true)
+    }
+
+    /// Prepares the queries in the [`registry`](crate::registry) that
+    /// match the filter.
+    ///
+    /// Use this when not every registered query is meant for this
+    /// database, e.g. `|query| query.in_module("my_app::sqlite")`.
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub fn prepare_all_matching<F>(&mut self, mut filter: F) -> Result<(), Error>
+    where
+        F: FnMut(&crate::registry::QueryInfo) -> bool,
+    {
+        for query in crate::registry::queries().filter(|query| filter(query)) {
+            let query_text = self.1.annotate(query.name(), query.text().into_owned());
+            let span = trace::Span::new(DB_SYSTEM, "prepare", query.name(), &query_text);
+            span.in_scope(|| {
+                self.as_mut()
+                    .prepare_cached(&query_text)
+                    .map_err(Error::prepare)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit, all in one transaction.  Returns the total number of rows
+    /// affected.  See the [`bulk`](crate::bulk) module for details.
+    pub fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Self>,
+    {
+        let mut txn = self.transaction()?;
+        let rows_affected = txn.insert_many(rows)?;
+        txn.commit()?;
+        Ok(rows_affected)
+    }
+
+    pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        Ok(Transaction(
+            self.0.transaction().map_err(Error::transaction)?,
+            self.1.clone(),
+        ))
+    }
+}
+
+pub struct Transaction<'a>(duckdb::Transaction<'a>, Hooks);
+
+impl<'a> Transaction<'a> {
+    /// The statistics collected for the client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.1.stats()
+    }
+
+    pub fn commit(self) -> Result<(), Error> {
+        let span = trace::Span::transaction(DB_SYSTEM, "commit");
+        span.in_scope(|| self.0.commit().map_err(Error::transaction))
+    }
+
+    pub fn rollback(self) -> Result<(), Error> {
+        let span = trace::Span::transaction(DB_SYSTEM, "rollback");
+        span.in_scope(|| self.0.rollback().map_err(Error::transaction))
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit.  Returns the total number of rows affected.
+    pub fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Client>,
+    {
+        let mut rows_affected = 0;
+        for chunk in bulk::chunks::<Client, S>(rows) {
+            rows_affected += self.execute(&chunk)?;
+        }
+        Ok(rows_affected)
+    }
+
+    pub fn query<Q: QueryRows<Client, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |rows| rows.len() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                let mut result = vec![];
+                while let Some(row) = rows.next().map_err(Error::query)? {
+                    result.push(query.map_row(row)?);
+                }
+
+                Ok(result)
+            },
+        )
+    }
+
+    pub fn query_one<Q: QueryRowsOne<Client, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_one", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |_| 1,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .ok_or_else(|| Error::query(duckdb::Error::QueryReturnedNoRows))
+                    .and_then(|row| query.map_row(row))
+            },
+        )
+    }
+
+    pub fn query_opt<Q: QueryRowsOne<Client, M>, M>(
+        &mut self,
+        query: &Q,
+    ) -> Result<Option<Q::Row>, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_opt", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |row| row.is_some() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .map(|row| query.map_row(row))
+                    .transpose()
+            },
+        )
+    }
+
+    pub fn execute<S: Statement<Client>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "execute", statement)?;
+        let query_text = self.1.annotate(type_name::<S>(), query_text);
+        span.run(
+            |rows_affected| *rows_affected,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = duckdb::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let rows_affected = statement.execute(params).map_err(Error::query)?;
+
+                Ok(rows_affected.try_into().unwrap_or_default())
+            },
+        )
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Client> + Returning,
+        S::Id: FromColumnIndexed<Client>,
+    {
+        self.query_one(&ReturningId::new(statement))
+    }
+
+    pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        let query_text = self.1.annotate(type_name::<S>(), S::QUERY_TEXT.into());
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), &query_text);
+        span.in_scope(|| {
+            self.0.prepare_cached(&query_text).map_err(Error::prepare)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use super::*;
+    use crate::{Query, QueryOne};
+
+    #[derive(Statement)]
+    #[aykroyd(text = "CREATE TABLE test_duckdb (id INTEGER PRIMARY KEY, label TEXT NOT NULL)")]
+    struct CreateTodos;
+
+    #[derive(Statement)]
+    #[aykroyd(
+        insert_values,
+        text = "INSERT INTO test_duckdb (id, label) VALUES ($1, $2)"
+    )]
+    struct InsertTodo<'a>(i32, &'a str);
+
+    #[derive(Query)]
+    #[aykroyd(row((i32, String)), text = "SELECT id, label FROM test_duckdb")]
+    struct GetAllTodos;
+
+    #[derive(QueryOne)]
+    #[aykroyd(row((i64, i64)), text = "SELECT count(*), sum(id) FROM test_duckdb")]
+    struct Summary;
+
+    #[test]
+    fn end_to_end_memory() {
+        use crate::combinator::{Count, Exists};
+
+        const TODO_TEXT: &str = "get things done, please!";
+
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        client.prepare::<GetAllTodos>().unwrap();
+        assert!(!client.query_one(&Exists::new(GetAllTodos)).unwrap());
+
+        client.execute(&InsertTodo(1, TODO_TEXT)).unwrap();
+
+        let todos = client.query(&GetAllTodos).unwrap();
+        assert_eq!(vec![(1, TODO_TEXT.to_string())], todos);
+
+        let mut txn = client.transaction().unwrap();
+        txn.execute(&InsertTodo(2, "rolled back")).unwrap();
+        txn.rollback().unwrap();
+
+        let todos: Vec<_> = (2..=4).map(|id| InsertTodo(id, "more")).collect();
+        assert_eq!(3, client.insert_many(&todos).unwrap());
+
+        assert_eq!((4, 10), client.query_one(&Summary).unwrap());
+        assert_eq!(4, client.query_one(&Count::new(GetAllTodos)).unwrap());
+        assert!(client.query_one(&Exists::new(GetAllTodos)).unwrap());
+
+        #[derive(Statement)]
+        #[aykroyd(returning(id: i32), text = "INSERT INTO test_duckdb (id, label) VALUES ($1 + 1, $2)")]
+        struct InsertNextTodo<'a>(i32, &'a str);
+
+        assert_eq!(5, client.insert(&InsertNextTodo(4, "next")).unwrap());
+    }
+}
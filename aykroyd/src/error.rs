@@ -8,6 +8,9 @@
 //! * retrieving values from the results
 //! * transaction control
 //!
+//! A query can also be rejected before it is run, by a
+//! [hook](crate::hook).
+//!
 //! Use the `kind()` method on [`Error`] to find out
 //! which step it was.  If we have an underlying database
 //! error it can be retrieved with the `inner()` method.
@@ -78,6 +81,29 @@ impl<ClientError> Error<ClientError> {
             inner,
         }
     }
+
+    /// Convert the underlying database error, keeping the message and kind.
+    pub fn map_inner<E, F: FnOnce(ClientError) -> E>(self, f: F) -> Error<E> {
+        Error {
+            message: self.message,
+            kind: self.kind,
+            inner: self.inner.map(f),
+        }
+    }
+
+    /// An error for a query that a [`Hook`](crate::hook::Hook) rejected.
+    ///
+    /// A hook's `before` returns this to stop the query from running,
+    /// with [`ErrorKind::Rejected`].
+    pub fn rejected_str<S: Into<String>>(message: S) -> Self {
+        let kind = ErrorKind::Rejected;
+        let message = message.into();
+        Error {
+            message,
+            kind,
+            inner: None,
+        }
+    }
 }
 
 impl<ClientError: std::fmt::Display> Error<ClientError> {
@@ -108,7 +134,10 @@ impl<ClientError: std::fmt::Display> Error<ClientError> {
 }
 
 /// What operation prompted the error?
+///
+/// More kinds may be added, so a `match` needs a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum ErrorKind {
     /// Database error while preparing a query.
     Prepare,
@@ -124,6 +153,9 @@ pub enum ErrorKind {
 
     /// Error in transaction control.
     Transaction,
+
+    /// A [`Hook`](crate::hook::Hook) rejected the query.
+    Rejected,
 }
 
 impl<ClientError> std::fmt::Display for Error<ClientError> {
@@ -0,0 +1,300 @@
+//! Hooks around every query.
+//!
+//! Add a [`Hook`] to a client with `add_hook` to run code before and
+//! after each `query`, `query_one`, `query_opt` and `execute`, on the
+//! client and its transactions.  Use this for cross-cutting concerns like
+//! logging and metrics, or to rewrite or reject queries.
+//!
+//! ```
+//! use aykroyd::error::ErrorKind;
+//! use aykroyd::hook::{Before, Hook};
+//! use std::time::Duration;
+//!
+//! struct ReadOnly;
+//!
+//! impl Hook for ReadOnly {
+//!     fn before(&self, query_name: &str, text: &str, _param_count: usize) -> Before {
+//!         if text.trim_start().to_uppercase().starts_with("SELECT") {
+//!             Before::Run
+//!         } else {
+//!             Before::Reject(format!("{query_name} is not allowed in read-only mode"))
+//!         }
+//!     }
+//! }
+//!
+//! struct Log;
+//!
+//! impl Hook for Log {
+//!     fn after(
+//!         &self,
+//!         query_name: &str,
+//!         _text: &str,
+//!         result: Result<u64, ErrorKind>,
+//!         elapsed: Duration,
+//!     ) {
+//!         eprintln!("{query_name}: {result:?} in {elapsed:?}");
+//!     }
+//! }
+//! ```
+//!
+//! Hooks run in the order they were added.  If a hook rewrites the query
+//! text, later hooks see the rewritten text.  Since rewritten text is
+//! prepared separately, keep rewrites stable for each query, or the
+//! statement cache won't do you much good.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::comment::Comments;
+use crate::error::{Error, ErrorKind};
+use crate::stats::Stats;
+
+/// Code to run around each query.
+///
+/// Both methods have default implementations that do nothing, so only
+/// implement the ones you need.
+pub trait Hook: Send + Sync {
+    /// Called before a query is run.
+    ///
+    /// The query name is the Rust type of the query.  Return whether to
+    /// run the query as-is, with different text, or not at all.
+    #[allow(unused_variables)]
+    fn before(&self, query_name: &str, text: &str, param_count: usize) -> Before {
+        Before::Run
+    }
+
+    /// Called after a query is run, unless it was rejected.
+    ///
+    /// The result is the number of rows returned, or for `execute` the
+    /// number of rows affected, or the kind of error.
+    #[allow(unused_variables)]
+    fn after(
+        &self,
+        query_name: &str,
+        text: &str,
+        result: Result<u64, ErrorKind>,
+        elapsed: Duration,
+    ) {
+    }
+}
+
+/// What to do with a query, decided by [`Hook::before`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Before {
+    /// Run the query.
+    Run,
+    /// Run the query with this text instead.
+    Rewrite(String),
+    /// Don't run the query, failing with this message.
+    Reject(String),
+}
+
+/// Query text with its comments.
+#[cfg_attr(not(any(feature = "mysql", feature = "mysql-async")), allow(dead_code))]
+pub(crate) struct Annotated {
+    pub(crate) text: String,
+    /// Whether the comments have per-call tags, so the statement should
+    /// be prepared for this call only, and not cached.
+    pub(crate) once: bool,
+}
+
+/// The hooks, stats and comments added to a client, shared with its
+/// transactions.
+#[cfg_attr(
+    not(any(
+        feature = "duckdb",
+        feature = "mysql",
+        feature = "postgres",
+        feature = "rusqlite",
+        feature = "tokio-postgres",
+    )),
+    allow(dead_code)
+)]
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    hooks: Arc<Vec<Arc<dyn Hook>>>,
+    stats: Option<Stats>,
+    comments: Option<Comments>,
+}
+
+#[cfg_attr(
+    not(any(
+        feature = "duckdb",
+        feature = "mysql",
+        feature = "postgres",
+        feature = "rusqlite",
+        feature = "tokio-postgres",
+    )),
+    allow(dead_code)
+)]
+impl Hooks {
+    pub(crate) fn add<H: Hook + 'static>(&mut self, hook: H) {
+        Arc::make_mut(&mut self.hooks).push(Arc::new(hook));
+    }
+
+    pub(crate) fn set_stats(&mut self, stats: Stats) {
+        self.stats = Some(stats);
+    }
+
+    pub(crate) fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    pub(crate) fn set_comments(&mut self, comments: Comments) {
+        self.comments = Some(comments);
+    }
+
+    /// Append the comments, if any, with only their fixed tags, for a
+    /// statement that's cached.
+    pub(crate) fn annotate(&self, query_name: &str, text: String) -> String {
+        match &self.comments {
+            Some(comments) => comments.annotate(query_name, text),
+            None => text,
+        }
+    }
+
+    /// Append the comments, if any, with their per-call tags, if there
+    /// are any this time.  The text is then meant for this call only.
+    pub(crate) fn annotate_once(&self, query_name: &str, text: &str) -> Option<String> {
+        self.comments
+            .as_ref()
+            .and_then(|comments| comments.annotate_once(query_name, text))
+    }
+
+    /// Append the comments, if any, for one call: with their per-call tags
+    /// if there are any this time, and only the fixed tags otherwise.
+    #[cfg_attr(not(any(feature = "mysql", feature = "mysql-async")), allow(dead_code))]
+    pub(crate) fn annotate_call(&self, query_name: &str, text: String) -> Annotated {
+        match self.annotate_once(query_name, &text) {
+            Some(text) => Annotated { text, once: true },
+            None => Annotated {
+                text: self.annotate(query_name, text),
+                once: false,
+            },
+        }
+    }
+
+    /// Whether to describe the parameters of each query, for the
+    /// slow-query log.
+    pub(crate) fn wants_params(&self) -> bool {
+        self.stats.as_ref().is_some_and(Stats::logs_slow_queries)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty() && self.stats.is_none()
+    }
+
+    /// Run the `before` hooks, returning the text to run.
+    pub(crate) fn before<E>(
+        &self,
+        query_name: &str,
+        mut text: String,
+        param_count: usize,
+    ) -> Result<String, Error<E>> {
+        for hook in self.hooks.iter() {
+            match hook.before(query_name, &text, param_count) {
+                Before::Run => {}
+                Before::Rewrite(rewritten) => text = rewritten,
+                Before::Reject(message) => return Err(Error::rejected_str(message)),
+            }
+        }
+        Ok(text)
+    }
+
+    /// Run the `after` hooks, and record the query's stats.
+    pub(crate) fn after(
+        &self,
+        query_name: &str,
+        text: &str,
+        param_count: usize,
+        params: Option<&[String]>,
+        result: Result<u64, ErrorKind>,
+        elapsed: Duration,
+    ) {
+        for hook in self.hooks.iter() {
+            hook.after(query_name, text, result, elapsed);
+        }
+        if let Some(stats) = &self.stats {
+            stats.record(query_name, text, param_count, params, result, elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    struct Comment;
+
+    impl Hook for Comment {
+        fn before(&self, query_name: &str, text: &str, _: usize) -> Before {
+            Before::Rewrite(format!("{text} /* {query_name} */"))
+        }
+    }
+
+    struct NoDeletes;
+
+    impl Hook for NoDeletes {
+        fn before(&self, _: &str, text: &str, _: usize) -> Before {
+            if text.starts_with("DELETE") {
+                Before::Reject("no deletes".into())
+            } else {
+                Before::Run
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Calls(Mutex<Vec<(String, Result<u64, ErrorKind>)>>);
+
+    impl Hook for Arc<Calls> {
+        fn after(&self, query_name: &str, _: &str, result: Result<u64, ErrorKind>, _: Duration) {
+            self.0.lock().unwrap().push((query_name.into(), result));
+        }
+    }
+
+    #[test]
+    fn before() {
+        let mut hooks = Hooks::default();
+        hooks.add(NoDeletes);
+        hooks.add(Comment);
+
+        let text = hooks.before::<()>("GetTodos", "SELECT * FROM todos".into(), 0);
+        assert_eq!("SELECT * FROM todos /* GetTodos */", text.unwrap());
+
+        let err = hooks
+            .before::<()>("DeleteTodos", "DELETE FROM todos".into(), 0)
+            .unwrap_err();
+        assert_eq!(ErrorKind::Rejected, err.kind());
+        assert_eq!("no deletes", err.to_string());
+    }
+
+    #[test]
+    fn after() {
+        let calls = Arc::new(Calls::default());
+        let mut hooks = Hooks::default();
+        hooks.add(calls.clone());
+
+        let shared = hooks.clone();
+        shared.after("GetTodos", "", 0, None, Ok(3), Duration::ZERO);
+        hooks.after(
+            "InsertTodo",
+            "",
+            1,
+            None,
+            Err(ErrorKind::Query),
+            Duration::ZERO,
+        );
+
+        let calls = calls.0.lock().unwrap();
+        assert_eq!(
+            vec![
+                ("GetTodos".to_string(), Ok(3)),
+                ("InsertTodo".to_string(), Err(ErrorKind::Query)),
+            ],
+            *calls,
+        );
+    }
+}
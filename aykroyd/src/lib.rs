@@ -33,7 +33,8 @@
 //! The binding is not magic, there is no verification against a database.
 //! `Query` and `Statement` implementations are an assertion by the developer,
 //! one that you would be wise to verify.  It is recommended to write a
-//! suite of automated tests which can be run against any database tier.
+//! suite of automated tests which can be run against any database tier,
+//! using the tools in the [`verify`](verify) module.
 #![cfg_attr(
     feature = "derive",
     doc = r##"
@@ -78,7 +79,20 @@ struct GetAllPets;
 //! | PostgreSQL | [postgres](https://crates.io/crates/postgres) | `postgres` | Sync | [`aykroyd::postgres::Client`](postgres::Client) |
 //! | PostgreSQL | [tokio-postgres](https://crates.io/crates/tokio-postgres) | `tokio-postgres` | Async | [`aykroyd::tokio_postgres::Client`](tokio_postgres::Client) |
 //! | MySQL/MariaDB | [mysql](https://crates.io/crates/mysql) | `mysql` | Sync | [`aykroyd::mysql::Client`](mysql::Client) |
+//! | MySQL/MariaDB | [mysql_async](https://crates.io/crates/mysql_async) | `mysql-async` | Async | [`aykroyd::mysql_async::Client`](mysql_async::Client) |
 //! | SQLite | [rusqlite](https://crates.io/crates/rusqlite) | `rusqlite` | Sync | [`aykroyd::rusqlite::Client`](rusqlite::Client) |
+//! | SQLite | [rusqlite](https://crates.io/crates/rusqlite) | `async-rusqlite` | Async | [`aykroyd::async_rusqlite::Client`](async_rusqlite::Client) |
+//! | DuckDB | [duckdb](https://crates.io/crates/duckdb) | `duckdb` | Sync | [`aykroyd::duckdb::Client`](duckdb::Client) |
+//!
+//! With the `tracing` feature, every `prepare`, `query`, `query_one`,
+//! `query_opt`, `execute`, `commit` and `rollback` runs in a
+//! [tracing](https://crates.io/crates/tracing) span.  The span fields
+//! follow the OpenTelemetry conventions for database calls: `db.system`,
+//! `db.operation`, `db.statement`, and once it's done,
+//! `db.response.returned_rows` or `db.rows_affected`, and `error.type`
+//! if it failed.  We also record the Rust type in `aykroyd.query_type`,
+//! and for the PostgreSQL clients whether the prepared statement was
+//! cached in `aykroyd.cache_hit`.
 //!
 //! ## Examples
 //!
@@ -230,15 +244,67 @@ assert_eq!(rows[0].name, "Dan");
 
 extern crate self as aykroyd;
 
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "rusqlite"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "mysql", feature = "postgres", feature = "rusqlite")))
+)]
+pub mod any;
+#[cfg(feature = "async-rusqlite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-rusqlite")))]
+pub mod async_rusqlite;
+pub mod builder;
+pub mod bulk;
+#[cfg(any(feature = "postgres", feature = "tokio-postgres"))]
+mod cache;
 pub mod client;
 pub mod combinator;
+pub mod comment;
+#[cfg(any(feature = "postgres", feature = "tokio-postgres"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "postgres", feature = "tokio-postgres")))
+)]
+pub mod copy;
+pub mod debug;
+#[cfg(feature = "duckdb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "duckdb")))]
+pub mod duckdb;
 pub mod error;
+pub mod hook;
+pub mod list;
+#[cfg(any(feature = "postgres", feature = "tokio-postgres"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "postgres", feature = "tokio-postgres")))
+)]
+pub mod notify;
+pub mod plan;
 pub mod query;
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub mod registry;
+pub mod replica;
 pub mod row;
+pub mod stats;
+#[cfg(any(
+    feature = "async-rusqlite",
+    feature = "duckdb",
+    feature = "mysql",
+    feature = "mysql-async",
+    feature = "postgres",
+    feature = "rusqlite",
+    feature = "tokio-postgres",
+))]
+mod trace;
+pub mod verify;
 
 #[cfg(feature = "mysql")]
 #[cfg_attr(docsrs, doc(cfg(feature = "mysql")))]
 pub mod mysql;
+#[cfg(feature = "mysql-async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mysql-async")))]
+pub mod mysql_async;
 #[cfg(feature = "postgres")]
 #[cfg_attr(docsrs, doc(cfg(feature = "postgres")))]
 pub mod postgres;
@@ -253,10 +319,23 @@ pub mod tokio_postgres;
 mod test;
 
 pub use error::Error;
+pub use verify::verify;
 
 mod traits;
 pub use traits::*;
 
+#[cfg(all(
+    feature = "derive",
+    any(feature = "postgres", feature = "tokio-postgres")
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(all(
+        feature = "derive",
+        any(feature = "postgres", feature = "tokio-postgres")
+    )))
+)]
+pub use aykroyd_derive::Notification;
 #[cfg(feature = "derive")]
 #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
 pub use aykroyd_derive::{FromRow, Query, QueryOne, Statement};
@@ -0,0 +1,403 @@
+//! List parameters, for `IN` conditions.
+//!
+//! Write the condition as `WHERE id IN $1` (or `IN ?` for MySQL) and mark
+//! the field with `#[aykroyd(expand)]`.  On PostgreSQL, which has arrays,
+//! the list is bound to a single parameter, as `WHERE id = ANY($1)`.
+//! Elsewhere, when the query is run, the placeholder is expanded to one
+//! for each item of the list, `IN ($1, $2, $3)`, each item is bound to
+//! its own parameter, and later placeholders are renumbered to match.
+//! Either way, one query type can be used with any client.
+#![cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::{FromRow, Query};
+use aykroyd::list::InList;
+use aykroyd::query::QueryText;
+
+# #[derive(FromRow)]
+# struct Todo;
+#[derive(Query)]
+#[aykroyd(row(Todo), text = "SELECT id, label FROM todo WHERE id IN $1 AND done = $2")]
+struct GetTodos<'a> {
+    #[aykroyd(expand)]
+    ids: InList<'a, i64>,
+    done: bool,
+}
+
+let query = GetTodos { ids: InList(&[1, 2, 3]), done: false };
+assert_eq!(
+    "SELECT id, label FROM todo WHERE id IN ($1, $2, $3) AND done = $4",
+    query.query_text(),
+);
+```
+"##
+)]
+//!
+//! The placeholder of a list field must directly follow `IN` or `NOT IN`,
+//! or the derive macro reports an error.
+//!
+//! On PostgreSQL the text is the same for every length of list, so the
+//! query is prepared once.  Elsewhere, every length of list has different
+//! query text, so each is prepared separately.  Since the text isn't
+//! constant, queries with list parameters can't be prepared in advance
+//! with `prepare`.
+//!
+//! An empty list is expanded to `IN (NULL)`, which matches no rows.
+//! After `NOT IN` it's expanded to an empty subquery instead, which
+//! matches every row, since `NOT IN (NULL)` would match none.  On
+//! PostgreSQL, `NOT IN $1` is bound as `<> ALL($1)`, which also matches
+//! every row for an empty array.
+
+use crate::client::Client;
+
+/// A list of values to bind to a single placeholder.
+///
+/// See the [module docs](crate::list) for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InList<'a, T>(pub &'a [T]);
+
+/// A type that holds a list of parameter values.
+pub trait ParamList: Sync {
+    type Item;
+
+    /// The values in the list.
+    fn items(&self) -> &[Self::Item];
+}
+
+impl<'a, T: Sync> ParamList for InList<'a, T> {
+    type Item = T;
+
+    fn items(&self) -> &[T] {
+        self.0
+    }
+}
+
+impl<T: Sync> ParamList for &[T] {
+    type Item = T;
+
+    fn items(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T: Sync> ParamList for Vec<T> {
+    type Item = T;
+
+    fn items(&self) -> &[T] {
+        self
+    }
+}
+
+/// A list that can be converted to database params.
+///
+/// Each client implements this: with one param for the whole list if
+/// the database has arrays (see [`Client::ARRAY_PARAMS`]), and one for
+/// each item otherwise.
+pub trait ToParamList<C: Client>: ParamList {
+    fn to_params(&self) -> Vec<C::Param<'_>>;
+}
+
+/// Bind list placeholders in query text the way a client of type `C`
+/// can: as an array if the database has them, expanded otherwise.
+///
+/// For each list, give the index of the parameter (counting from one)
+/// and the length of the list.
+pub fn bind<C: Client>(text: &str, lists: &[(usize, usize)]) -> String {
+    if C::ARRAY_PARAMS {
+        let lists: Vec<_> = lists.iter().map(|&(index, _)| index).collect();
+        arrays(text, &lists)
+    } else {
+        expand(text, lists)
+    }
+}
+
+/// Rewrite list placeholders to take an array, `IN $1` to `= ANY($1)`
+/// and `NOT IN $1` to `<> ALL($1)`.  The numbering is unchanged.
+pub(crate) fn arrays(text: &str, lists: &[usize]) -> String {
+    let mut result = String::with_capacity(text.len());
+    for piece in pieces(text) {
+        let param = match piece {
+            Piece::Text(text) => {
+                result.push_str(text);
+                continue;
+            }
+            Piece::Param(param) => param,
+        };
+
+        let placeholder = match param.prefix {
+            Some(prefix) => format!("{prefix}{}", param.index),
+            None => "?".into(),
+        };
+
+        let before_in = strip_keyword(&result, "in").filter(|_| lists.contains(&param.index));
+        match before_in {
+            Some(before) => {
+                let (before, operator) = match strip_keyword(before, "not") {
+                    Some(before) => (before, "<> ALL"),
+                    None => (before, "= ANY"),
+                };
+                let len = before.len();
+                result.truncate(len);
+                result.push_str(&format!("{operator}({placeholder})"));
+            }
+            None => result.push_str(&placeholder),
+        }
+    }
+    result
+}
+
+/// Expand list placeholders in query text.
+///
+/// For each list, give the index of the parameter (counting from one)
+/// and the length of the list.  Only placeholders that directly follow
+/// `IN` are expanded.
+pub fn expand(text: &str, lists: &[(usize, usize)]) -> String {
+    let pieces = pieces(text);
+
+    let expanded: Vec<(usize, usize)> = lists
+        .iter()
+        .copied()
+        .filter(|(index, _)| {
+            pieces.iter().any(|piece| match piece {
+                Piece::Param(param) => param.index == *index && param.after_in,
+                Piece::Text(_) => false,
+            })
+        })
+        .collect();
+
+    // The number of the first placeholder for a parameter, after the
+    // lists before it are expanded.
+    let number = |index: usize| {
+        expanded
+            .iter()
+            .filter(|(list, _)| *list < index)
+            .fold(index, |number, (_, len)| number + len - 1)
+    };
+
+    let mut result = String::with_capacity(text.len());
+    for piece in pieces {
+        let param = match piece {
+            Piece::Text(text) => {
+                result.push_str(text);
+                continue;
+            }
+            Piece::Param(param) => param,
+        };
+
+        let placeholder = |number: usize| match param.prefix {
+            Some(prefix) => format!("{prefix}{number}"),
+            None => "?".into(),
+        };
+
+        let list = expanded
+            .iter()
+            .find(|(index, _)| *index == param.index && param.after_in);
+        match list {
+            Some((_, 0)) if follows_not_in(&result) => result.push_str(NO_ROWS),
+            Some((_, 0)) => result.push_str("(NULL)"),
+            Some((index, len)) => {
+                let first = number(*index);
+                let placeholders: Vec<_> = (first..first + len).map(placeholder).collect();
+                result.push('(');
+                result.push_str(&placeholders.join(", "));
+                result.push(')');
+            }
+            None => result.push_str(&placeholder(number(param.index))),
+        }
+    }
+
+    result
+}
+
+/// A subquery with no rows, for an empty list after `NOT IN`.  Unlike
+/// `(NULL)`, nothing is ever in it, so every row matches.
+const NO_ROWS: &str = "(SELECT NULL FROM (SELECT 1) AS no_rows WHERE 1 = 0)";
+
+/// Expand list placeholders to a single item, so the text can be prepared.
+pub(crate) fn expand_single(text: &str, lists: &[usize]) -> String {
+    let lists: Vec<_> = lists.iter().map(|&index| (index, 1)).collect();
+    expand(text, &lists)
+}
+
+pub(crate) enum Piece<'a> {
+    Text(&'a str),
+    Param(Param),
+}
+
+pub(crate) struct Param {
+    /// The prefix of a numbered placeholder, or `None` for a bare `?`.
+    pub(crate) prefix: Option<char>,
+    pub(crate) index: usize,
+    pub(crate) after_in: bool,
+}
+
+/// Split query text into placeholders and the text between them.
+pub(crate) fn pieces(text: &str) -> Vec<Piece<'_>> {
+    let mut pieces = vec![];
+    let mut quote = None;
+    let mut positional = 0;
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((at, c)) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '$' | '?') => {
+                let mut end = at + 1;
+                while let Some((next, digit)) = chars.peek().copied() {
+                    if !digit.is_ascii_digit() {
+                        break;
+                    }
+                    end = next + 1;
+                    chars.next();
+                }
+
+                let (prefix, index) = match text[at + 1..end].parse() {
+                    Ok(index) => (Some(c), index),
+                    Err(_) if c == '?' => {
+                        positional += 1;
+                        (None, positional)
+                    }
+                    Err(_) => continue,
+                };
+
+                pieces.push(Piece::Text(&text[start..at]));
+                pieces.push(Piece::Param(Param {
+                    prefix,
+                    index,
+                    after_in: follows_in(&text[..at]),
+                }));
+                start = end;
+            }
+            _ => {}
+        }
+    }
+    pieces.push(Piece::Text(&text[start..]));
+
+    pieces
+}
+
+/// Does the text end with the keyword `IN`?
+fn follows_in(text: &str) -> bool {
+    strip_keyword(text, "in").is_some()
+}
+
+/// Does the text end with the keywords `NOT IN`?
+fn follows_not_in(text: &str) -> bool {
+    match strip_keyword(text, "in") {
+        Some(text) => strip_keyword(text, "not").is_some(),
+        None => false,
+    }
+}
+
+/// The text before a keyword it ends with, ignoring trailing whitespace.
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let text = text.trim_end();
+    let at = text.len().checked_sub(keyword.len())?;
+    if !text.get(at..)?.eq_ignore_ascii_case(keyword) {
+        return None;
+    }
+    let before = &text[..at];
+    match before.chars().next_back() {
+        Some(c) if c.is_alphanumeric() || c == '_' => None,
+        _ => Some(before),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbered() {
+        assert_eq!(
+            "SELECT * FROM t WHERE a = $1 AND id IN ($2, $3, $4) AND b = $5",
+            expand(
+                "SELECT * FROM t WHERE a = $1 AND id IN $2 AND b = $3",
+                &[(2, 3)],
+            ),
+        );
+        assert_eq!(
+            "SELECT * FROM t WHERE id in (?1, ?2) OR id IN (?3, ?4, ?5) AND b = ?6",
+            expand(
+                "SELECT * FROM t WHERE id in ?1 OR id IN ?2 AND b = ?3",
+                &[(1, 2), (2, 3)],
+            ),
+        );
+    }
+
+    #[test]
+    fn positional() {
+        assert_eq!(
+            "SELECT * FROM t WHERE a = ? AND id IN (?, ?) AND b = ?",
+            expand(
+                "SELECT * FROM t WHERE a = ? AND id IN ? AND b = ?",
+                &[(2, 2)],
+            ),
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(
+            "SELECT * FROM t WHERE id IN (NULL) AND b = $1",
+            expand("SELECT * FROM t WHERE id IN $1 AND b = $2", &[(1, 0)]),
+        );
+    }
+
+    #[test]
+    fn empty_not_in() {
+        assert_eq!(
+            "SELECT * FROM t WHERE id NOT IN (SELECT NULL FROM (SELECT 1) AS no_rows WHERE 1 = 0) \
+            AND b = ? AND c IN (NULL)",
+            expand(
+                "SELECT * FROM t WHERE id NOT IN ? AND b = ? AND c IN ?",
+                &[(1, 0), (3, 0)],
+            ),
+        );
+        assert_eq!(
+            "SELECT * FROM t WHERE knot IN (NULL)",
+            expand("SELECT * FROM t WHERE knot IN $1", &[(1, 0)]),
+        );
+    }
+
+    #[test]
+    fn single() {
+        assert_eq!(
+            "SELECT * FROM t WHERE id IN ($1) AND b = $2 AND c IN ($3)",
+            expand_single(
+                "SELECT * FROM t WHERE id IN $1 AND b = $2 AND c IN $3",
+                &[1, 3]
+            ),
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(
+            "SELECT * FROM t WHERE a = $1 AND id = ANY($2) AND b NOT IN ($3) AND c <> ALL($4)",
+            super::arrays(
+                "SELECT * FROM t WHERE a = $1 AND id IN $2 AND b NOT IN ($3) AND c not in $4",
+                &[2, 4],
+            ),
+        );
+    }
+
+    #[test]
+    fn not_expanded() {
+        assert_eq!(
+            "SELECT * FROM t WHERE id = ANY($1) AND b = $2",
+            expand("SELECT * FROM t WHERE id = ANY($1) AND b = $2", &[(1, 3)]),
+        );
+        assert_eq!(
+            "SELECT '$1', begin FROM t WHERE login $1 AND id IN ($2)",
+            expand(
+                "SELECT '$1', begin FROM t WHERE login $1 AND id IN ($2)",
+                &[(1, 2), (2, 2)],
+            ),
+        );
+    }
+}
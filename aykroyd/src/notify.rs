@@ -0,0 +1,174 @@
+//! Typed PostgreSQL notifications, with `LISTEN` and `NOTIFY`.
+//!
+//! A [`Notification`] is a payload type bound to a channel name.  Both
+//! PostgreSQL clients can `listen` for a notification type, `notify`
+//! listeners with a value, and receive the values sent to them.
+//!
+//! The derive macro sends a struct with a single field as text, using
+//! its `Display` and `FromStr` implementations.  A struct with no fields
+//! has an empty payload.  Otherwise, use the `json` attribute to send
+//! the payload as JSON, with the struct's serde implementations.  That
+//! needs the `json` feature.
+#![cfg_attr(
+    all(feature = "derive", feature = "json"),
+    doc = r##"
+
+```
+# use aykroyd::Notification;
+#[derive(Notification)]
+#[aykroyd(channel = "customer_updated")]
+struct CustomerUpdated(i32);
+
+#[derive(Notification, serde::Serialize, serde::Deserialize)]
+#[aykroyd(channel = "cache_invalidated", json)]
+struct CacheInvalidated {
+    table: String,
+    ids: Vec<i32>,
+}
+```
+"##
+)]
+//!
+//! With the synchronous client, `notifications` waits for values:
+//!
+//! ```ignore
+//! client.listen::<CustomerUpdated>()?;
+//! for notification in client.notifications::<CustomerUpdated>() {
+//!     let CustomerUpdated(id) = notification?;
+//! }
+//! ```
+//!
+//! The asynchronous client receives notifications on its connection,
+//! so split them off with `aykroyd::tokio_postgres::notifications` and
+//! spawn the connection as usual.
+//!
+//! ```ignore
+//! let (client, connection) = connect("host=localhost user=postgres", NoTls).await?;
+//! let (notifications, connection) = aykroyd::tokio_postgres::notifications(connection);
+//! tokio::spawn(connection);
+//!
+//! client.listen::<CacheInvalidated>().await?;
+//! let mut invalidated = notifications.subscribe::<CacheInvalidated>();
+//! while let Some(notification) = invalidated.next().await {
+//!     let CacheInvalidated { table, ids } = notification?;
+//! }
+//! ```
+
+use crate::error;
+
+/// The type of errors from a notification.
+pub type Error = error::Error<tokio_postgres::Error>;
+
+/// A notification payload, sent on a channel.
+///
+/// This can be derived automatically, see the [module docs](crate::notify).
+pub trait Notification: Sized {
+    /// The name of the channel.
+    const CHANNEL: &'static str;
+
+    /// Convert this value to the notification payload.
+    fn to_payload(&self) -> Result<String, Error>;
+
+    /// Convert a notification payload to a value.
+    fn from_payload(payload: &str) -> Result<Self, Error>;
+}
+
+/// Parse a text payload.
+pub fn from_text<T>(payload: &str) -> Result<T, Error>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    payload
+        .parse()
+        .map_err(|err| Error::from_column_str(format!("invalid notification payload: {err}"), None))
+}
+
+/// Serialize a value as a JSON payload.
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub fn to_json<T: serde::Serialize>(value: &T) -> Result<String, Error> {
+    serde_json::to_string(value)
+        .map_err(|err| Error::query_str(format!("unable to serialize notification: {err}"), None))
+}
+
+/// Deserialize a JSON payload.
+#[cfg(feature = "json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+pub fn from_json<T: serde::de::DeserializeOwned>(payload: &str) -> Result<T, Error> {
+    serde_json::from_str(payload)
+        .map_err(|err| Error::from_column_str(format!("invalid notification payload: {err}"), None))
+}
+
+/// The statement to start listening on a channel.
+pub(crate) fn listen_text<N: Notification>() -> String {
+    format!("LISTEN {}", quote(N::CHANNEL))
+}
+
+/// The statement to stop listening on a channel.
+pub(crate) fn unlisten_text<N: Notification>() -> String {
+    format!("UNLISTEN {}", quote(N::CHANNEL))
+}
+
+/// The statement to notify listeners on a channel.
+pub(crate) const NOTIFY_TEXT: &str = "SELECT pg_notify($1, $2)";
+
+/// Convert a notification, if it was sent on the channel.
+pub(crate) fn parse<N: Notification>(
+    notification: &tokio_postgres::Notification,
+) -> Option<Result<N, Error>> {
+    if notification.channel() == N::CHANNEL {
+        Some(N::from_payload(notification.payload()))
+    } else {
+        None
+    }
+}
+
+/// Quote a channel name, since `pg_notify` doesn't fold case.
+fn quote(channel: &str) -> String {
+    format!("\"{}\"", channel.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Invalidated(i32);
+
+    impl Notification for Invalidated {
+        const CHANNEL: &'static str = "Cache \"invalidated\"";
+
+        fn to_payload(&self) -> Result<String, Error> {
+            Ok(self.0.to_string())
+        }
+
+        fn from_payload(payload: &str) -> Result<Self, Error> {
+            from_text(payload).map(Invalidated)
+        }
+    }
+
+    #[test]
+    fn channel() {
+        assert_eq!(
+            "LISTEN \"Cache \"\"invalidated\"\"\"",
+            listen_text::<Invalidated>(),
+        );
+        assert_eq!(
+            "UNLISTEN \"Cache \"\"invalidated\"\"\"",
+            unlisten_text::<Invalidated>(),
+        );
+    }
+
+    #[test]
+    fn payload() {
+        assert_eq!(42, Invalidated::from_payload("42").unwrap().0);
+        assert!(Invalidated::from_payload("forty-two").is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_payload() {
+        let ids: Vec<i32> = from_json("[1,2,3]").unwrap();
+        assert_eq!("[1,2,3]", to_json(&ids).unwrap());
+    }
+}
@@ -0,0 +1,461 @@
+//! Query plans, as reported by `EXPLAIN`.
+//!
+//! Use the [`Explain`](crate::combinator::Explain) combinator to ask
+//! the database for the plan of a query, then collect the rows into a
+//! [`Plan`].  Each database reports plans in its own format, so they
+//! are reduced to a common tree of [`PlanNode`]s, with the operation,
+//! the table, and the index used, if any.
+//!
+//! That's enough to write tests that the hot queries of an application
+//! keep using their indexes as the schema changes.
+//!
+//! PostgreSQL and MySQL report plans as JSON, so explaining queries on
+//! those clients needs the `json` feature.
+//!
+//! ```
+//! use aykroyd::plan::{Plan, PlanNode};
+//!
+//! # let rows = vec![aykroyd::plan::PlanRow::new(None, None, vec![
+//! #     PlanNode::new("Index Scan", Some("todo"), Some("todo_label_idx"), vec![]),
+//! # ])];
+//! // let rows = client.query(&Explain::new(GetTodosByLabel("groceries")))?;
+//! let plan = Plan::from_rows(rows);
+//! assert!(plan.uses_index("todo_label_idx"));
+//! ```
+
+use crate::client::Client;
+use crate::error::Error;
+
+/// How a database client explains queries.
+pub trait Dialect: Client {
+    /// The text to explain the given query.
+    ///
+    /// With `analyze`, the query is run to gather actual statistics,
+    /// on databases that support that.
+    fn explain_text(query_text: &str, analyze: bool) -> String;
+
+    /// Read a row of `EXPLAIN` output.
+    fn plan_row(row: &Self::Row<'_>) -> Result<PlanRow, Error<Self::Error>>;
+}
+
+/// One row of `EXPLAIN` output.
+///
+/// PostgreSQL and MySQL return the whole plan in a single row, while
+/// SQLite returns one row per node, linked to its parent by id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanRow {
+    id: Option<i64>,
+    parent: Option<i64>,
+    nodes: Vec<PlanNode>,
+}
+
+impl PlanRow {
+    /// A row with the given nodes, with an id and parent id if they are
+    /// to be assembled into a tree.
+    pub fn new(id: Option<i64>, parent: Option<i64>, nodes: Vec<PlanNode>) -> Self {
+        PlanRow { id, parent, nodes }
+    }
+}
+
+/// A query plan.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    /// The top-level nodes of the plan.
+    pub roots: Vec<PlanNode>,
+}
+
+impl Plan {
+    /// Assemble the rows of `EXPLAIN` output into a plan.
+    pub fn from_rows(rows: Vec<PlanRow>) -> Self {
+        let mut ids = vec![];
+        let mut parents = vec![];
+        let mut slots = vec![];
+        for row in rows {
+            for node in row.nodes {
+                ids.push(row.id);
+                parents.push(row.parent);
+                slots.push(Some(node));
+            }
+        }
+
+        // Children come after their parents, so we can fill the tree
+        // in reverse, attaching each child to the nearest earlier parent.
+        for index in (0..slots.len()).rev() {
+            let parent = parents[index].and_then(|parent| {
+                (0..index)
+                    .rev()
+                    .find(|&candidate| ids[candidate] == Some(parent))
+            });
+            if let Some(parent) = parent {
+                let node = slots[index].take().expect("node is taken once");
+                let parent = slots[parent].as_mut().expect("parents come first");
+                parent.children.insert(0, node);
+            }
+        }
+
+        Plan {
+            roots: slots.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Iterate over every node, depth-first.
+    pub fn nodes(&self) -> impl Iterator<Item = &PlanNode> {
+        let mut stack: Vec<&PlanNode> = self.roots.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Does the plan use the named index?
+    pub fn uses_index(&self, index: &str) -> bool {
+        self.nodes()
+            .any(|node| node.index.as_deref() == Some(index))
+    }
+
+    /// The tables read in full, see [`PlanNode::is_full_scan`].
+    pub fn full_scans(&self) -> Vec<&str> {
+        self.nodes()
+            .filter(|node| node.is_full_scan())
+            .filter_map(|node| node.relation.as_deref())
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn write(f: &mut std::fmt::Formatter, node: &PlanNode, depth: usize) -> std::fmt::Result {
+            writeln!(f, "{:indent$}{node}", "", indent = depth * 2)?;
+            for child in &node.children {
+                write(f, child, depth + 1)?;
+            }
+            Ok(())
+        }
+
+        for root in &self.roots {
+            write(f, root, 0)?;
+        }
+        Ok(())
+    }
+}
+
+/// A step of a query plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanNode {
+    /// What the database does at this step, such as `Seq Scan` or `SEARCH`.
+    pub operation: String,
+    /// The table read, if any.
+    pub relation: Option<String>,
+    /// The index used, if any.
+    pub index: Option<String>,
+    /// The steps that feed this one.
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    /// A step with the given children.
+    pub fn new<S: Into<String>>(
+        operation: S,
+        relation: Option<&str>,
+        index: Option<&str>,
+        children: Vec<PlanNode>,
+    ) -> Self {
+        PlanNode {
+            operation: operation.into(),
+            relation: relation.map(Into::into),
+            index: index.map(Into::into),
+            children,
+        }
+    }
+
+    /// Does this step read every row of its table?
+    ///
+    /// That's a `Seq Scan` on PostgreSQL, a `SCAN` without an index on
+    /// SQLite, and an `ALL` access or `Table scan` on MySQL.  Other steps
+    /// aren't, such as a PostgreSQL `Bitmap Heap Scan`, which names the
+    /// table while the index is on its child `Bitmap Index Scan`.
+    pub fn is_full_scan(&self) -> bool {
+        match self.operation.as_str() {
+            "Seq Scan" | "ALL" | "Table scan" => true,
+            "SCAN" => self.index.is_none(),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for PlanNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(relation) = &self.relation {
+            write!(f, " on {relation}")?;
+        }
+        if let Some(index) = &self.index {
+            write!(f, " using {index}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a plan in PostgreSQL's `FORMAT JSON`.
+#[cfg(all(
+    feature = "json",
+    any(feature = "postgres", feature = "tokio-postgres")
+))]
+pub(crate) fn from_postgres_json(value: &serde_json::Value) -> Vec<PlanNode> {
+    fn node(plan: &serde_json::Value) -> PlanNode {
+        let field = |name| plan.get(name).and_then(serde_json::Value::as_str);
+        let children = plan
+            .get("Plans")
+            .and_then(serde_json::Value::as_array)
+            .map(|plans| plans.iter().map(node).collect())
+            .unwrap_or_default();
+        PlanNode::new(
+            field("Node Type").unwrap_or("Unknown"),
+            field("Relation Name"),
+            field("Index Name"),
+            children,
+        )
+    }
+
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|query| query.get("Plan"))
+        .map(node)
+        .collect()
+}
+
+/// Read a plan in MySQL's `FORMAT=JSON`, or the tree format of `ANALYZE`.
+#[cfg(all(feature = "json", feature = "mysql"))]
+pub(crate) fn from_mysql(text: &str) -> Vec<PlanNode> {
+    fn nodes(name: &str, value: &serde_json::Value) -> Vec<PlanNode> {
+        match value {
+            serde_json::Value::Object(object) if object.contains_key("table_name") => {
+                let field = |name| object.get(name).and_then(serde_json::Value::as_str);
+                let children = object
+                    .iter()
+                    .flat_map(|(name, value)| nodes(name, value))
+                    .collect();
+                vec![PlanNode::new(
+                    field("access_type").unwrap_or("table"),
+                    field("table_name"),
+                    field("key"),
+                    children,
+                )]
+            }
+            serde_json::Value::Object(object) => {
+                let children = object
+                    .iter()
+                    .flat_map(|(name, value)| nodes(name, value))
+                    .collect();
+                // Tables and array items are wrapped in an extra object.
+                match name {
+                    "" | "table" => children,
+                    _ => vec![PlanNode::new(name, None, None, children)],
+                }
+            }
+            serde_json::Value::Array(items) => {
+                let children = items.iter().flat_map(|item| nodes("", item)).collect();
+                vec![PlanNode::new(name, None, None, children)]
+            }
+            _ => vec![],
+        }
+    }
+
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(serde_json::Value::Object(object)) => object
+            .iter()
+            .flat_map(|(name, value)| nodes(name, value))
+            .collect(),
+        _ => from_tree(text),
+    }
+}
+
+/// Read a plan in MySQL's tree format, with nodes like `-> Table scan on t`.
+#[cfg(all(feature = "json", feature = "mysql"))]
+fn from_tree(text: &str) -> Vec<PlanNode> {
+    let mut stack: Vec<(usize, PlanNode)> = vec![];
+    let mut roots = vec![];
+
+    fn pop(stack: &mut Vec<(usize, PlanNode)>, roots: &mut Vec<PlanNode>, depth: usize) {
+        while stack.last().is_some_and(|(d, _)| *d >= depth) {
+            let (_, node) = stack.pop().expect("stack is not empty");
+            match stack.last_mut() {
+                Some((_, parent)) => parent.children.push(node),
+                None => roots.push(node),
+            }
+        }
+    }
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let operation = match trimmed.strip_prefix("-> ") {
+            Some(operation) => operation,
+            None => continue,
+        };
+        let depth = line.len() - trimmed.len();
+        pop(&mut stack, &mut roots, depth);
+
+        let operation = match operation.find("  (") {
+            Some(end) => &operation[..end],
+            None => operation,
+        };
+        let relation = after_word(operation, " on ");
+        let index = after_word(operation, " using ");
+        let operation = match operation.find(" on ") {
+            Some(end) => &operation[..end],
+            None => operation,
+        };
+        stack.push((depth, PlanNode::new(operation, relation, index, vec![])));
+    }
+    pop(&mut stack, &mut roots, 0);
+
+    roots
+}
+
+/// Read a row of SQLite's `EXPLAIN QUERY PLAN`, like `SEARCH t USING INDEX i (a=?)`.
+#[cfg(feature = "rusqlite")]
+pub(crate) fn from_sqlite(detail: &str) -> PlanNode {
+    let mut words = detail.split_whitespace();
+    let operation = words.next().unwrap_or_default();
+    let relation = match words.next() {
+        Some("TABLE") => words.next(),
+        relation => relation,
+    };
+    let relation = match operation {
+        "SCAN" | "SEARCH" => relation,
+        _ => None,
+    };
+    let index = after_word(detail, " INDEX ");
+    PlanNode::new(operation, relation, index, vec![])
+}
+
+#[cfg(any(all(feature = "json", feature = "mysql"), feature = "rusqlite"))]
+fn after_word<'a>(text: &'a str, word: &str) -> Option<&'a str> {
+    let start = text.find(word)? + word.len();
+    text[start..].split_whitespace().next()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn leaf(operation: &str, relation: Option<&str>, index: Option<&str>) -> PlanNode {
+        PlanNode::new(operation, relation, index, vec![])
+    }
+
+    #[test]
+    fn tree_from_rows() {
+        let rows = vec![
+            PlanRow::new(Some(2), Some(0), vec![leaf("SCAN", Some("a"), None)]),
+            PlanRow::new(
+                Some(5),
+                Some(0),
+                vec![leaf("SEARCH", Some("b"), Some("b_idx"))],
+            ),
+            PlanRow::new(Some(7), Some(5), vec![leaf("LIST", None, None)]),
+            PlanRow::new(Some(9), Some(0), vec![leaf("TEMP", None, None)]),
+        ];
+        let plan = Plan::from_rows(rows);
+
+        assert_eq!(
+            "SCAN on a\nSEARCH on b using b_idx\n  LIST\nTEMP\n",
+            plan.to_string(),
+        );
+        assert!(plan.uses_index("b_idx"));
+        assert!(!plan.uses_index("a_idx"));
+        assert_eq!(vec!["a"], plan.full_scans());
+    }
+
+    #[cfg(all(
+        feature = "json",
+        any(feature = "postgres", feature = "tokio-postgres")
+    ))]
+    #[test]
+    fn postgres_json() {
+        let value = serde_json::json!([{
+            "Plan": {
+                "Node Type": "Nested Loop",
+                "Plans": [
+                    { "Node Type": "Seq Scan", "Relation Name": "a" },
+                    { "Node Type": "Index Scan", "Relation Name": "b", "Index Name": "b_pkey" },
+                    {
+                        "Node Type": "Bitmap Heap Scan",
+                        "Relation Name": "c",
+                        "Plans": [
+                            { "Node Type": "Bitmap Index Scan", "Index Name": "c_idx" }
+                        ]
+                    }
+                ]
+            }
+        }]);
+        let plan = Plan {
+            roots: from_postgres_json(&value),
+        };
+        assert_eq!(
+            "Nested Loop\n  Seq Scan on a\n  Index Scan on b using b_pkey\n  \
+                Bitmap Heap Scan on c\n    Bitmap Index Scan using c_idx\n",
+            plan.to_string(),
+        );
+        assert!(plan.uses_index("c_idx"));
+        assert_eq!(vec!["a"], plan.full_scans());
+    }
+
+    #[cfg(all(feature = "json", feature = "mysql"))]
+    #[test]
+    fn mysql() {
+        let json = r#"{
+            "query_block": {
+                "select_id": 1,
+                "nested_loop": [
+                    { "table": { "table_name": "a", "access_type": "ALL" } },
+                    { "table": { "table_name": "b", "access_type": "ref", "key": "b_idx" } }
+                ]
+            }
+        }"#;
+        let plan = Plan {
+            roots: from_mysql(json),
+        };
+        assert_eq!(
+            "query_block\n  nested_loop\n    ALL on a\n    ref on b using b_idx\n",
+            plan.to_string(),
+        );
+        assert_eq!(vec!["a"], plan.full_scans());
+
+        let tree = "-> Nested loop inner join  (cost=1.2 rows=1)\n    \
+            -> Table scan on a  (cost=0.35 rows=1)\n    \
+            -> Index lookup on b using b_idx (id=a.id)  (cost=0.35 rows=1)\n";
+        let plan = Plan {
+            roots: from_mysql(tree),
+        };
+        assert_eq!(
+            "Nested loop inner join\n  Table scan on a\n  Index lookup on b using b_idx\n",
+            plan.to_string(),
+        );
+        assert_eq!(vec!["a"], plan.full_scans());
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn sqlite() {
+        assert_eq!(leaf("SCAN", Some("todo"), None), from_sqlite("SCAN todo"));
+        assert_eq!(
+            leaf("SCAN", Some("todo"), None),
+            from_sqlite("SCAN TABLE todo")
+        );
+        assert_eq!(
+            leaf("SEARCH", Some("todo"), Some("todo_label_idx")),
+            from_sqlite("SEARCH todo USING INDEX todo_label_idx (label=?)"),
+        );
+        assert_eq!(
+            leaf("SEARCH", Some("todo"), Some("todo_label_idx")),
+            from_sqlite("SEARCH todo USING COVERING INDEX todo_label_idx (label=?)"),
+        );
+        assert_eq!(
+            leaf("USE", None, None),
+            from_sqlite("USE TEMP B-TREE FOR ORDER BY")
+        );
+    }
+}
@@ -1,20 +1,36 @@
 //! A synchronous client for PostgreSQL.
 
+use std::any::type_name;
+use std::time::Instant;
+
+use crate::bulk::InsertValues;
 use crate::client::{FromColumnIndexed, FromColumnNamed, ToParam};
-use crate::query::StaticQueryText;
-use crate::{error, FromRow, Query, QueryOne, Statement};
+use crate::combinator::ReturningId;
+use crate::comment::Comments;
+use crate::hook::{Hook, Hooks};
+use crate::list::{InList, ToParamList};
+use crate::notify::Notification;
+#[cfg(feature = "json")]
+use crate::plan;
+use crate::query::{Returning, StaticQueryText, ToParams};
+use crate::stats::Stats;
+use crate::verify::{ColumnType, Description, ParamType};
+use crate::{
+    builder, bulk, cache, copy, debug, error, notify, replica, trace, verify, Query, QueryRows,
+    QueryRowsOne, Statement,
+};
 
 /// The type of errors from a `Client`.
 pub type Error = error::Error<tokio_postgres::Error>;
 
+/// The OpenTelemetry name of the database, for tracing spans.
+const DB_SYSTEM: &str = "postgresql";
+
 impl<T> FromColumnIndexed<Client> for T
 where
     T: tokio_postgres::types::FromSqlOwned,
 {
-    fn from_column(
-        row: &tokio_postgres::Row,
-        index: usize,
-    ) -> Result<Self, Error> {
+    fn from_column(row: &tokio_postgres::Row, index: usize) -> Result<Self, Error> {
         row.try_get(index).map_err(Error::from_column)
     }
 }
@@ -23,10 +39,7 @@ impl<T> FromColumnNamed<Client> for T
 where
     T: tokio_postgres::types::FromSqlOwned,
 {
-    fn from_column(
-        row: &tokio_postgres::Row,
-        name: &str,
-    ) -> Result<Self, Error> {
+    fn from_column(row: &tokio_postgres::Row, name: &str) -> Result<Self, Error> {
         row.try_get(name).map_err(Error::from_column)
     }
 }
@@ -40,10 +53,61 @@ where
     }
 }
 
+impl<'a, T> ToParamList<Client> for InList<'a, T>
+where
+    T: tokio_postgres::types::ToSql + Sync,
+{
+    fn to_params(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![&self.0]
+    }
+}
+
+impl<T> ToParamList<Client> for &[T]
+where
+    T: tokio_postgres::types::ToSql + Sync,
+{
+    fn to_params(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![self]
+    }
+}
+
+impl<T> ToParamList<Client> for Vec<T>
+where
+    T: tokio_postgres::types::ToSql + Sync,
+{
+    fn to_params(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![self]
+    }
+}
+
+impl<T> ParamType<Client> for T
+where
+    T: tokio_postgres::types::ToSql,
+{
+    fn accepts(ty: &tokio_postgres::types::Type) -> bool {
+        <T as tokio_postgres::types::ToSql>::accepts(ty)
+    }
+}
+
+impl<T> ColumnType<Client> for T
+where
+    T: tokio_postgres::types::FromSqlOwned,
+{
+    fn accepts(ty: &tokio_postgres::types::Type) -> bool {
+        <T as tokio_postgres::types::FromSql>::accepts(ty)
+    }
+
+    fn accepts_null() -> bool {
+        <T as tokio_postgres::types::FromSql>::from_sql_null(&tokio_postgres::types::Type::TEXT)
+            .is_ok()
+    }
+}
+
 /// A synchronous PostgreSQL client.
 pub struct Client {
     client: postgres::Client,
-    statements: std::collections::HashMap<String, tokio_postgres::Statement>,
+    statements: cache::Statements<tokio_postgres::Statement>,
+    hooks: Hooks,
 }
 
 impl AsMut<postgres::Client> for Client {
@@ -56,6 +120,88 @@ impl crate::client::Client for Client {
     type Row<'a> = tokio_postgres::Row;
     type Param<'a> = &'a (dyn tokio_postgres::types::ToSql + Sync);
     type Error = tokio_postgres::Error;
+
+    const ARRAY_PARAMS: bool = true;
+}
+
+impl builder::Placeholder for Client {
+    fn placeholder(index: usize) -> String {
+        format!("${index}")
+    }
+
+    fn literal_question_mark() -> bool {
+        true
+    }
+}
+
+impl debug::Quote for Client {
+    fn quote_bytes(value: &[u8]) -> String {
+        let hex: String = value.iter().map(|byte| format!("{byte:02x}")).collect();
+        format!("'\\x{hex}'::bytea")
+    }
+}
+
+impl bulk::ParamLimit for Client {
+    fn param_limit() -> usize {
+        65535
+    }
+}
+
+#[cfg(feature = "json")]
+impl plan::Dialect for Client {
+    fn explain_text(query_text: &str, analyze: bool) -> String {
+        let analyze = if analyze { "ANALYZE, " } else { "" };
+        format!("EXPLAIN ({analyze}FORMAT JSON) {query_text}")
+    }
+
+    fn plan_row(row: &tokio_postgres::Row) -> Result<plan::PlanRow, Error> {
+        let value: serde_json::Value = row.try_get(0).map_err(Error::from_column)?;
+        Ok(plan::PlanRow::new(
+            None,
+            None,
+            plan::from_postgres_json(&value),
+        ))
+    }
+}
+
+impl verify::ClientTypes for Client {
+    type Type = tokio_postgres::types::Type;
+}
+
+impl verify::Describe for Client {
+    /// Prepares the statement, and looks up the nullability of any
+    /// result columns that come directly from a table.
+    fn describe(
+        &mut self,
+        query_text: &str,
+    ) -> Result<Description<tokio_postgres::types::Type>, Error> {
+        let statement = self.prepare_internal(None, query_text)?;
+
+        let params = statement.params().iter().cloned().map(Some).collect();
+
+        let mut columns = vec![];
+        for column in statement.columns() {
+            let nullable = match (column.table_oid(), column.column_id()) {
+                (Some(table_oid), Some(column_id)) => self
+                    .client
+                    .query_opt(
+                        "SELECT NOT attnotnull FROM pg_catalog.pg_attribute \
+                        WHERE attrelid = $1 AND attnum = $2",
+                        &[&table_oid, &column_id],
+                    )
+                    .map_err(Error::query)?
+                    .map(|row| row.get(0)),
+                _ => None,
+            };
+            columns.push(verify::Column {
+                name: column.name().into(),
+                ty: Some(column.type_().clone()),
+                nullable,
+            });
+        }
+
+        Ok(Description { params, columns })
+    }
 }
 
 impl AsRef<postgres::Client> for Client {
@@ -73,8 +219,39 @@ impl From<postgres::Client> for Client {
 impl Client {
     /// Create a new `Client` from a `postgres::Client`.
     pub fn new(client: postgres::Client) -> Self {
-        let statements = std::collections::HashMap::new();
-        Client { client, statements }
+        let statements = cache::Statements::default();
+        let hooks = Hooks::default();
+        Client {
+            client,
+            statements,
+            hooks,
+        }
+    }
+
+    /// Add a hook to run around each query, on this client and its transactions.
+    ///
+    /// See the [`hook`](crate::hook) module for details.
+    pub fn add_hook<H: Hook + 'static>(&mut self, hook: H) {
+        self.hooks.add(hook);
+    }
+
+    /// Collect statistics for each query, on this client and its transactions.
+    ///
+    /// See the [`stats`](crate::stats) module for details.
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.hooks.set_stats(stats);
+    }
+
+    /// The statistics collected for this client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.hooks.stats()
+    }
+
+    /// Append a comment to each query, on this client and its transactions.
+    ///
+    /// See the [`comment`](crate::comment) module for details.
+    pub fn set_comments(&mut self, comments: Comments) {
+        self.hooks.set_comments(comments);
     }
 
     /// A convenience function which parses a configuration string into a `Config` and then connects to the database.
@@ -97,21 +274,53 @@ impl Client {
         T::Stream: Send,
         <T::TlsConnect as postgres::tls::TlsConnect<postgres::Socket>>::Future: Send,
     {
-        let client = postgres::Client::connect(params, tls_mode)
-            .map_err(Error::connect)?;
+        let client = postgres::Client::connect(params, tls_mode).map_err(Error::connect)?;
         Ok(Self::new(client))
     }
 
-    fn prepare_internal<S: Into<String>>(
+    /// The hooks, for [`any::Client`](crate::any::Client) to replace.
+    pub(crate) fn hooks_mut(&mut self) -> &mut Hooks {
+        &mut self.hooks
+    }
+
+    pub(crate) fn prepare_internal<S: Into<String>>(
         &mut self,
+        query_name: Option<&str>,
         query_text: S,
     ) -> Result<postgres::Statement, Error> {
-        match self.statements.entry(query_text.into()) {
-            std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.get().clone()),
-            std::collections::hash_map::Entry::Vacant(entry) => {
-                let statement = self.client.prepare(entry.key()).map_err(Error::prepare)?;
-                Ok(entry.insert(statement).clone())
+        let query_text = query_text.into();
+        if let Some(statement) = self.statements.get(&query_text) {
+            trace::cache_hit(true);
+            return Ok(statement);
+        }
+
+        trace::cache_hit(false);
+        let statement = match query_name {
+            Some(query_name) => {
+                let annotated = self.hooks.annotate(query_name, query_text.clone());
+                self.client.prepare(&annotated)
+            }
+            None => self.client.prepare(&query_text),
+        }
+        .map_err(Error::prepare)?;
+        self.statements.insert(query_text, statement.clone());
+        Ok(statement)
+    }
+
+    /// Prepare a query to run.  If its comments have per-call tags, it's
+    /// prepared for this call only, otherwise it goes through the cache.
+    fn prepare_query(
+        &mut self,
+        query_name: &str,
+        query_text: String,
+        once: Option<String>,
+    ) -> Result<postgres::Statement, Error> {
+        match once {
+            Some(annotated) => {
+                trace::cache_hit(false);
+                self.client.prepare(&annotated).map_err(Error::prepare)
             }
+            None => self.prepare_internal(Some(query_name), query_text),
         }
     }
 
@@ -141,7 +350,54 @@ impl Client {
     /// # }
     /// ```
     pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
-        self.prepare_internal(S::QUERY_TEXT)?;
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), S::QUERY_TEXT);
+        span.in_scope(|| {
+            self.prepare_internal(Some(type_name::<S>()), S::QUERY_TEXT)?;
+            Ok(())
+        })
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Self> + Returning,
+        S::Id: FromColumnIndexed<Self>,
+    {
+        self.query_one(&ReturningId::new(statement))
+    }
+
+    /// Prepares every query in the [`registry`](crate::registry).
+    ///
+    /// Call this after connecting to catch invalid query text at
+    /// startup rather than first use.  It also warms the statement cache,
+    /// but that holds at most 256 statements.
+    /// Every registered query is prepared, including those of other
+    /// crates, so they should all be meant for this database.  If not,
+    /// use [`prepare_all_matching`](Self::prepare_all_matching).
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub fn prepare_all(&mut self) -> Result<(), Error> {
+        self.prepare_all_matching(|_| true)
+    }
+
+    /// Prepares the queries in the [`registry`](crate::registry) that
+    /// match the filter.
+    ///
+    /// Use this when not every registered query is meant for this
+    /// database, e.g. `|query| query.in_module("my_app::sqlite")`.
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub fn prepare_all_matching<F>(&mut self, mut filter: F) -> Result<(), Error>
+    where
+        F: FnMut(&crate::registry::QueryInfo) -> bool,
+    {
+        for query in crate::registry::queries().filter(|query| filter(query)) {
+            let query_text = query.text_for::<Self>();
+            let span = trace::Span::new(DB_SYSTEM, "prepare", query.name(), &query_text);
+            span.in_scope(|| self.prepare_internal(Some(query.name()), query_text))?;
+        }
         Ok(())
     }
 
@@ -175,20 +431,23 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn query<Q: Query<Self>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Vec<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text())?;
+    pub fn query<Q: QueryRows<Self, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run(
+            |rows| rows.len() as u64,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<Q>(), query_text, once)?;
 
-        let rows = self
-            .client
-            .query(&statement, params)
-            .map_err(Error::query)?;
+                let rows = self
+                    .client
+                    .query(&statement, params)
+                    .map_err(Error::query)?;
 
-        FromRow::from_rows(&rows)
+                query.map_rows(&rows)
+            },
+        )
     }
 
     /// Executes a statement which returns a single row, returning it.
@@ -220,20 +479,23 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn query_one<Q: QueryOne<Self>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Q::Row, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text())?;
+    pub fn query_one<Q: QueryRowsOne<Self, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_one", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run(
+            |_| 1,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<Q>(), query_text, once)?;
 
-        let row = self
-            .client
-            .query_one(&statement, params)
-            .map_err(Error::query)?;
+                let row = self
+                    .client
+                    .query_one(&statement, params)
+                    .map_err(Error::query)?;
 
-        FromRow::from_row(&row)
+                query.map_row(&row)
+            },
+        )
     }
 
     /// Executes a statement which returns zero or one rows, returning it.
@@ -266,20 +528,26 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn query_opt<Q: QueryOne<Self>>(
+    pub fn query_opt<Q: QueryRowsOne<Self, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Option<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text())?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_opt", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run(
+            |row| row.is_some() as u64,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<Q>(), query_text, once)?;
 
-        let row = self
-            .client
-            .query_opt(&statement, params)
-            .map_err(Error::query)?;
+                let row = self
+                    .client
+                    .query_opt(&statement, params)
+                    .map_err(Error::query)?;
 
-        row.map(|row| FromRow::from_row(&row)).transpose()
+                row.map(|row| query.map_row(&row)).transpose()
+            },
+        )
     }
 
     /// Executes a statement, returning the number of rows modified.
@@ -305,66 +573,305 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn execute<S: Statement<Self>>(
-        &mut self,
-        statement: &S,
-    ) -> Result<u64, Error> {
-        let params = statement.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(statement.query_text())?;
+    pub fn execute<S: Statement<Self>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "execute", statement)?;
+        let once = self.hooks.annotate_once(type_name::<S>(), &query_text);
+        span.run(
+            |rows_affected| *rows_affected,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<S>(), query_text, once)?;
+
+                let rows_affected = self
+                    .client
+                    .execute(&statement, params)
+                    .map_err(Error::query)?;
+
+                Ok(rows_affected)
+            },
+        )
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit, all in one transaction.  Returns the total number of rows
+    /// affected.  See the [`bulk`](crate::bulk) module for details.
+    pub fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Self>,
+    {
+        let mut txn = self.transaction()?;
+        let rows_affected = txn.insert_many(rows)?;
+        txn.commit()?;
+        Ok(rows_affected)
+    }
+
+    /// Copies rows into a table, returning the number of rows copied.
+    ///
+    /// The table may be followed by a list of columns.  The rows are
+    /// sent in the binary format, with the column types looked up by
+    /// preparing a query of the same columns.  See the
+    /// [`copy`](crate::copy) module for details.
+    pub fn copy_in<T: ToParams<Self>>(&mut self, table: &str, rows: &[T]) -> Result<u64, Error> {
+        let statement = self.prepare_internal(None, copy::select_text(table))?;
+        let types = copy::column_types(&statement);
 
-        let rows_affected = self
+        let writer = self
             .client
-            .execute(&statement, params)
+            .copy_in(&copy::copy_in_text(table))
             .map_err(Error::query)?;
 
-        Ok(rows_affected)
+        copy_in_rows(writer, &types, rows)
+    }
+
+    /// Copies out the rows of a query.
+    ///
+    /// The rows are read in the binary format, so they must load their
+    /// columns by index.  See the [`copy`](crate::copy) module for details.
+    pub fn copy_out<Q>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error>
+    where
+        Q: StaticQueryText + Query<copy::Binary>,
+    {
+        let statement = self.prepare_internal(None, Q::QUERY_TEXT)?;
+        let types = copy::column_types(&statement);
+
+        let reader = self
+            .client
+            .copy_out(&copy::copy_out_text(Q::QUERY_TEXT))
+            .map_err(Error::query)?;
+
+        copy_out_rows(reader, &types, query)
+    }
+
+    /// Listens for notifications of type `N`, on its channel.
+    ///
+    /// See the [`notify`](crate::notify) module for details.
+    pub fn listen<N: Notification>(&mut self) -> Result<(), Error> {
+        self.client
+            .batch_execute(&notify::listen_text::<N>())
+            .map_err(Error::query)
+    }
+
+    /// Stops listening for notifications of type `N`.
+    pub fn unlisten<N: Notification>(&mut self) -> Result<(), Error> {
+        self.client
+            .batch_execute(&notify::unlisten_text::<N>())
+            .map_err(Error::query)
+    }
+
+    /// Sends a notification to any listeners on its channel.
+    pub fn notify<N: Notification>(&mut self, notification: &N) -> Result<(), Error> {
+        let payload = notification.to_payload()?;
+        let statement = self.prepare_internal(None, notify::NOTIFY_TEXT)?;
+
+        self.client
+            .execute(&statement, &[&N::CHANNEL, &payload])
+            .map_err(Error::query)?;
+
+        Ok(())
+    }
+
+    /// Waits for notifications of type `N`.
+    ///
+    /// Notifications on other channels are skipped.  Use `listen` first
+    /// to receive any notifications at all.
+    pub fn notifications<N: Notification>(&mut self) -> Notifications<'_, N> {
+        Notifications {
+            notifications: self.client.notifications(),
+            notification: std::marker::PhantomData,
+        }
     }
 
     /// Begins a new database transaction.
     ///
     /// The transaction will roll back by default - use the `commit` method to commit it.
-    pub fn transaction(&mut self) -> Result<Transaction, Error> {
+    pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
         Ok(Transaction {
             txn: self.client.transaction().map_err(Error::transaction)?,
             statements: &mut self.statements,
+            hooks: self.hooks.clone(),
         })
     }
 }
 
+/// An iterator of notifications of one type.
+///
+/// Blocks waiting for each notification.  See [`Client::notifications`].
+pub struct Notifications<'a, N> {
+    notifications: postgres::Notifications<'a>,
+    notification: std::marker::PhantomData<fn() -> N>,
+}
+
+impl<'a, N: Notification> Iterator for Notifications<'a, N> {
+    type Item = Result<N, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use postgres::fallible_iterator::FallibleIterator;
+
+        loop {
+            match self.notifications.blocking_iter().next() {
+                Ok(Some(notification)) => {
+                    if let Some(notification) = notify::parse(&notification) {
+                        return Some(notification);
+                    }
+                }
+                Ok(None) => return None,
+                Err(err) => return Some(Err(Error::query(err))),
+            }
+        }
+    }
+}
+
 /// A synchronous PostgreSQL transaction.
 ///
 /// Transactions will implicitly roll back by default when dropped. Use the
 /// `commit` method to commit the changes made in the transaction.
 pub struct Transaction<'a> {
     txn: postgres::Transaction<'a>,
-    statements: &'a mut std::collections::HashMap<String, tokio_postgres::Statement>,
+    statements: &'a mut cache::Statements<tokio_postgres::Statement>,
+    hooks: Hooks,
 }
 
 impl<'a> Transaction<'a> {
+    /// The statistics collected for the client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.hooks.stats()
+    }
+
     fn prepare_internal<S: Into<String>>(
         &mut self,
+        query_name: Option<&str>,
         query_text: S,
     ) -> Result<tokio_postgres::Statement, Error> {
-        match self.statements.entry(query_text.into()) {
-            std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.get().clone()),
-            std::collections::hash_map::Entry::Vacant(entry) => {
-                let statement = self.txn.prepare(entry.key()).map_err(Error::prepare)?;
-                Ok(entry.insert(statement).clone())
+        let query_text = query_text.into();
+        if let Some(statement) = self.statements.get(&query_text) {
+            trace::cache_hit(true);
+            return Ok(statement);
+        }
+
+        trace::cache_hit(false);
+        let statement = match query_name {
+            Some(query_name) => {
+                let annotated = self.hooks.annotate(query_name, query_text.clone());
+                self.txn.prepare(&annotated)
             }
+            None => self.txn.prepare(&query_text),
+        }
+        .map_err(Error::prepare)?;
+        self.statements.insert(query_text, statement.clone());
+        Ok(statement)
+    }
+
+    /// Prepare a query to run.  If its comments have per-call tags, it's
+    /// prepared for this call only, otherwise it goes through the cache.
+    fn prepare_query(
+        &mut self,
+        query_name: &str,
+        query_text: String,
+        once: Option<String>,
+    ) -> Result<tokio_postgres::Statement, Error> {
+        match once {
+            Some(annotated) => {
+                trace::cache_hit(false);
+                self.txn.prepare(&annotated).map_err(Error::prepare)
+            }
+            None => self.prepare_internal(Some(query_name), query_text),
         }
     }
 
     /// Consumes the transaction, committing all changes made within it.
     pub fn commit(self) -> Result<(), Error> {
-        self.txn.commit().map_err(Error::transaction)
+        let span = trace::Span::transaction(DB_SYSTEM, "commit");
+        span.in_scope(|| self.txn.commit().map_err(Error::transaction))
     }
 
     /// Rolls the transaction back, discarding all changes made within it.
     ///
     /// This is equivalent to `Transaction`'s `Drop` implementation, but provides any error encountered to the caller.
     pub fn rollback(self) -> Result<(), Error> {
-        self.txn.rollback().map_err(Error::transaction)
+        let span = trace::Span::transaction(DB_SYSTEM, "rollback");
+        span.in_scope(|| self.txn.rollback().map_err(Error::transaction))
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit.  Returns the total number of rows affected.
+    pub fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Client>,
+    {
+        let mut rows_affected = 0;
+        for chunk in bulk::chunks::<Client, S>(rows) {
+            rows_affected += self.execute(&chunk)?;
+        }
+        Ok(rows_affected)
+    }
+
+    /// Copies rows into a table, returning the number of rows copied.
+    ///
+    /// The table may be followed by a list of columns.  The rows are
+    /// sent in the binary format, with the column types looked up by
+    /// preparing a query of the same columns.  See the
+    /// [`copy`](crate::copy) module for details.
+    pub fn copy_in<T: ToParams<Client>>(&mut self, table: &str, rows: &[T]) -> Result<u64, Error> {
+        let statement = self.prepare_internal(None, copy::select_text(table))?;
+        let types = copy::column_types(&statement);
+
+        let writer = self
+            .txn
+            .copy_in(&copy::copy_in_text(table))
+            .map_err(Error::query)?;
+
+        copy_in_rows(writer, &types, rows)
+    }
+
+    /// Copies out the rows of a query.
+    ///
+    /// The rows are read in the binary format, so they must load their
+    /// columns by index.  See the [`copy`](crate::copy) module for details.
+    pub fn copy_out<Q>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error>
+    where
+        Q: StaticQueryText + Query<copy::Binary>,
+    {
+        let statement = self.prepare_internal(None, Q::QUERY_TEXT)?;
+        let types = copy::column_types(&statement);
+
+        let reader = self
+            .txn
+            .copy_out(&copy::copy_out_text(Q::QUERY_TEXT))
+            .map_err(Error::query)?;
+
+        copy_out_rows(reader, &types, query)
+    }
+
+    /// Listens for notifications of type `N`, on its channel.
+    ///
+    /// See the [`notify`](crate::notify) module for details.
+    pub fn listen<N: Notification>(&mut self) -> Result<(), Error> {
+        self.txn
+            .batch_execute(&notify::listen_text::<N>())
+            .map_err(Error::query)
+    }
+
+    /// Stops listening for notifications of type `N`.
+    pub fn unlisten<N: Notification>(&mut self) -> Result<(), Error> {
+        self.txn
+            .batch_execute(&notify::unlisten_text::<N>())
+            .map_err(Error::query)
+    }
+
+    /// Sends a notification to any listeners on its channel.
+    pub fn notify<N: Notification>(&mut self, notification: &N) -> Result<(), Error> {
+        let payload = notification.to_payload()?;
+        let statement = self.prepare_internal(None, notify::NOTIFY_TEXT)?;
+
+        self.txn
+            .execute(&statement, &[&N::CHANNEL, &payload])
+            .map_err(Error::query)?;
+
+        Ok(())
     }
 
     /// Creates a new prepared statement.
@@ -394,8 +901,22 @@ impl<'a> Transaction<'a> {
     /// # }
     /// ```
     pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
-        self.prepare_internal(S::QUERY_TEXT)?;
-        Ok(())
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), S::QUERY_TEXT);
+        span.in_scope(|| {
+            self.prepare_internal(Some(type_name::<S>()), S::QUERY_TEXT)?;
+            Ok(())
+        })
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Client> + Returning,
+        S::Id: FromColumnIndexed<Client>,
+    {
+        self.query_one(&ReturningId::new(statement))
     }
 
     /// Executes a statement, returning the resulting rows.
@@ -429,17 +950,20 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn query<Q: Query<Client>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Vec<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text())?;
+    pub fn query<Q: QueryRows<Client, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run(
+            |rows| rows.len() as u64,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<Q>(), query_text, once)?;
 
-        let rows = self.txn.query(&statement, params).map_err(Error::query)?;
+                let rows = self.txn.query(&statement, params).map_err(Error::query)?;
 
-        FromRow::from_rows(&rows)
+                query.map_rows(&rows)
+            },
+        )
     }
 
     /// Executes a statement which returns a single row, returning it.
@@ -472,17 +996,23 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn query_one<Q: QueryOne<Client>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Q::Row, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text())?;
+    pub fn query_one<Q: QueryRowsOne<Client, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_one", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run(
+            |_| 1,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<Q>(), query_text, once)?;
 
-        let row = self.txn.query_one(&statement, params).map_err(Error::query)?;
+                let row = self
+                    .txn
+                    .query_one(&statement, params)
+                    .map_err(Error::query)?;
 
-        FromRow::from_row(&row)
+                query.map_row(&row)
+            },
+        )
     }
 
     /// Executes a statement which returns zero or one rows, returning it.
@@ -516,20 +1046,26 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn query_opt<Q: QueryOne<Client>>(
+    pub fn query_opt<Q: QueryRowsOne<Client, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Option<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text())?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_opt", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run(
+            |row| row.is_some() as u64,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<Q>(), query_text, once)?;
 
-        let row = self
-            .txn
-            .query_opt(&statement, params)
-            .map_err(Error::query)?;
+                let row = self
+                    .txn
+                    .query_opt(&statement, params)
+                    .map_err(Error::query)?;
 
-        row.map(|row| FromRow::from_row(&row)).transpose()
+                row.map(|row| query.map_row(&row)).transpose()
+            },
+        )
     }
 
     /// Executes a statement, returning the number of rows modified.
@@ -556,25 +1092,126 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn execute<S: Statement<Client>>(
+    pub fn execute<S: Statement<Client>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "execute", statement)?;
+        let once = self.hooks.annotate_once(type_name::<S>(), &query_text);
+        span.run(
+            |rows_affected| *rows_affected,
+            || {
+                let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+                let statement = self.prepare_query(type_name::<S>(), query_text, once)?;
+
+                let rows_affected = self.txn.execute(&statement, params).map_err(Error::query)?;
+
+                Ok(rows_affected)
+            },
+        )
+    }
+}
+
+fn copy_in_rows<T: ToParams<Client>>(
+    writer: postgres::CopyInWriter<'_>,
+    types: &[tokio_postgres::types::Type],
+    rows: &[T],
+) -> Result<u64, Error> {
+    let mut writer = postgres::binary_copy::BinaryCopyInWriter::new(writer, types);
+    for row in rows {
+        let params = row.to_params().unwrap_or_default();
+        copy::check_row(types, &params)?;
+        writer.write(&params).map_err(Error::query)?;
+    }
+    writer.finish().map_err(Error::query)
+}
+
+fn copy_out_rows<Q: Query<copy::Binary>>(
+    reader: postgres::CopyOutReader<'_>,
+    types: &[tokio_postgres::types::Type],
+    query: &Q,
+) -> Result<Vec<Q::Row>, Error> {
+    use postgres::fallible_iterator::FallibleIterator;
+
+    let mut rows = postgres::binary_copy::BinaryCopyOutIter::new(reader, types);
+    let mut result = vec![];
+    while let Some(row) = rows.next().map_err(Error::query)? {
+        result.push(query.map_row(&row)?);
+    }
+    Ok(result)
+}
+
+/// A client with read replicas.
+///
+/// See the [`replica`](crate::replica) module for details.
+pub type Replicated = replica::Replicated<Client>;
+
+impl replica::Replicated<Client> {
+    /// Prepares a statement on the primary and every replica.
+    pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        for client in self.clients() {
+            client.prepare::<S>()?;
+        }
+        Ok(())
+    }
+
+    /// Executes a query on a replica, returning the resulting rows.
+    ///
+    /// Queries marked `primary` run on the primary.
+    pub fn query<Q: QueryRows<Client, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let route = self.route(Q::PRIMARY);
+        let start = Instant::now();
+        let result = self.client(route).query(query);
+        self.record(route, start.elapsed());
+        result
+    }
+
+    /// Executes a query on a replica, returning a single row.
+    ///
+    /// Queries marked `primary` run on the primary.
+    pub fn query_one<Q: QueryRowsOne<Client, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let route = self.route(Q::PRIMARY);
+        let start = Instant::now();
+        let result = self.client(route).query_one(query);
+        self.record(route, start.elapsed());
+        result
+    }
+
+    /// Executes a query on a replica, returning zero or one rows.
+    ///
+    /// Queries marked `primary` run on the primary.
+    pub fn query_opt<Q: QueryRowsOne<Client, M>, M>(
         &mut self,
-        statement: &S,
-    ) -> Result<u64, Error> {
-        let params = statement.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(statement.query_text())?;
+        query: &Q,
+    ) -> Result<Option<Q::Row>, Error> {
+        let route = self.route(Q::PRIMARY);
+        let start = Instant::now();
+        let result = self.client(route).query_opt(query);
+        self.record(route, start.elapsed());
+        result
+    }
 
-        let rows_affected = self
-            .txn
-            .execute(&statement, params)
-            .map_err(Error::query)?;
+    /// Executes a statement on the primary, returning the number of rows modified.
+    pub fn execute<S: Statement<Client>>(&mut self, statement: &S) -> Result<u64, Error> {
+        self.primary().execute(statement)
+    }
 
-        Ok(rows_affected)
+    /// Inserts a row on the primary, returning its generated id.
+    ///
+    /// See [`Client::insert`] for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Client> + Returning,
+        S::Id: FromColumnIndexed<Client>,
+    {
+        self.primary().insert(statement)
+    }
+
+    /// Begins a new database transaction on the primary.
+    pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        self.primary().transaction()
     }
 }
 
 // TODO: not derive support
-#[cfg(all(test, feature ="derive"))]
+#[cfg(all(test, feature = "derive"))]
 mod test {
     use super::*;
 
@@ -596,23 +1233,153 @@ mod test {
     #[aykroyd(row((i32, String)), text = "SELECT id, label FROM test_postgres")]
     struct GetAllTodos;
 
+    #[derive(Query)]
+    #[aykroyd(
+        row((i32, String)),
+        text = "SELECT id, label FROM test_postgres WHERE id IN $1 AND label NOT IN $2"
+    )]
+    struct GetTodos<'a> {
+        #[aykroyd(expand)]
+        ids: crate::list::InList<'a, i32>,
+        #[aykroyd(expand)]
+        labels: Vec<String>,
+    }
+
+    #[test]
+    fn lists() {
+        let query = GetTodos {
+            ids: crate::list::InList(&[1, 2, 3]),
+            labels: vec!["a".into(), "b".into()],
+        };
+        assert_eq!(
+            "SELECT id, label FROM test_postgres WHERE id = ANY($1) AND label <> ALL($2)",
+            crate::query::QueryText::query_text_for::<Client>(&query),
+        );
+        assert_eq!(
+            Some(2),
+            ToParams::<Client>::to_params(&query).map(|params| params.len()),
+        );
+    }
+
     #[test]
     fn end_to_end() {
+        use crate::combinator::{Count, Exists};
+
         const TODO_TEXT: &str = "get things done, please!";
 
         let mut client = Client::connect(
             "host=localhost user=aykroyd_test password=aykroyd_test",
             NoTls,
-        ).unwrap();
+        )
+        .unwrap();
 
         client.execute(&CreateTodos).unwrap();
+        assert!(!client.query_one(&Exists::new(GetAllTodos)).unwrap());
 
         client.execute(&InsertTodo(TODO_TEXT)).unwrap();
 
         let todos = client.query(&GetAllTodos).unwrap();
         assert_eq!(1, todos.len());
         assert_eq!(TODO_TEXT, todos[0].1);
+        assert_eq!(1, client.query_one(&Count::new(GetAllTodos)).unwrap());
+        assert!(client.query_one(&Exists::new(GetAllTodos)).unwrap());
+
+        client.execute(&DropTodos).unwrap();
+    }
+
+    #[test]
+    fn verify() {
+        let mut client = Client::connect(
+            "host=localhost user=aykroyd_test password=aykroyd_test",
+            NoTls,
+        )
+        .unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+
+        let mismatches = crate::verify::<GetAllTodos, _>(&mut client).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+
+        let mismatches = crate::verify::<InsertTodo, _>(&mut client).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+
+        client.execute(&DropTodos).unwrap();
+    }
+
+    #[test]
+    fn copy() {
+        let mut client = Client::connect(
+            "host=localhost user=aykroyd_test password=aykroyd_test",
+            NoTls,
+        )
+        .unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+
+        let todos = [InsertTodo("first"), InsertTodo("second")];
+        let rows_copied = client.copy_in("test_postgres (label)", &todos).unwrap();
+        assert_eq!(2, rows_copied);
+
+        let todos = client.copy_out(&GetAllTodos).unwrap();
+        assert_eq!(2, todos.len());
+        assert_eq!("second", todos[1].1);
+
+        client.execute(&DropTodos).unwrap();
+    }
+
+    #[test]
+    fn comments() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let mut client = Client::connect(
+            "host=localhost user=aykroyd_test password=aykroyd_test",
+            NoTls,
+        )
+        .unwrap();
+
+        let request = Arc::new(AtomicUsize::new(0));
+        let context = request.clone();
+        client.set_comments(Comments::new().context(move || {
+            vec![(
+                "request",
+                context.fetch_add(1, Ordering::Relaxed).to_string(),
+            )]
+        }));
+
+        client.execute(&CreateTodos).unwrap();
+
+        client.query(&GetAllTodos).unwrap();
+        client.query(&GetAllTodos).unwrap();
+
+        // With per-call tags, each query is prepared for that call only.
+        let cached = client
+            .statements
+            .texts()
+            .filter(|text| text.contains("test_postgres"))
+            .count();
+        assert_eq!(0, cached);
+        assert_eq!(3, request.load(Ordering::Relaxed));
 
         client.execute(&DropTodos).unwrap();
     }
+
+    #[derive(crate::Notification)]
+    #[aykroyd(channel = "test_postgres")]
+    struct TodoAdded(i32);
+
+    #[test]
+    fn notify() {
+        let mut client = Client::connect(
+            "host=localhost user=aykroyd_test password=aykroyd_test",
+            NoTls,
+        )
+        .unwrap();
+
+        client.listen::<TodoAdded>().unwrap();
+        client.notify(&TodoAdded(42)).unwrap();
+
+        let TodoAdded(id) = client.notifications::<TodoAdded>().next().unwrap().unwrap();
+        assert_eq!(42, id);
+    }
 }
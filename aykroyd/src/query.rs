@@ -13,6 +13,15 @@ use crate::client::Client;
 /// query combinators.
 pub trait QueryText {
     fn query_text(&self) -> String;
+
+    /// The text to run on a client of type `C`.
+    ///
+    /// This only differs from `query_text` for queries with
+    /// [list parameters](crate::list), on databases with arrays.
+    #[doc(hidden)]
+    fn query_text_for<C: Client>(&self) -> String {
+        self.query_text()
+    }
 }
 
 /// The constant text of a `Query` or `Statement`.
@@ -38,7 +47,8 @@ struct A;
 
 assert_eq!("A", A::QUERY_TEXT);
 ```
-"##)]
+"##
+)]
 pub trait StaticQueryText {
     const QUERY_TEXT: &'static str;
 }
@@ -59,4 +69,174 @@ impl<S: StaticQueryText> QueryText for S {
 /// derive macro for `Query` or `Statement`.
 pub trait ToParams<C: Client>: Sync {
     fn to_params(&self) -> Option<Vec<C::Param<'_>>>;
+
+    /// The parameters rendered for diagnostics, if we know how.
+    ///
+    /// The derive macros implement this with [`DescribeParams`].
+    /// Otherwise, the parameters are left out of logs entirely.
+    #[doc(hidden)]
+    fn redacted_params(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+/// Render query parameters for diagnostics, like logs and errors.
+///
+/// There is one string for each parameter, in order, using the `Debug`
+/// implementation of its type.  Fields marked `#[aykroyd(sensitive)]`
+/// are masked, so that passwords and personal information stay out of
+/// the logs.  Parameters of types that aren't `Debug` are shown as `<?>`.
+///
+/// Don't implement this trait directly, use the derive macro for
+/// `Query` or `Statement`.
+#[cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::Statement;
+# use aykroyd::query::DescribeParams;
+#[derive(Statement)]
+#[aykroyd(text = "UPDATE users SET password_hash = $2 WHERE email = $1")]
+struct SetPassword<'a> {
+    email: &'a str,
+    #[aykroyd(sensitive)]
+    password_hash: &'a str,
+}
+
+let statement = SetPassword {
+    email: "dan@example.com",
+    password_hash: "$argon2id$v=19$...",
+};
+assert_eq!(
+    vec!["\"dan@example.com\"", "<redacted>"],
+    statement.describe_params(),
+);
+```
+"##
+)]
+pub trait DescribeParams {
+    fn describe_params(&self) -> Vec<String>;
+}
+
+/// An insert statement that generates an id for the new row.
+///
+/// Each client's `insert` method runs the statement and returns the id.
+/// PostgreSQL, SQLite and DuckDB append `RETURNING` and the id column to
+/// the statement (see [`ReturningId`](crate::combinator::ReturningId)),
+/// while MySQL asks the connection for the last `AUTO_INCREMENT` value.
+///
+/// Don't implement this trait directly, use the `returning` attribute
+/// of the derive macro for `Statement`.
+#[cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::Statement;
+#[derive(Statement)]
+#[aykroyd(returning(id: i64), text = "INSERT INTO todos (label) VALUES ($1)")]
+struct InsertTodo<'a>(&'a str);
+```
+"##
+)]
+pub trait Returning {
+    /// The type of the generated id.
+    type Id;
+
+    /// The name of the id column.
+    const ID_COLUMN: &'static str;
+}
+
+/// Helpers for the derived [`DescribeParams`].
+#[doc(hidden)]
+pub mod describe {
+    use std::fmt::Debug;
+
+    /// The text in place of a sensitive parameter.
+    pub const REDACTED: &str = "<redacted>";
+
+    /// A parameter to describe, with `Debug` if it has it.
+    ///
+    /// Method resolution prefers [`ViaDebug`] on `Describe` to [`Opaque`]
+    /// on `&Describe`, so call `(&Describe(value)).describe()`.
+    pub struct Describe<'a, T: ?Sized>(pub &'a T);
+
+    pub trait ViaDebug {
+        fn describe(&self) -> String;
+    }
+
+    impl<T: Debug + ?Sized> ViaDebug for Describe<'_, T> {
+        fn describe(&self) -> String {
+            format!("{:?}", self.0)
+        }
+    }
+
+    pub trait Opaque {
+        fn describe(&self) -> String;
+    }
+
+    impl<T: ?Sized> Opaque for &Describe<'_, T> {
+        fn describe(&self) -> String {
+            "<?>".into()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod test {
+    use super::*;
+    use crate::list::InList;
+    use crate::test::sync_client::{TestClient, ToParam};
+    use crate::Statement;
+
+    struct Opaque;
+
+    impl ToParam for Opaque {
+        fn to_param(&self) -> String {
+            "opaque".into()
+        }
+    }
+
+    #[derive(Statement)]
+    #[aykroyd(text = "UPDATE users SET password = $2 WHERE id IN $1 AND token IN $3 AND o = $4")]
+    struct SetPassword<'a> {
+        #[aykroyd(expand)]
+        ids: InList<'a, i32>,
+        #[aykroyd(sensitive)]
+        password: String,
+        #[aykroyd(expand, sensitive)]
+        tokens: InList<'a, String>,
+        opaque: Opaque,
+    }
+
+    #[derive(Statement)]
+    enum Login {
+        #[aykroyd(text = "SELECT login($1, $2)")]
+        Password(String, #[aykroyd(sensitive)] String),
+        #[aykroyd(text = "SELECT logout()")]
+        Logout,
+    }
+
+    #[test]
+    fn describe_params() {
+        let statement = SetPassword {
+            ids: InList(&[1, 2]),
+            password: "hunter2".into(),
+            tokens: InList(&["a".into(), "b".into()]),
+            opaque: Opaque,
+        };
+        assert_eq!(
+            vec!["1", "2", "<redacted>", "<redacted>", "<redacted>", "<?>"],
+            statement.describe_params(),
+        );
+        assert_eq!(
+            Some(statement.describe_params()),
+            ToParams::<TestClient>::redacted_params(&statement),
+        );
+
+        let login = Login::Password("dan".into(), "hunter2".into());
+        assert_eq!(vec!["\"dan\"", "<redacted>"], login.describe_params());
+        assert!(Login::Logout.describe_params().is_empty());
+    }
 }
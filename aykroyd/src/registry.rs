@@ -0,0 +1,198 @@
+//! A registry of every derived query.
+//!
+//! Each type deriving [`Statement`](crate::Statement),
+//! [`Query`](crate::Query) or [`QueryOne`](crate::QueryOne) registers
+//! itself here, so an application can list the queries it contains.
+//! The database clients use this to prepare them all at once with
+//! `prepare_all`, so that invalid SQL is caught at startup rather than
+//! when the query is first run.
+//!
+//! The registry holds the queries of every crate in the binary, tests
+//! included.  If they aren't all meant for the same database, use
+//! `prepare_all_matching` to pick out the ones for each client, for
+//! instance by the module they are defined in.
+//!
+//! ```
+//! # use aykroyd::{FromRow, Query};
+//! # #[derive(FromRow)]
+//! # struct Todo;
+//! #[derive(Query)]
+//! #[aykroyd(row(Todo), text = "SELECT id, label FROM todo")]
+//! struct GetAllTodos;
+//!
+//! let query = aykroyd::registry::queries()
+//!     .find(|query| query.name().ends_with("::GetAllTodos"))
+//!     .unwrap();
+//! assert_eq!("SELECT id, label FROM todo", query.text().into_owned());
+//! ```
+
+/// The kind of a registered query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    /// A [`Statement`](crate::Statement).
+    Statement,
+    /// A [`Query`](crate::Query).
+    Query,
+    /// A [`QueryOne`](crate::QueryOne).
+    QueryOne,
+}
+
+/// A query type found in the registry.
+#[derive(Debug)]
+pub struct QueryInfo {
+    name: &'static str,
+    text: &'static str,
+    lists: &'static [usize],
+    kind: QueryKind,
+}
+
+impl QueryInfo {
+    #[doc(hidden)]
+    pub const fn new(
+        name: &'static str,
+        text: &'static str,
+        lists: &'static [usize],
+        kind: QueryKind,
+    ) -> Self {
+        QueryInfo {
+            name,
+            text,
+            lists,
+            kind,
+        }
+    }
+
+    /// The path of the query type, including the module.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The text of the query.
+    ///
+    /// Any [list parameters](crate::list) are expanded to a single item,
+    /// so that the text can be prepared.
+    pub fn text(&self) -> std::borrow::Cow<'static, str> {
+        if self.lists.is_empty() {
+            self.text.into()
+        } else {
+            crate::list::expand_single(self.text, self.lists).into()
+        }
+    }
+
+    /// The text of the query, as a client of type `C` runs it.
+    ///
+    /// On databases with arrays, list parameters are bound as an array,
+    /// so this is the text that's prepared when the query is run.
+    #[cfg_attr(
+        not(any(feature = "postgres", feature = "tokio-postgres")),
+        allow(dead_code)
+    )]
+    pub(crate) fn text_for<C: crate::client::Client>(&self) -> std::borrow::Cow<'static, str> {
+        if C::ARRAY_PARAMS && !self.lists.is_empty() {
+            crate::list::arrays(self.text, self.lists).into()
+        } else {
+            self.text()
+        }
+    }
+
+    /// Whether it was derived as a statement or query.
+    pub fn kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    /// Is the query type defined in the given module, or one inside it?
+    pub fn in_module(&self, module: &str) -> bool {
+        self.name
+            .strip_prefix(module)
+            .is_some_and(|rest| rest.starts_with("::"))
+    }
+}
+
+inventory::collect!(QueryInfo);
+
+#[doc(hidden)]
+pub use inventory::submit;
+
+/// Iterate over every registered query.
+pub fn queries() -> impl Iterator<Item = &'static QueryInfo> {
+    inventory::iter::<QueryInfo>.into_iter()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FromRow, Query, QueryOne, Statement};
+
+    #[allow(dead_code)]
+    #[derive(FromRow)]
+    struct Todo {
+        label: String,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Statement)]
+    #[aykroyd(text = "INSERT INTO todo (label) VALUES ($1)")]
+    struct InsertTodo<'a>(&'a str);
+
+    #[allow(dead_code)]
+    #[derive(Query)]
+    #[aykroyd(row(Todo), text = "SELECT label FROM todo")]
+    struct GetAllTodos;
+
+    #[allow(dead_code)]
+    #[derive(QueryOne)]
+    #[aykroyd(row(Todo), text = "SELECT label FROM todo WHERE id = $1")]
+    struct GetTodo(i32);
+
+    #[allow(dead_code)]
+    #[derive(Query)]
+    #[aykroyd(row(Todo), text = "SELECT label FROM todo")]
+    struct GetAllTodosAgain;
+
+    /// The distinct query texts in the registry.
+    fn query_texts() -> Vec<String> {
+        let mut texts: Vec<_> = queries().map(|query| query.text().into_owned()).collect();
+        texts.sort_unstable();
+        texts.dedup();
+        texts
+    }
+
+    fn find(name: &str) -> &'static QueryInfo {
+        queries()
+            .find(|query| query.name() == name)
+            .unwrap_or_else(|| panic!("{name} not registered"))
+    }
+
+    #[test]
+    fn registered() {
+        let query = find("aykroyd::registry::test::InsertTodo");
+        assert_eq!(
+            "INSERT INTO todo (label) VALUES ($1)",
+            query.text().into_owned()
+        );
+        assert_eq!(QueryKind::Statement, query.kind());
+
+        let query = find("aykroyd::registry::test::GetAllTodos");
+        assert_eq!("SELECT label FROM todo", query.text().into_owned());
+        assert_eq!(QueryKind::Query, query.kind());
+
+        let query = find("aykroyd::registry::test::GetTodo");
+        assert_eq!(QueryKind::QueryOne, query.kind());
+        assert!(query.in_module("aykroyd::registry"));
+        assert!(query.in_module("aykroyd::registry::test"));
+        assert!(!query.in_module("aykroyd::reg"));
+        assert!(!query.in_module("aykroyd::registry::test::GetTodo"));
+    }
+
+    #[test]
+    fn distinct_texts() {
+        find("aykroyd::registry::test::GetAllTodosAgain");
+
+        let texts = query_texts();
+        let count = texts
+            .iter()
+            .filter(|text| **text == "SELECT label FROM todo")
+            .count();
+        assert_eq!(1, count);
+    }
+}
@@ -0,0 +1,232 @@
+//! Route queries to read replicas.
+//!
+//! A [`Replicated`] client holds a connection to the primary database
+//! and connections to one or more of its replicas.  Since Aykroyd knows
+//! a [`Query`](crate::Query) from a [`Statement`](crate::Statement) by
+//! its type, it can send each to the right place: queries go to a
+//! replica, and statements and transactions go to the primary.
+//!
+//! Replicas lag behind the primary, so a query that must see the writes
+//! just made can be marked to always run on the primary:
+#![cfg_attr(
+    feature = "derive",
+    doc = r##"
+
+```
+# use aykroyd::QueryOne;
+#[derive(QueryOne)]
+#[aykroyd(primary, row((i32, String)), text = "SELECT id, status FROM orders WHERE id = $1")]
+struct GetOrder(i32);
+```
+"##
+)]
+//!
+//! The clients for PostgreSQL and MySQL each have a `Replicated` alias
+//! with the usual query methods:
+//!
+//! ```ignore
+//! use aykroyd::postgres::{Client, Replicated};
+//! use aykroyd::replica::Balance;
+//!
+//! let mut client = Replicated::new(primary, vec![replica1, replica2])
+//!     .balance(Balance::LeastLoaded);
+//!
+//! let orders = client.query(&GetRecentOrders)?; // on a replica
+//! client.execute(&InsertOrder { total })?; // on the primary
+//! ```
+
+use std::time::Duration;
+
+/// How to choose a replica for each query.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Balance {
+    /// Take turns.
+    #[default]
+    RoundRobin,
+    /// Choose the replica with the lowest load, so slower replicas get
+    /// fewer queries.
+    ///
+    /// A replica's load is a moving average of how long its recent
+    /// queries took, with each new query weighted by a fifth.  It also
+    /// decays slightly whenever a query runs on another replica, so one
+    /// that was slow for a while is tried again once the others have
+    /// run a few dozen queries.  Replicas that haven't run any queries
+    /// yet are tried first.
+    LeastLoaded,
+}
+
+/// The weight of the latest query in a replica's load.
+const LOAD_WEIGHT: f64 = 0.2;
+
+/// How much the load of the other replicas decays on each query.
+const IDLE_DECAY: f64 = 0.98;
+
+/// A primary database client with read replicas.
+///
+/// See the [module docs](crate::replica) for details.
+#[derive(Debug)]
+pub struct Replicated<C> {
+    primary: C,
+    replicas: Vec<Replica<C>>,
+    balance: Balance,
+    next: usize,
+}
+
+#[derive(Debug)]
+struct Replica<C> {
+    client: C,
+    load: Duration,
+}
+
+impl<C> Replicated<C> {
+    /// Route queries between a primary and its replicas.
+    ///
+    /// With no replicas, everything runs on the primary.
+    pub fn new(primary: C, replicas: Vec<C>) -> Self {
+        let replicas = replicas
+            .into_iter()
+            .map(|client| Replica {
+                client,
+                load: Duration::ZERO,
+            })
+            .collect();
+        Replicated {
+            primary,
+            replicas,
+            balance: Balance::default(),
+            next: 0,
+        }
+    }
+
+    /// Choose replicas this way.
+    pub fn balance(mut self, balance: Balance) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// The primary client.
+    pub fn primary(&mut self) -> &mut C {
+        &mut self.primary
+    }
+
+    /// The replica clients.
+    pub fn replicas(&mut self) -> impl Iterator<Item = &mut C> {
+        self.replicas.iter_mut().map(|replica| &mut replica.client)
+    }
+}
+
+#[cfg_attr(
+    not(any(
+        feature = "mysql",
+        feature = "mysql-async",
+        feature = "postgres",
+        feature = "tokio-postgres",
+    )),
+    allow(dead_code)
+)]
+impl<C> Replicated<C> {
+    /// Every client, the primary first.
+    pub(crate) fn clients(&mut self) -> impl Iterator<Item = &mut C> {
+        let replicas = self.replicas.iter_mut().map(|replica| &mut replica.client);
+        std::iter::once(&mut self.primary).chain(replicas)
+    }
+
+    /// Choose where to run a query, returning the index of the replica,
+    /// or `None` for the primary.
+    pub(crate) fn route(&mut self, primary: bool) -> Option<usize> {
+        if primary || self.replicas.is_empty() {
+            return None;
+        }
+
+        match self.balance {
+            Balance::RoundRobin => {
+                let index = self.next % self.replicas.len();
+                self.next = index + 1;
+                Some(index)
+            }
+            Balance::LeastLoaded => self
+                .replicas
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, replica)| replica.load)
+                .map(|(index, _)| index),
+        }
+    }
+
+    /// The client to run a query on, given the route.
+    pub(crate) fn client(&mut self, route: Option<usize>) -> &mut C {
+        match route {
+            Some(index) => &mut self.replicas[index].client,
+            None => &mut self.primary,
+        }
+    }
+
+    /// Record how long a query took, updating the load of each replica.
+    pub(crate) fn record(&mut self, route: Option<usize>, elapsed: Duration) {
+        let Some(route) = route else {
+            return;
+        };
+        for (index, replica) in self.replicas.iter_mut().enumerate() {
+            replica.load = if index == route {
+                replica.load.mul_f64(1.0 - LOAD_WEIGHT) + elapsed.mul_f64(LOAD_WEIGHT)
+            } else {
+                replica.load.mul_f64(IDLE_DECAY)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        let mut client = Replicated::new("primary", vec!["a", "b"]);
+        let routes: Vec<_> = (0..3).map(|_| client.route(false)).collect();
+        assert_eq!(vec![Some(0), Some(1), Some(0)], routes);
+        assert_eq!(None, client.route(true));
+        assert_eq!("b", *client.client(Some(1)));
+        assert_eq!("primary", *client.client(None));
+
+        let mut client = Replicated::new("primary", vec![]);
+        assert_eq!(None, client.route(false));
+    }
+
+    #[test]
+    fn least_loaded() {
+        let mut client =
+            Replicated::new("primary", vec!["a", "b", "c"]).balance(Balance::LeastLoaded);
+        client.record(Some(0), Duration::from_millis(10));
+        client.record(Some(1), Duration::from_millis(2));
+        client.record(Some(2), Duration::from_millis(5));
+        client.record(None, Duration::from_millis(1));
+        assert_eq!(Some(1), client.route(false));
+
+        client.record(Some(1), Duration::from_millis(5));
+        assert_eq!(Some(2), client.route(false));
+    }
+
+    #[test]
+    fn least_loaded_recent() {
+        let mut client = Replicated::new("primary", vec!["a", "b"]).balance(Balance::LeastLoaded);
+        for _ in 0..10 {
+            client.record(Some(0), Duration::from_millis(2));
+        }
+        client.record(Some(1), Duration::from_millis(8));
+        client.record(Some(1), Duration::from_millis(8));
+        // "b" has spent less time in total, but its queries are slower.
+        assert_eq!(Some(0), client.route(false));
+
+        // "a" slows down, and "b" is tried again as its load decays.
+        client.record(Some(0), Duration::from_millis(50));
+        assert_eq!(Some(1), client.route(false));
+        let routes: Vec<_> = (0..40)
+            .map(|_| {
+                client.record(Some(1), Duration::from_millis(20));
+                client.route(false)
+            })
+            .collect();
+        assert!(routes.contains(&Some(0)));
+    }
+}
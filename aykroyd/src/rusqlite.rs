@@ -1,11 +1,23 @@
 //! Sqlite bindings.
 
-use crate::client::{FromColumnIndexed, FromColumnNamed, ToParam};
-use crate::query::StaticQueryText;
-use crate::{error, FromRow, Query, QueryOne, Statement};
+use std::any::type_name;
+
+use crate::bulk::InsertValues;
+use crate::client::{ExecuteResult, FromColumnIndexed, FromColumnNamed, ToParam};
+use crate::combinator::ReturningId;
+use crate::comment::Comments;
+use crate::hook::{Hook, Hooks};
+use crate::list::{ParamList, ToParamList};
+use crate::query::{Returning, StaticQueryText, ToParams};
+use crate::stats::Stats;
+use crate::verify::{ColumnType, Description, ParamType};
+use crate::{builder, bulk, debug, error, plan, trace, verify, QueryRows, QueryRowsOne, Statement};
 
 pub type Error = error::Error<rusqlite::Error>;
 
+/// The OpenTelemetry name of the database, for tracing spans.
+const DB_SYSTEM: &str = "sqlite";
+
 impl<T> FromColumnIndexed<Client> for T
 where
     T: rusqlite::types::FromSql,
@@ -33,7 +45,41 @@ where
     }
 }
 
-pub struct Client(rusqlite::Connection);
+impl<L> ToParamList<Client> for L
+where
+    L: ParamList,
+    L::Item: rusqlite::types::ToSql,
+{
+    fn to_params(&self) -> Vec<&dyn rusqlite::types::ToSql> {
+        self.items().iter().map(ToParam::to_param).collect()
+    }
+}
+
+/// SQLite doesn't report parameter types, so any type is accepted.
+impl<T> ParamType<Client> for T
+where
+    T: rusqlite::types::ToSql,
+{
+    fn accepts(_ty: &String) -> bool {
+        true
+    }
+}
+
+/// SQLite columns are dynamically typed, so any type is accepted.
+impl<T> ColumnType<Client> for T
+where
+    T: rusqlite::types::FromSql,
+{
+    fn accepts(_ty: &String) -> bool {
+        true
+    }
+
+    fn accepts_null() -> bool {
+        T::column_result(rusqlite::types::ValueRef::Null).is_ok()
+    }
+}
+
+pub struct Client(rusqlite::Connection, Hooks);
 
 impl crate::client::Client for Client {
     type Row<'a> = rusqlite::Row<'a>;
@@ -41,6 +87,71 @@ impl crate::client::Client for Client {
     type Error = rusqlite::Error;
 }
 
+impl builder::Placeholder for Client {
+    fn placeholder(index: usize) -> String {
+        format!("?{index}")
+    }
+}
+
+impl debug::Quote for Client {
+    /// SQLite has no boolean type, and stores them as integers.
+    fn quote_bool(value: bool) -> String {
+        (value as i32).to_string()
+    }
+}
+
+/// SQLite allows 999 parameters, or 32766 since version 3.32.0.
+impl bulk::ParamLimit for Client {
+    fn param_limit() -> usize {
+        if rusqlite::version_number() >= 3_032_000 {
+            32766
+        } else {
+            999
+        }
+    }
+}
+
+impl plan::Dialect for Client {
+    fn explain_text(query_text: &str, _analyze: bool) -> String {
+        format!("EXPLAIN QUERY PLAN {query_text}")
+    }
+
+    fn plan_row(row: &rusqlite::Row) -> Result<plan::PlanRow, Error> {
+        let id = row.get(0).map_err(Error::from_column)?;
+        let parent = row.get(1).map_err(Error::from_column)?;
+        let detail: String = row.get(3).map_err(Error::from_column)?;
+        Ok(plan::PlanRow::new(
+            Some(id),
+            Some(parent),
+            vec![plan::from_sqlite(&detail)],
+        ))
+    }
+}
+
+/// The declared type of a column.
+impl verify::ClientTypes for Client {
+    type Type = String;
+}
+
+impl verify::Describe for Client {
+    fn describe(&mut self, query_text: &str) -> Result<Description<String>, Error> {
+        let statement = self.0.prepare_cached(query_text).map_err(Error::prepare)?;
+
+        let params = (0..statement.parameter_count()).map(|_| None).collect();
+        let columns = statement
+            .columns()
+            .into_iter()
+            .map(|column| verify::Column {
+                name: column.name().into(),
+                ty: column.decl_type().map(Into::into),
+                nullable: None,
+            })
+            .collect();
+
+        Ok(Description { params, columns })
+    }
+}
+
 impl AsMut<rusqlite::Connection> for Client {
     fn as_mut(&mut self) -> &mut rusqlite::Connection {
         &mut self.0
@@ -55,207 +166,424 @@ impl AsRef<rusqlite::Connection> for Client {
 
 impl From<rusqlite::Connection> for Client {
     fn from(inner: rusqlite::Connection) -> Self {
-        Client(inner)
+        Client(inner, Hooks::default())
     }
 }
 
 impl Client {
-    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
-        rusqlite::Connection::open(path).map(Client).map_err(Error::connect)
+    /// Add a hook to run around each query, on this client and its transactions.
+    ///
+    /// See the [`hook`](crate::hook) module for details.
+    pub fn add_hook<H: Hook + 'static>(&mut self, hook: H) {
+        self.1.add(hook);
     }
 
-    pub fn open_in_memory() -> Result<Self, Error> {
-        rusqlite::Connection::open_in_memory().map(Client).map_err(Error::connect)
+    /// Collect statistics for each query, on this client and its transactions.
+    ///
+    /// See the [`stats`](crate::stats) module for details.
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.1.set_stats(stats);
     }
 
-    pub fn query<Q: Query<Self>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Vec<Q::Row>, Error> {
-        let params = query.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-
-        let mut statement =
-            rusqlite::Connection::prepare_cached(self.as_mut(), &query.query_text())
-                .map_err(Error::prepare)?;
+    /// The statistics collected for this client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.1.stats()
+    }
 
-        let mut rows = statement.query(params).map_err(Error::query)?;
+    /// Append a comment to each query, on this client and its transactions.
+    ///
+    /// See the [`comment`](crate::comment) module for details.
+    pub fn set_comments(&mut self, comments: Comments) {
+        self.1.set_comments(comments);
+    }
 
-        let mut result = vec![];
-        while let Some(row) = rows.next().map_err(Error::query)? {
-            result.push(FromRow::from_row(row)?);
-        }
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        rusqlite::Connection::open(path)
+            .map(Client::from)
+            .map_err(Error::connect)
+    }
 
-        Ok(result)
+    pub fn open_in_memory() -> Result<Self, Error> {
+        rusqlite::Connection::open_in_memory()
+            .map(Client::from)
+            .map_err(Error::connect)
     }
 
-    pub fn query_one<Q: QueryOne<Self>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Q::Row, Error> {
-        let params = query.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+    pub fn query<Q: QueryRows<Self, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |rows| rows.len() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
 
-        let mut statement =
-            rusqlite::Connection::prepare_cached(self.as_mut(), &query.query_text())
-                .map_err(Error::prepare)?;
+                let mut statement =
+                    rusqlite::Connection::prepare_cached(self.as_mut(), &query_text)
+                        .map_err(Error::prepare)?;
 
-        let mut rows = statement.query(params).map_err(Error::query)?;
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                let mut result = vec![];
+                while let Some(row) = rows.next().map_err(Error::query)? {
+                    result.push(query.map_row(row)?);
+                }
+
+                Ok(result)
+            },
+        )
+    }
 
-        rows.next()
-            .map_err(Error::query)?
-            .ok_or_else(|| Error::query(rusqlite::Error::QueryReturnedNoRows))
-            .and_then(|row| FromRow::from_row(row))
+    pub fn query_one<Q: QueryRowsOne<Self, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_one", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |_| 1,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement =
+                    rusqlite::Connection::prepare_cached(self.as_mut(), &query_text)
+                        .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .ok_or_else(|| Error::query(rusqlite::Error::QueryReturnedNoRows))
+                    .and_then(|row| query.map_row(row))
+            },
+        )
     }
 
-    pub fn query_opt<Q: QueryOne<Self>>(
+    pub fn query_opt<Q: QueryRowsOne<Self, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Option<Q::Row>, Error> {
-        let params = query.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_opt", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |row| row.is_some() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement =
+                    rusqlite::Connection::prepare_cached(self.as_mut(), &query_text)
+                        .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .map(|row| query.map_row(row))
+                    .transpose()
+            },
+        )
+    }
 
-        let mut statement =
-            rusqlite::Connection::prepare_cached(self.as_mut(), &query.query_text())
-                .map_err(Error::prepare)?;
+    pub fn execute<S: Statement<Self>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "execute", statement)?;
+        let query_text = self.1.annotate(type_name::<S>(), query_text);
+        span.run(
+            |rows_affected| *rows_affected,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
 
-        let mut rows = statement.query(params).map_err(Error::query)?;
+                let mut statement =
+                    rusqlite::Connection::prepare_cached(self.as_mut(), &query_text)
+                        .map_err(Error::prepare)?;
 
-        rows.next()
-            .map_err(Error::query)?
-            .map(|row| FromRow::from_row(row))
-            .transpose()
+                let rows_affected = statement.execute(params).map_err(Error::query)?;
+
+                Ok(rows_affected.try_into().unwrap_or_default())
+            },
+        )
     }
 
-    pub fn execute<S: Statement<Self>>(
+    /// Executes a statement, returning the number of rows affected and
+    /// the rowid of the last row inserted.
+    pub fn execute_returning_id<S: Statement<Self>>(
         &mut self,
         statement: &S,
-    ) -> Result<u64, Error> {
-        let params = statement.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+    ) -> Result<ExecuteResult, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "execute", statement)?;
+        let query_text = self.1.annotate(type_name::<S>(), query_text);
+        span.run(
+            |result| result.rows_affected,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = rusqlite::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let rows_affected = statement.execute(params).map_err(Error::query)?;
+
+                Ok(ExecuteResult {
+                    rows_affected: rows_affected.try_into().unwrap_or_default(),
+                    last_insert_id: Some(self.0.last_insert_rowid()).filter(|&id| id != 0),
+                })
+            },
+        )
+    }
 
-        let mut statement =
-            rusqlite::Connection::prepare_cached(self.as_mut(), &statement.query_text())
-                .map_err(Error::prepare)?;
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Self> + Returning,
+        S::Id: FromColumnIndexed<Self>,
+    {
+        self.query_one(&ReturningId::new(statement))
+    }
 
-        let rows_affected = statement.execute(params).map_err(Error::query)?;
+    pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        let query_text = self.1.annotate(type_name::<S>(), S::QUERY_TEXT.into());
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), &query_text);
+        span.in_scope(|| {
+            self.as_mut()
+                .prepare_cached(&query_text)
+                .map_err(Error::prepare)?;
+            Ok(())
+        })
+    }
 
-        Ok(rows_affected.try_into().unwrap_or_default())
+    /// Prepares every query in the [`registry`](crate::registry).
+    ///
+    /// Call this after connecting to catch invalid query text at
+    /// startup rather than first use.  It also warms the statement cache,
+    /// but that only holds 16 statements unless you raise it with
+    /// `set_prepared_statement_cache_capacity`.
+    /// Every registered query is prepared, including those of other
+    /// crates, so they should all be meant for this database.  If not,
+    /// use [`prepare_all_matching`](Self::prepare_all_matching).
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub fn prepare_all(&mut self) -> Result<(), Error> {
+        self.prepare_all_matching(|_| true)
     }
 
-    pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
-        self.as_mut()
-            .prepare_cached(S::QUERY_TEXT)
-            .map_err(Error::prepare)?;
+    /// Prepares the queries in the [`registry`](crate::registry) that
+    /// match the filter.
+    ///
+    /// Use this when not every registered query is meant for this
+    /// database, e.g. `|query| query.in_module("my_app::sqlite")`.
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub fn prepare_all_matching<F>(&mut self, mut filter: F) -> Result<(), Error>
+    where
+        F: FnMut(&crate::registry::QueryInfo) -> bool,
+    {
+        for query in crate::registry::queries().filter(|query| filter(query)) {
+            let query_text = self.1.annotate(query.name(), query.text().into_owned());
+            let span = trace::Span::new(DB_SYSTEM, "prepare", query.name(), &query_text);
+            span.in_scope(|| {
+                self.as_mut()
+                    .prepare_cached(&query_text)
+                    .map_err(Error::prepare)
+            })?;
+        }
         Ok(())
     }
 
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit, all in one transaction.  Returns the total number of rows
+    /// affected.  See the [`bulk`](crate::bulk) module for details.
+    pub fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Self>,
+    {
+        let mut txn = self.transaction()?;
+        let rows_affected = txn.insert_many(rows)?;
+        txn.commit()?;
+        Ok(rows_affected)
+    }
+
     pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
         Ok(Transaction(
             self.0.transaction().map_err(Error::transaction)?,
+            self.1.clone(),
         ))
     }
 }
 
-pub struct Transaction<'a>(rusqlite::Transaction<'a>);
+pub struct Transaction<'a>(rusqlite::Transaction<'a>, Hooks);
 
 impl<'a> Transaction<'a> {
+    /// The statistics collected for the client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.1.stats()
+    }
+
     pub fn commit(self) -> Result<(), Error> {
-        self.0.commit().map_err(Error::transaction)
+        let span = trace::Span::transaction(DB_SYSTEM, "commit");
+        span.in_scope(|| self.0.commit().map_err(Error::transaction))
     }
 
     pub fn rollback(self) -> Result<(), Error> {
-        self.0.rollback().map_err(Error::transaction)
+        let span = trace::Span::transaction(DB_SYSTEM, "rollback");
+        span.in_scope(|| self.0.rollback().map_err(Error::transaction))
     }
 
-    pub fn query<Q: Query<Client>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Vec<Q::Row>, Error> {
-        let params = query.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-
-        let mut statement = rusqlite::Connection::prepare_cached(&self.0, &query.query_text())
-            .map_err(Error::prepare)?;
-
-        let mut rows = statement.query(params).map_err(Error::query)?;
-
-        let mut result = vec![];
-        while let Some(row) = rows.next().map_err(Error::query)? {
-            result.push(FromRow::from_row(row)?);
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit.  Returns the total number of rows affected.
+    pub fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Client>,
+    {
+        let mut rows_affected = 0;
+        for chunk in bulk::chunks::<Client, S>(rows) {
+            rows_affected += self.execute(&chunk)?;
         }
-
-        Ok(result)
+        Ok(rows_affected)
     }
 
-    pub fn query_one<Q: QueryOne<Client>>(
-        &mut self,
-        query: &Q,
-    ) -> Result<Q::Row, Error> {
-        let params = query.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+    pub fn query<Q: QueryRows<Client, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |rows| rows.len() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
 
-        let mut statement =
-            rusqlite::Connection::prepare_cached(&self.0, &query.query_text())
-                .map_err(Error::prepare)?;
+                let mut statement = rusqlite::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
 
-        let mut rows = statement.query(params).map_err(Error::query)?;
+                let mut rows = statement.query(params).map_err(Error::query)?;
 
-        rows.next()
-            .map_err(Error::query)?
-            .ok_or_else(|| Error::query(rusqlite::Error::QueryReturnedNoRows))
-            .and_then(|row| FromRow::from_row(row))
+                let mut result = vec![];
+                while let Some(row) = rows.next().map_err(Error::query)? {
+                    result.push(query.map_row(row)?);
+                }
+
+                Ok(result)
+            },
+        )
     }
 
-    pub fn query_opt<Q: QueryOne<Client>>(
+    pub fn query_one<Q: QueryRowsOne<Client, M>, M>(&mut self, query: &Q) -> Result<Q::Row, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_one", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |_| 1,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = rusqlite::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .ok_or_else(|| Error::query(rusqlite::Error::QueryReturnedNoRows))
+                    .and_then(|row| query.map_row(row))
+            },
+        )
+    }
+
+    pub fn query_opt<Q: QueryRowsOne<Client, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Option<Q::Row>, Error> {
-        let params = query.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "query_opt", query)?;
+        let query_text = self.1.annotate(type_name::<Q>(), query_text);
+        span.run(
+            |row| row.is_some() as u64,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = rusqlite::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let mut rows = statement.query(params).map_err(Error::query)?;
+
+                rows.next()
+                    .map_err(Error::query)?
+                    .map(|row| query.map_row(row))
+                    .transpose()
+            },
+        )
+    }
 
-        let mut statement =
-            rusqlite::Connection::prepare_cached(&self.0, &query.query_text())
-                .map_err(Error::prepare)?;
+    pub fn execute<S: Statement<Client>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "execute", statement)?;
+        let query_text = self.1.annotate(type_name::<S>(), query_text);
+        span.run(
+            |rows_affected| *rows_affected,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
 
-        let mut rows = statement.query(params).map_err(Error::query)?;
+                let mut statement = rusqlite::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
 
-        rows.next()
-            .map_err(Error::query)?
-            .map(|row| FromRow::from_row(row))
-            .transpose()
+                let rows_affected = statement.execute(params).map_err(Error::query)?;
+
+                Ok(rows_affected.try_into().unwrap_or_default())
+            },
+        )
     }
 
-    pub fn execute<S: Statement<Client>>(
+    /// Executes a statement, returning the number of rows affected and
+    /// the rowid of the last row inserted.
+    pub fn execute_returning_id<S: Statement<Client>>(
         &mut self,
         statement: &S,
-    ) -> Result<u64, Error> {
-        let params = statement.to_params();
-        let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-
-        let mut statement = rusqlite::Connection::prepare_cached(&self.0, &statement.query_text())
-            .map_err(Error::prepare)?;
-
-        let rows_affected = statement.execute(params).map_err(Error::query)?;
+    ) -> Result<ExecuteResult, Error> {
+        let (params, query_text, span) = self.1.start(DB_SYSTEM, "execute", statement)?;
+        let query_text = self.1.annotate(type_name::<S>(), query_text);
+        span.run(
+            |result| result.rows_affected,
+            || {
+                let params: &[_] = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+
+                let mut statement = rusqlite::Connection::prepare_cached(&self.0, &query_text)
+                    .map_err(Error::prepare)?;
+
+                let rows_affected = statement.execute(params).map_err(Error::query)?;
+
+                Ok(ExecuteResult {
+                    rows_affected: rows_affected.try_into().unwrap_or_default(),
+                    last_insert_id: Some(self.0.last_insert_rowid()).filter(|&id| id != 0),
+                })
+            },
+        )
+    }
 
-        Ok(rows_affected.try_into().unwrap_or_default())
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Client> + Returning,
+        S::Id: FromColumnIndexed<Client>,
+    {
+        self.query_one(&ReturningId::new(statement))
     }
 
     pub fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
-        self.0
-            .prepare_cached(S::QUERY_TEXT)
-            .map_err(Error::prepare)?;
-        Ok(())
+        let query_text = self.1.annotate(type_name::<S>(), S::QUERY_TEXT.into());
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), &query_text);
+        span.in_scope(|| {
+            self.0.prepare_cached(&query_text).map_err(Error::prepare)?;
+            Ok(())
+        })
     }
 }
 
 // TODO: not derive support
-#[cfg(all(test, feature ="derive"))]
+#[cfg(all(test, feature = "derive"))]
 mod test {
     use super::*;
+    use crate::{Query, QueryOne};
 
     #[derive(Statement)]
-    #[aykroyd(text = "CREATE TABLE test_rusqlite (id INTEGER PRIMARY KEY AUTOINCREMENT, label TEXT NOT NULL)")]
+    #[aykroyd(
+        text = "CREATE TABLE test_rusqlite (id INTEGER PRIMARY KEY AUTOINCREMENT, label TEXT NOT NULL)"
+    )]
     struct CreateTodos;
 
     #[derive(Statement)]
@@ -287,6 +615,492 @@ mod test {
         client.execute(&DropTodos).unwrap();
     }
 
+    #[derive(Statement)]
+    #[aykroyd(
+        check = "src/test/schema.json",
+        text = "INSERT INTO test_rusqlite (label) VALUES ($1)"
+    )]
+    struct CheckedInsertTodo<'a>(&'a str);
+
+    #[derive(QueryOne)]
+    #[aykroyd(
+        row((i32, String)),
+        check = "src/test/schema.json",
+        text = "SELECT id, label FROM test_rusqlite WHERE label = $1"
+    )]
+    struct CheckedGetTodoByLabel<'a>(&'a str);
+
+    #[test]
+    fn checked() {
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        client.execute(&CheckedInsertTodo("checked")).unwrap();
+
+        let (_, label) = client.query_one(&CheckedGetTodoByLabel("checked")).unwrap();
+        assert_eq!("checked", label);
+    }
+
+    #[derive(Query)]
+    #[aykroyd(row((i32, String, String)), text = "SELECT id, label FROM test_rusqlite WHERE id = $1")]
+    struct GetTodoWrong;
+
+    #[allow(dead_code)]
+    #[derive(Query)]
+    #[aykroyd(row((i32, String)))]
+    enum GetTodos {
+        #[aykroyd(text = "SELECT id, label FROM test_rusqlite WHERE id = $1")]
+        ById(i32),
+        #[aykroyd(text = "SELECT id, label FROM test_rusqlite WHERE label = $1")]
+        ByLabel,
+    }
+
+    #[test]
+    fn verify() {
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+
+        let mismatches = crate::verify::<GetAllTodos, _>(&mut client).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+
+        let mismatches = crate::verify::<InsertTodo, _>(&mut client).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+
+        let mismatches = crate::verify::<GetTodoWrong, _>(&mut client).unwrap();
+        assert_eq!(
+            vec![
+                verify::Mismatch::ParamCount {
+                    expected: 0,
+                    actual: 1
+                },
+                verify::Mismatch::ColumnCount {
+                    expected: 3,
+                    actual: 2
+                },
+            ],
+            mismatches,
+        );
+        let mismatches = crate::verify::verify_variants::<GetTodos, _>(&mut client).unwrap();
+        assert_eq!(
+            vec![
+                ("ById", vec![]),
+                (
+                    "ByLabel",
+                    vec![verify::Mismatch::ParamCount {
+                        expected: 0,
+                        actual: 1
+                    }],
+                ),
+            ],
+            mismatches,
+        );
+    }
+
+    #[test]
+    fn builder() {
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        for label in ["a", "b", "c"] {
+            client.execute(&InsertTodo(label)).unwrap();
+        }
+
+        let query = crate::builder::Builder::new("SELECT id, label FROM test_rusqlite")
+            .filter("label <> ?", "b")
+            .order_by("id DESC")
+            .limit(1)
+            .build_query::<(i32, String)>();
+        let todos = client.query(&query).unwrap();
+        assert_eq!(vec![(3, "c".to_string())], todos);
+
+        let statement = crate::builder::Builder::new("DELETE FROM test_rusqlite")
+            .filter("label = ?", "a")
+            .build();
+        assert_eq!(1, client.execute(&statement).unwrap());
+    }
+
+    #[test]
+    fn pagination() {
+        use crate::combinator::{Keyset, Paginate};
+
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        for label in ["a", "b", "c"] {
+            client.execute(&InsertTodo(label)).unwrap();
+        }
+
+        let query = Paginate::new(GetAllTodos, &["id"], 2).offset(1);
+        let page = query.page(client.query(&query).unwrap());
+        assert_eq!(vec![(2, "b".to_string()), (3, "c".to_string())], page.items);
+        assert_eq!(None, page.next);
+
+        let query = Keyset::new(GetAllTodos, &["label"], 2).descending();
+        let page = query.page(client.query(&query).unwrap(), |row| (row.1.clone(),));
+        assert_eq!(vec![(3, "c".to_string()), (2, "b".to_string())], page.items);
+        assert_eq!(Some(("b".to_string(),)), page.next);
+
+        let query = query.after(page.next.unwrap());
+        let page = query.page(client.query(&query).unwrap(), |row| (row.1.clone(),));
+        assert_eq!(vec![(1, "a".to_string())], page.items);
+        assert_eq!(None, page.next);
+    }
+
+    #[test]
+    fn count_and_exists() {
+        use crate::combinator::{Count, Exists};
+
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        assert_eq!(0, client.query_one(&Count::new(GetAllTodos)).unwrap());
+        assert!(!client.query_one(&Exists::new(GetAllTodos)).unwrap());
+
+        client.execute(&InsertTodo("a")).unwrap();
+        client.execute(&InsertTodo("b")).unwrap();
+        assert_eq!(2, client.query_one(&Count::new(GetAllTodos)).unwrap());
+        assert!(client.query_one(&Exists::new(GetAllTodos)).unwrap());
+    }
+
+    #[test]
+    fn list_params() {
+        use crate::list::InList;
+
+        #[derive(Query)]
+        #[aykroyd(row((i32, String)), text = "SELECT id, label FROM test_rusqlite WHERE id IN $1 AND label <> $2 ORDER BY id")]
+        struct GetTodosById<'a> {
+            #[aykroyd(expand)]
+            ids: InList<'a, i32>,
+            except: &'a str,
+        }
+
+        #[derive(Query)]
+        #[aykroyd(row((i32, String)), text = "SELECT id, label FROM test_rusqlite WHERE id NOT IN $1 ORDER BY id")]
+        struct GetOtherTodos<'a> {
+            #[aykroyd(expand)]
+            ids: InList<'a, i32>,
+        }
+
+        #[derive(Statement)]
+        enum DeleteTodos<'a> {
+            #[aykroyd(text = "DELETE FROM test_rusqlite WHERE label IN ?")]
+            Labels(#[aykroyd(expand)] Vec<&'a str>),
+        }
+
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        for label in ["a", "b", "c", "d"] {
+            client.execute(&InsertTodo(label)).unwrap();
+        }
+
+        let todos = client
+            .query(&GetTodosById {
+                ids: InList(&[1, 3, 4]),
+                except: "d",
+            })
+            .unwrap();
+        assert_eq!(vec![(1, "a".to_string()), (3, "c".to_string())], todos);
+
+        let todos = client
+            .query(&GetTodosById {
+                ids: InList(&[]),
+                except: "d",
+            })
+            .unwrap();
+        assert!(todos.is_empty());
+
+        let deleted = client
+            .execute(&DeleteTodos::Labels(vec!["a", "b"]))
+            .unwrap();
+        assert_eq!(2, deleted);
+
+        let todos = client.query(&GetOtherTodos { ids: InList(&[3]) }).unwrap();
+        assert_eq!(vec![(4, "d".to_string())], todos);
+
+        let todos = client.query(&GetOtherTodos { ids: InList(&[]) }).unwrap();
+        assert_eq!(vec![(3, "c".to_string()), (4, "d".to_string())], todos);
+
+        let registered = crate::registry::queries()
+            .find(|query| query.name().ends_with("::GetTodosById"))
+            .unwrap();
+        assert!(registered.text().contains("WHERE id IN ($1) AND"));
+    }
+
+    mod startup {
+        use crate::Statement;
+
+        #[allow(dead_code)]
+        #[derive(Statement)]
+        #[aykroyd(text = "UPDATE test_rusqlite SET label = $1 WHERE id = $2")]
+        struct RenameTodo<'a>(&'a str, i32);
+
+        #[allow(dead_code)]
+        #[derive(Statement)]
+        #[aykroyd(text = "DELETE FROM test_rusqlite WHERE id = $1")]
+        struct DeleteTodo(i32);
+    }
+
+    mod invalid {
+        use crate::Statement;
+
+        #[allow(dead_code)]
+        #[derive(Statement)]
+        #[aykroyd(text = "DELETE FROM no_such_table")]
+        struct DeleteMissing;
+    }
+
+    /// The text of each statement prepared on the connection.
+    fn prepared(client: &Client) -> Vec<String> {
+        use rusqlite::ffi;
+
+        let mut texts = vec![];
+        // SAFETY: we only read the statement list of an open connection,
+        // and don't hold on to anything past this function.
+        unsafe {
+            let db = client.as_ref().handle();
+            let mut statement = ffi::sqlite3_next_stmt(db, std::ptr::null_mut());
+            while !statement.is_null() {
+                let text = std::ffi::CStr::from_ptr(ffi::sqlite3_sql(statement));
+                texts.push(text.to_string_lossy().into_owned());
+                statement = ffi::sqlite3_next_stmt(db, statement);
+            }
+        }
+        texts
+    }
+
+    #[test]
+    fn prepare_all_matching() {
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+
+        client
+            .prepare_all_matching(|query| query.in_module("aykroyd::rusqlite::test::startup"))
+            .unwrap();
+        let prepared = prepared(&client);
+        assert_eq!(3, prepared.len(), "{prepared:?}");
+        assert!(prepared.contains(&"UPDATE test_rusqlite SET label = $1 WHERE id = $2".into()));
+        assert!(prepared.contains(&"DELETE FROM test_rusqlite WHERE id = $1".into()));
+
+        let err = client
+            .prepare_all_matching(|query| query.in_module("aykroyd::rusqlite::test::invalid"))
+            .unwrap_err();
+        assert_eq!(crate::error::ErrorKind::Prepare, err.kind());
+        assert!(client.prepare_all().is_err());
+    }
+
+    #[test]
+    fn insert_many() {
+        #[derive(Statement)]
+        #[aykroyd(
+            insert_values,
+            text = "INSERT INTO test_rusqlite (label) VALUES (upper($1))"
+        )]
+        struct InsertTodoUpper(String);
+
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+
+        let todos: Vec<_> = (0..40000)
+            .map(|i| InsertTodoUpper(format!("todo {i}")))
+            .collect();
+        let inserted = client.insert_many(&todos).unwrap();
+        assert_eq!(40000, inserted);
+
+        let todos = client.query(&GetAllTodos).unwrap();
+        assert_eq!(40000, todos.len());
+        assert_eq!("TODO 39999", todos[39999].1);
+    }
+
+    #[test]
+    fn returning() {
+        #[derive(Statement)]
+        #[aykroyd(returning(id: i64), text = "INSERT INTO test_rusqlite (label) VALUES ($1)")]
+        struct InsertTodoReturning<'a>(&'a str);
+
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        assert_eq!(
+            ExecuteResult::default(),
+            client.execute_returning_id(&DropTodos).unwrap()
+        );
+        client.execute(&CreateTodos).unwrap();
+
+        assert_eq!(1, client.insert(&InsertTodoReturning("a")).unwrap());
+        assert_eq!(
+            ExecuteResult {
+                rows_affected: 1,
+                last_insert_id: Some(2)
+            },
+            client.execute_returning_id(&InsertTodo("b")).unwrap(),
+        );
+
+        let mut txn = client.transaction().unwrap();
+        assert_eq!(3, txn.insert(&InsertTodoReturning("c")).unwrap());
+        txn.commit().unwrap();
+
+        assert_eq!(3, client.query(&GetAllTodos).unwrap().len());
+    }
+
+    #[test]
+    fn explain() {
+        use crate::combinator::Explain;
+        use crate::plan::Plan;
+
+        #[derive(Statement)]
+        #[aykroyd(text = "CREATE INDEX test_rusqlite_label ON test_rusqlite (label)")]
+        struct CreateIndex;
+
+        #[derive(Query)]
+        #[aykroyd(row((i32, String)), text = "SELECT id, label FROM test_rusqlite WHERE label = $1")]
+        struct GetTodosByLabel<'a>(&'a str);
+
+        #[derive(Query)]
+        #[aykroyd(primary, row((i32, String)), text = "SELECT id, label FROM test_rusqlite")]
+        struct GetAllTodosFresh;
+
+        fn primary<Q: QueryRows<Client, M>, M>(_query: &Q) -> bool {
+            Q::PRIMARY
+        }
+
+        assert!(!primary(&Explain::new(GetTodosByLabel("a"))));
+        assert!(primary(&Explain::new(GetAllTodosFresh)));
+        assert!(primary(&Explain::new(CreateIndex)));
+        assert!(primary(&Explain::new(GetTodosByLabel("a")).analyze()));
+
+        let mut client = Client::open_in_memory().unwrap();
+
+        client.execute(&CreateTodos).unwrap();
+        let rows = client.query(&Explain::new(GetTodosByLabel("a"))).unwrap();
+        let plan = Plan::from_rows(rows);
+        assert_eq!(vec!["test_rusqlite"], plan.full_scans());
+
+        client.execute(&CreateIndex).unwrap();
+        let rows = client.query(&Explain::new(GetTodosByLabel("a"))).unwrap();
+        let plan = Plan::from_rows(rows);
+        assert!(plan.uses_index("test_rusqlite_label"), "{plan}");
+        assert!(plan.full_scans().is_empty());
+    }
+
+    #[test]
+    fn hooks() {
+        use crate::error::ErrorKind;
+        use crate::hook::{Before, Hook};
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        struct NoDrops;
+
+        impl Hook for NoDrops {
+            fn before(&self, _: &str, text: &str, _: usize) -> Before {
+                if text.starts_with("DROP") {
+                    Before::Reject("no drops".into())
+                } else {
+                    Before::Run
+                }
+            }
+        }
+
+        #[derive(Default)]
+        struct Counts(Mutex<Vec<Result<u64, ErrorKind>>>);
+
+        impl Hook for Arc<Counts> {
+            fn after(&self, _: &str, _: &str, result: Result<u64, ErrorKind>, _: Duration) {
+                self.0.lock().unwrap().push(result);
+            }
+        }
+
+        let counts = Arc::new(Counts::default());
+        let mut client = Client::open_in_memory().unwrap();
+        client.add_hook(NoDrops);
+        client.add_hook(counts.clone());
+
+        client.execute(&CreateTodos).unwrap();
+        let mut txn = client.transaction().unwrap();
+        txn.execute(&InsertTodo("a")).unwrap();
+        txn.execute(&InsertTodo("b")).unwrap();
+        txn.commit().unwrap();
+        assert_eq!(2, client.query(&GetAllTodos).unwrap().len());
+
+        let err = client.execute(&DropTodos).unwrap_err();
+        assert_eq!(ErrorKind::Rejected, err.kind());
+
+        assert_eq!(vec![Ok(0), Ok(1), Ok(1), Ok(2)], *counts.0.lock().unwrap());
+    }
+
+    #[test]
+    fn stats() {
+        let stats = crate::stats::Stats::new();
+        let mut client = Client::open_in_memory().unwrap();
+        client.set_stats(stats.clone());
+
+        client.execute(&CreateTodos).unwrap();
+        let mut txn = client.transaction().unwrap();
+        txn.execute(&InsertTodo("a")).unwrap();
+        txn.execute(&InsertTodo("b")).unwrap();
+        txn.commit().unwrap();
+        client.query(&GetAllTodos).unwrap();
+
+        let queries = client.stats().unwrap().snapshot();
+        let calls: Vec<_> = queries
+            .iter()
+            .map(|query| {
+                (
+                    query.query_name.rsplit("::").next().unwrap(),
+                    query.calls,
+                    query.rows,
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("CreateTodos", 1, 0),
+                ("GetAllTodos", 1, 2),
+                ("InsertTodo<'_>", 2, 2)
+            ],
+            calls
+        );
+    }
+
+    #[test]
+    fn comments() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let stats = crate::stats::Stats::new();
+        let mut client = Client::open_in_memory().unwrap();
+        client.set_stats(stats.clone());
+        let request = Arc::new(AtomicUsize::new(0));
+        let context = request.clone();
+        client.set_comments(
+            crate::comment::Comments::new()
+                .tag("application", "test")
+                .context(move || {
+                    vec![(
+                        "request",
+                        context.fetch_add(1, Ordering::Relaxed).to_string(),
+                    )]
+                }),
+        );
+
+        client.execute(&CreateTodos).unwrap();
+        client.prepare::<GetAllTodos>().unwrap();
+        client.query(&GetAllTodos).unwrap();
+        client.query(&GetAllTodos).unwrap();
+
+        let queries = stats.snapshot();
+        assert_eq!(2, queries.len());
+        assert_eq!("SELECT id, label FROM test_rusqlite", queries[1].text);
+        assert_eq!(2, queries[1].calls);
+
+        // SQLite leaves out the per-call tags, to keep using its cache.
+        assert_eq!(0, request.load(Ordering::Relaxed));
+    }
+
     #[test]
     fn end_to_end_file() {
         const TODO_TEXT: &str = "get things done, please!";
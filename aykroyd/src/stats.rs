@@ -0,0 +1,380 @@
+//! Per-query statistics and the slow-query log.
+//!
+//! Give a client a [`Stats`] collector with `set_stats`, and every
+//! `query`, `query_one`, `query_opt` and `execute` on the client and its
+//! transactions is counted, keyed by the Rust type of the query and its
+//! text.  A collector is cheap to clone and can be shared by many
+//! clients, say all the connections in a pool, and retrieved from any
+//! of them with `stats`.
+//!
+//! ```
+//! use aykroyd::stats::Stats;
+//! use std::time::Duration;
+//!
+//! let stats = Stats::new().slow_query_threshold(Duration::from_millis(250));
+//! // client.set_stats(stats.clone());
+//!
+//! for query in stats.snapshot() {
+//!     println!(
+//!         "{}: {} calls, {} errors, p99 {:?}",
+//!         query.query_name,
+//!         query.calls,
+//!         query.errors,
+//!         query.latency.quantile(0.99),
+//!     );
+//! }
+//! ```
+//!
+//! Queries slower than the threshold are kept in the slow-query log,
+//! with their text and parameters.  Derived queries show parameters with
+//! [`DescribeParams`](crate::query::DescribeParams), masking the fields
+//! marked sensitive, and the parameters of other queries are all masked.
+//! With the `tracing` feature slow queries are also logged as a warning,
+//! with target `aykroyd::slow_query`.
+//!
+//! With the `metrics` feature, each query is also recorded with the
+//! [metrics](https://crates.io/crates/metrics) facade, labeled with the
+//! query type: the counters `aykroyd_queries_total`,
+//! `aykroyd_query_errors_total` and `aykroyd_query_rows_total`, and the
+//! histogram `aykroyd_query_duration_seconds`.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::error::ErrorKind;
+use crate::query::describe::REDACTED;
+
+/// The number of slow queries to keep.
+const SLOW_QUERIES: usize = 64;
+
+/// The upper bounds of the latency histogram buckets.
+const BUCKETS: [Duration; 16] = [
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_micros(2500),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+/// A collector of per-query statistics.
+///
+/// Clones share the same statistics.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    inner: Arc<Mutex<Inner>>,
+    slow_query_threshold: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    queries: HashMap<(String, String), QueryStats>,
+    slow_queries: VecDeque<SlowQuery>,
+}
+
+impl Stats {
+    /// A new, empty collector.
+    pub fn new() -> Self {
+        Stats::default()
+    }
+
+    /// Log queries that take longer than the threshold.
+    pub fn slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// The statistics for each query so far, ordered by query type.
+    pub fn snapshot(&self) -> Vec<QueryStats> {
+        let inner = self.inner.lock().unwrap();
+        let mut queries: Vec<_> = inner.queries.values().cloned().collect();
+        queries.sort_by(|a, b| (&a.query_name, &a.text).cmp(&(&b.query_name, &b.text)));
+        queries
+    }
+
+    /// The most recent slow queries, oldest first.
+    pub fn slow_queries(&self) -> Vec<SlowQuery> {
+        let inner = self.inner.lock().unwrap();
+        inner.slow_queries.iter().cloned().collect()
+    }
+
+    /// Forget all the statistics so far.
+    pub fn reset(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.queries.clear();
+        inner.slow_queries.clear();
+    }
+
+    /// Whether slow queries are logged.
+    pub(crate) fn logs_slow_queries(&self) -> bool {
+        self.slow_query_threshold.is_some()
+    }
+
+    /// Record one run of a query.
+    ///
+    /// Without described parameters, they are all redacted.
+    pub(crate) fn record(
+        &self,
+        query_name: &str,
+        text: &str,
+        param_count: usize,
+        params: Option<&[String]>,
+        result: Result<u64, ErrorKind>,
+        elapsed: Duration,
+    ) {
+        #[cfg(feature = "metrics")]
+        record_metrics(query_name, result, elapsed);
+
+        let slow = self
+            .slow_query_threshold
+            .filter(|threshold| elapsed >= *threshold)
+            .map(|_| SlowQuery {
+                query_name: query_name.into(),
+                text: text.into(),
+                params: match params {
+                    Some(params) => params.to_vec(),
+                    None => vec![REDACTED.into(); param_count],
+                },
+                elapsed,
+            });
+
+        #[cfg(feature = "tracing")]
+        if let Some(slow) = &slow {
+            tracing::warn!(target: "aykroyd::slow_query", "{slow}");
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let key = (query_name.to_string(), text.to_string());
+        let query = inner.queries.entry(key).or_insert_with(|| QueryStats {
+            query_name: query_name.into(),
+            text: text.into(),
+            ..QueryStats::default()
+        });
+        query.calls += 1;
+        match result {
+            Ok(rows) => query.rows += rows,
+            Err(_) => query.errors += 1,
+        }
+        query.latency.record(elapsed);
+
+        if let Some(slow) = slow {
+            if inner.slow_queries.len() == SLOW_QUERIES {
+                inner.slow_queries.pop_front();
+            }
+            inner.slow_queries.push_back(slow);
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn record_metrics(query_name: &str, result: Result<u64, ErrorKind>, elapsed: Duration) {
+    let query = query_name.to_string();
+    metrics::counter!("aykroyd_queries_total", "query" => query.clone()).increment(1);
+    match result {
+        Ok(rows) => {
+            metrics::counter!("aykroyd_query_rows_total", "query" => query.clone()).increment(rows)
+        }
+        Err(_) => {
+            metrics::counter!("aykroyd_query_errors_total", "query" => query.clone()).increment(1)
+        }
+    }
+    metrics::histogram!("aykroyd_query_duration_seconds", "query" => query)
+        .record(elapsed.as_secs_f64());
+}
+
+/// The statistics for one query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryStats {
+    /// The Rust type of the query.
+    pub query_name: String,
+    /// The text of the query.
+    pub text: String,
+    /// The number of times the query was run.
+    pub calls: u64,
+    /// The number of times the query failed.
+    pub errors: u64,
+    /// The total number of rows returned, or for statements affected.
+    pub rows: u64,
+    /// How long the query took.
+    pub latency: Histogram,
+}
+
+/// A histogram of query latencies.
+///
+/// The buckets grow from 100 microseconds up to 10 seconds, so quantiles
+/// are approximate, rounded up to the bucket boundary.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    counts: [u64; BUCKETS.len() + 1],
+    sum: Duration,
+    max: Duration,
+}
+
+impl Histogram {
+    fn record(&mut self, elapsed: Duration) {
+        let bucket = BUCKETS.partition_point(|bound| *bound < elapsed);
+        self.counts[bucket] += 1;
+        self.sum += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    /// The number of latencies recorded.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The total of all latencies.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// The longest latency.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The average latency.
+    pub fn mean(&self) -> Duration {
+        match self.count() {
+            0 => Duration::ZERO,
+            count => self.sum / count as u32,
+        }
+    }
+
+    /// The latency below which the given fraction of queries fall.
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        let rank = (quantile.clamp(0.0, 1.0) * self.count() as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bound, count) in self.buckets() {
+            seen += count;
+            if seen >= rank.max(1) {
+                return bound.unwrap_or(self.max).min(self.max);
+            }
+        }
+        Duration::ZERO
+    }
+
+    /// The count in each bucket, with its upper bound.
+    ///
+    /// The last bucket has no upper bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        BUCKETS
+            .iter()
+            .copied()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .zip(self.counts.iter().copied())
+    }
+}
+
+/// A query that took longer than the slow-query threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowQuery {
+    /// The Rust type of the query.
+    pub query_name: String,
+    /// The text of the query.
+    pub text: String,
+    /// The parameters, with sensitive ones masked.
+    pub params: Vec<String>,
+    /// How long the query took.
+    pub elapsed: Duration,
+}
+
+impl std::fmt::Display for SlowQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "slow query {} took {:?}: {}",
+            self.query_name,
+            self.elapsed,
+            self.text.trim(),
+        )?;
+        for (index, param) in self.params.iter().enumerate() {
+            let sep = if index == 0 { " with " } else { ", " };
+            write!(f, "{sep}${} = {param}", index + 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let stats = Stats::new().slow_query_threshold(Duration::from_millis(100));
+        let shared = stats.clone();
+        let ms = Duration::from_millis;
+
+        let params = ["\"milk\"".to_string(), REDACTED.into()];
+        stats.record("GetTodos", "SELECT * FROM todos", 0, None, Ok(3), ms(2));
+        shared.record("GetTodos", "SELECT * FROM todos", 0, None, Ok(2), ms(4));
+        stats.record(
+            "InsertTodo",
+            "INSERT INTO todos",
+            2,
+            None,
+            Err(ErrorKind::Query),
+            ms(200),
+        );
+        stats.record(
+            "InsertTodo",
+            "INSERT INTO todos",
+            2,
+            Some(&params),
+            Ok(1),
+            ms(300),
+        );
+
+        let queries = stats.snapshot();
+        assert_eq!(2, queries.len());
+        assert_eq!("GetTodos", queries[0].query_name);
+        assert_eq!(2, queries[0].calls);
+        assert_eq!(0, queries[0].errors);
+        assert_eq!(5, queries[0].rows);
+        assert_eq!(ms(3), queries[0].latency.mean());
+        assert_eq!(ms(4), queries[0].latency.max());
+        assert_eq!(1, queries[1].errors);
+
+        let slow = shared.slow_queries();
+        assert_eq!(2, slow.len());
+        assert_eq!(
+            "slow query InsertTodo took 200ms: INSERT INTO todos with $1 = <redacted>, $2 = <redacted>",
+            slow[0].to_string(),
+        );
+        assert_eq!(
+            "slow query InsertTodo took 300ms: INSERT INTO todos with $1 = \"milk\", $2 = <redacted>",
+            slow[1].to_string(),
+        );
+
+        stats.reset();
+        assert!(shared.snapshot().is_empty());
+    }
+
+    #[test]
+    fn quantile() {
+        let mut histogram = Histogram::default();
+        assert_eq!(Duration::ZERO, histogram.quantile(0.5));
+
+        for ms in 1..=100 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(100, histogram.count());
+        assert_eq!(Duration::from_millis(50), histogram.quantile(0.5));
+        assert_eq!(Duration::from_millis(100), histogram.quantile(0.99));
+        assert_eq!(Duration::from_millis(1), histogram.quantile(0.0));
+        assert_eq!(Some((None, 0)), histogram.buckets().last());
+    }
+}
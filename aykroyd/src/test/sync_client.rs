@@ -1,4 +1,5 @@
-use crate::{client, error, query, FromRow, Query, QueryOne, Statement};
+use crate::list::{ParamList, ToParamList};
+use crate::{builder, bulk, client, error, query, verify, QueryRows, QueryRowsOne, Statement};
 
 #[derive(Debug, Default, Clone)]
 pub struct TestClient {
@@ -10,6 +11,7 @@ pub struct TestClient {
     transaction_results: Vec<Result<()>>,
     commit_results: Vec<Result<()>>,
     rollback_results: Vec<Result<()>>,
+    describe_results: Vec<Result<verify::Description<String>>>,
     records: Vec<Record>,
 }
 
@@ -35,6 +37,10 @@ impl TestClient {
         self.query_one_results.push(result);
     }
 
+    pub fn push_describe_result(&mut self, result: Result<verify::Description<String>>) {
+        self.describe_results.push(result);
+    }
+
     pub fn records(&self) -> &[Record] {
         &self.records[..]
     }
@@ -89,18 +95,62 @@ pub trait ToParam {
     fn to_param(&self) -> String;
 }
 
+impl ToParam for String {
+    fn to_param(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ToParam for i32 {
+    fn to_param(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToParam for i64 {
+    fn to_param(&self) -> String {
+        self.to_string()
+    }
+}
+
 impl<T: ToParam> client::ToParam<TestClient> for T {
     fn to_param(&self) -> &dyn ToParam {
         self
     }
 }
 
+impl<L> ToParamList<TestClient> for L
+where
+    L: ParamList,
+    L::Item: ToParam,
+{
+    fn to_params(&self) -> Vec<&dyn ToParam> {
+        self.items().iter().map(client::ToParam::to_param).collect()
+    }
+}
+
 impl client::FromColumnIndexed<TestClient> for String {
     fn from_column(row: &Row<'_>, index: usize) -> Result<Self> {
         Ok(row.1.values[index].clone()) // TODO: not panic
     }
 }
 
+impl client::FromColumnIndexed<TestClient> for i64 {
+    fn from_column(row: &Row<'_>, index: usize) -> Result<Self> {
+        row.1.values[index]
+            .parse()
+            .map_err(|_| error::Error::from_column_str("not an integer", None))
+    }
+}
+
+impl client::FromColumnIndexed<TestClient> for bool {
+    fn from_column(row: &Row<'_>, index: usize) -> Result<Self> {
+        row.1.values[index]
+            .parse()
+            .map_err(|_| error::Error::from_column_str("not a boolean", None))
+    }
+}
+
 impl client::FromColumnNamed<TestClient> for String {
     fn from_column(row: &Row<'_>, name: &str) -> Result<Self> {
         let index = row
@@ -128,42 +178,67 @@ impl client::Client for TestClient {
     type Error = ErrorDetails;
 }
 
+impl builder::Placeholder for TestClient {
+    fn placeholder(index: usize) -> String {
+        format!("${index}")
+    }
+
+    fn literal_question_mark() -> bool {
+        true
+    }
+}
+
+impl bulk::ParamLimit for TestClient {
+    fn param_limit() -> usize {
+        4
+    }
+}
+
+impl verify::ClientTypes for TestClient {
+    type Type = String;
+}
+
+impl verify::Describe for TestClient {
+    fn describe(&mut self, query_text: &str) -> Result<verify::Description<String>> {
+        self.prepare_internal(query_text)?;
+        self.describe_results.pop().unwrap()
+    }
+}
+
 impl TestClient {
-    pub fn prepare<S: query::StaticQueryText>(&mut self) -> Result<()> {
+    fn prepare_internal(&mut self, query_text: &str) -> Result<()> {
         self.records.push(Record {
-            text: S::QUERY_TEXT.into(),
+            text: query_text.into(),
             params: None,
             kind: Kind::Prepare,
         });
         self.prepare_results.pop().unwrap_or(Ok(()))
     }
 
-    pub fn query<Q: Query<Self>>(&mut self, query: &Q) -> Result<Vec<Q::Row>> {
+    pub fn prepare<S: query::StaticQueryText>(&mut self) -> Result<()> {
+        self.prepare_internal(S::QUERY_TEXT)
+    }
+
+    pub fn query<Q: QueryRows<Self, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>> {
         self.records.push(Record {
             text: query.query_text(),
-            params: query.to_params().map(|params| {
-                params
-                    .into_iter()
-                    .map(ToParam::to_param)
-                    .collect()
-            }),
+            params: query
+                .to_params()
+                .map(|params| params.into_iter().map(ToParam::to_param).collect()),
             kind: Kind::Query,
         });
         self.query_results.pop().unwrap().and_then(|rows| {
             let statement = TestStatement::new(self);
-            FromRow::from_rows(&statement.execute(rows))
+            query.map_rows(&statement.execute(rows))
         })
     }
 
-    pub fn query_opt<Q: QueryOne<Self>>(&mut self, query: &Q) -> Result<Option<Q::Row>> {
+    pub fn query_opt<Q: QueryRowsOne<Self, M>, M>(&mut self, query: &Q) -> Result<Option<Q::Row>> {
         self.records.push(Record {
             text: query.query_text(),
-            params: query.to_params().map(|params| {
-                params
-                    .into_iter()
-                    .map(ToParam::to_param)
-                    .collect()
-            }),
+            params: query
+                .to_params()
+                .map(|params| params.into_iter().map(ToParam::to_param).collect()),
             kind: Kind::QueryOpt,
         });
         self.query_opt_results
@@ -172,44 +247,38 @@ impl TestClient {
             .and_then(|maybe_maybe_row| {
                 let statement = TestStatement::new(self);
                 Ok(match maybe_maybe_row {
-                    Some(Some(row)) => Some(FromRow::from_row(&statement.execute_one(row))?),
+                    Some(Some(row)) => Some(query.map_row(&statement.execute_one(row))?),
                     _ => None,
                 })
             })
     }
 
-    pub fn query_one<Q: QueryOne<Self>>(&mut self, query: &Q) -> Result<Q::Row> {
+    pub fn query_one<Q: QueryRowsOne<Self, M>, M>(&mut self, query: &Q) -> Result<Q::Row> {
         self.records.push(Record {
             text: query.query_text(),
-            params: query.to_params().map(|params| {
-                params
-                    .into_iter()
-                    .map(ToParam::to_param)
-                    .collect()
-            }),
+            params: query
+                .to_params()
+                .map(|params| params.into_iter().map(ToParam::to_param).collect()),
             kind: Kind::QueryOne,
         });
         self.query_one_results.pop().unwrap().and_then(|row| {
             let statement = TestStatement::new(self);
-            FromRow::from_row(&statement.execute_one(row))
+            query.map_row(&statement.execute_one(row))
         })
     }
 
     pub fn execute<S: Statement<Self>>(&mut self, statement: &S) -> Result<u64> {
         self.records.push(Record {
             text: statement.query_text(),
-            params: statement.to_params().map(|params| {
-                params
-                    .into_iter()
-                    .map(ToParam::to_param)
-                    .collect()
-            }),
+            params: statement
+                .to_params()
+                .map(|params| params.into_iter().map(ToParam::to_param).collect()),
             kind: Kind::Statement,
         });
         self.execute_results.pop().unwrap_or(Ok(0))
     }
 
-    pub fn transaction(&mut self) -> Result<Transaction> {
+    pub fn transaction(&mut self) -> Result<Transaction<'_>> {
         self.records.push(Record {
             text: "BEGIN".into(),
             params: None,
@@ -253,15 +322,18 @@ impl<'a> Transaction<'a> {
         self.as_mut().prepare::<S>()
     }
 
-    pub fn query<Q: Query<TestClient>>(&mut self, query: &Q) -> Result<Vec<Q::Row>> {
+    pub fn query<Q: QueryRows<TestClient, M>, M>(&mut self, query: &Q) -> Result<Vec<Q::Row>> {
         self.as_mut().query(query)
     }
 
-    pub fn query_opt<Q: QueryOne<TestClient>>(&mut self, query: &Q) -> Result<Option<Q::Row>> {
+    pub fn query_opt<Q: QueryRowsOne<TestClient, M>, M>(
+        &mut self,
+        query: &Q,
+    ) -> Result<Option<Q::Row>> {
         self.as_mut().query_opt(query)
     }
 
-    pub fn query_one<Q: QueryOne<TestClient>>(&mut self, query: &Q) -> Result<Q::Row> {
+    pub fn query_one<Q: QueryRowsOne<TestClient, M>, M>(&mut self, query: &Q) -> Result<Q::Row> {
         self.as_mut().query_one(query)
     }
 
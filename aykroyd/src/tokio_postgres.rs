@@ -1,11 +1,33 @@
 //! An asynchronous, pipelined, PostgreSQL client.
 
+use std::any::type_name;
+use std::time::Instant;
+
+use crate::bulk::InsertValues;
 use crate::client::{FromColumnIndexed, FromColumnNamed, ToParam};
-use crate::query::StaticQueryText;
-use crate::{error, FromRow, Query, QueryOne, Statement};
+use crate::combinator::ReturningId;
+use crate::comment::Comments;
+use crate::hook::{Hook, Hooks};
+use crate::list::{InList, ToParamList};
+use crate::notify::Notification;
+#[cfg(feature = "json")]
+use crate::plan;
+use crate::query::{Returning, StaticQueryText, ToParams};
+use crate::stats::Stats;
+use crate::verify::{
+    ColumnType, DescribeQuery, DescribeVariants, Description, Mismatch, ParamType,
+    VariantMismatches,
+};
+use crate::{
+    builder, bulk, cache, copy, debug, error, notify, replica, trace, verify, Query, QueryRows,
+    QueryRowsOne, Statement,
+};
 
 pub type Error = error::Error<tokio_postgres::Error>;
 
+/// The OpenTelemetry name of the database, for tracing spans.
+const DB_SYSTEM: &str = "postgresql";
+
 /// A convenience function which parses a connection string and connects to the database.
 ///
 /// See the documentation for [`tokio_postgres::Config`] for details on the connection string format.
@@ -32,10 +54,7 @@ impl<T> FromColumnIndexed<Client> for T
 where
     T: tokio_postgres::types::FromSqlOwned,
 {
-    fn from_column(
-        row: &tokio_postgres::Row,
-        index: usize,
-    ) -> Result<Self, Error> {
+    fn from_column(row: &tokio_postgres::Row, index: usize) -> Result<Self, Error> {
         row.try_get(index).map_err(Error::from_column)
     }
 }
@@ -44,10 +63,7 @@ impl<T> FromColumnNamed<Client> for T
 where
     T: tokio_postgres::types::FromSqlOwned,
 {
-    fn from_column(
-        row: &tokio_postgres::Row,
-        name: &str,
-    ) -> Result<Self, Error> {
+    fn from_column(row: &tokio_postgres::Row, name: &str) -> Result<Self, Error> {
         row.try_get(name).map_err(Error::from_column)
     }
 }
@@ -61,16 +77,187 @@ where
     }
 }
 
+impl<'a, T> ToParamList<Client> for InList<'a, T>
+where
+    T: tokio_postgres::types::ToSql + Sync,
+{
+    fn to_params(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![&self.0]
+    }
+}
+
+impl<T> ToParamList<Client> for &[T]
+where
+    T: tokio_postgres::types::ToSql + Sync,
+{
+    fn to_params(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![self]
+    }
+}
+
+impl<T> ToParamList<Client> for Vec<T>
+where
+    T: tokio_postgres::types::ToSql + Sync,
+{
+    fn to_params(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![self]
+    }
+}
+
+impl<T> ParamType<Client> for T
+where
+    T: tokio_postgres::types::ToSql,
+{
+    fn accepts(ty: &tokio_postgres::types::Type) -> bool {
+        <T as tokio_postgres::types::ToSql>::accepts(ty)
+    }
+}
+
+impl<T> ColumnType<Client> for T
+where
+    T: tokio_postgres::types::FromSqlOwned,
+{
+    fn accepts(ty: &tokio_postgres::types::Type) -> bool {
+        <T as tokio_postgres::types::FromSql>::accepts(ty)
+    }
+
+    fn accepts_null() -> bool {
+        <T as tokio_postgres::types::FromSql>::from_sql_null(&tokio_postgres::types::Type::TEXT)
+            .is_ok()
+    }
+}
+
+/// Splits the notifications off a connection.
+///
+/// Returns the notifications, and a future to spawn in place of the
+/// connection.  Subscribe to notifications of each type you `listen`
+/// for, see the [`notify`](crate::notify) module.
+pub fn notifications<S, T>(
+    mut connection: tokio_postgres::Connection<S, T>,
+) -> (
+    Notifications,
+    impl std::future::Future<Output = Result<(), Error>>,
+)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let (sender, _) = tokio::sync::broadcast::channel(NOTIFICATIONS_CAPACITY);
+    let notifications = Notifications {
+        sender: sender.clone(),
+    };
+
+    let connection = async move {
+        while let Some(message) = std::future::poll_fn(|cx| connection.poll_message(cx)).await {
+            if let tokio_postgres::AsyncMessage::Notification(notification) =
+                message.map_err(Error::connect)?
+            {
+                // No subscribers is fine, the notification is dropped.
+                let _ = sender.send(notification);
+            }
+        }
+        Ok(())
+    };
+
+    (notifications, connection)
+}
+
+/// How many notifications to hold for a slow subscriber.
+const NOTIFICATIONS_CAPACITY: usize = 1024;
+
+/// The notifications received on a connection.
+///
+/// See [`notifications`].
+#[derive(Clone)]
+pub struct Notifications {
+    sender: tokio::sync::broadcast::Sender<tokio_postgres::Notification>,
+}
+
+impl Notifications {
+    /// A stream of the notifications of type `N`.
+    ///
+    /// Notifications on other channels are skipped.  Notifications are
+    /// only received after subscribing, and if a subscriber falls too far
+    /// behind, it gets an error for the notifications it missed.
+    pub fn subscribe<N: Notification>(&self) -> impl futures_util::Stream<Item = Result<N, Error>> {
+        use tokio::sync::broadcast::error::RecvError;
+
+        futures_util::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(notification) => {
+                        if let Some(notification) = notify::parse(&notification) {
+                            return Some((notification, receiver));
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        let message = format!("missed {missed} notifications");
+                        return Some((Err(Error::query_str(message, None)), receiver));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
 /// An asynchronous PostgreSQL client.
 pub struct Client {
     client: tokio_postgres::Client,
-    statements: std::collections::HashMap<String, tokio_postgres::Statement>,
+    statements: cache::Statements<tokio_postgres::Statement>,
+    hooks: Hooks,
 }
 
 impl crate::client::Client for Client {
     type Row<'a> = tokio_postgres::Row;
     type Param<'a> = &'a (dyn tokio_postgres::types::ToSql + Sync);
     type Error = tokio_postgres::Error;
+
+    const ARRAY_PARAMS: bool = true;
+}
+
+impl builder::Placeholder for Client {
+    fn placeholder(index: usize) -> String {
+        format!("${index}")
+    }
+
+    fn literal_question_mark() -> bool {
+        true
+    }
+}
+
+impl debug::Quote for Client {
+    fn quote_bytes(value: &[u8]) -> String {
+        let hex: String = value.iter().map(|byte| format!("{byte:02x}")).collect();
+        format!("'\\x{hex}'::bytea")
+    }
+}
+
+impl bulk::ParamLimit for Client {
+    fn param_limit() -> usize {
+        65535
+    }
+}
+
+#[cfg(feature = "json")]
+impl plan::Dialect for Client {
+    fn explain_text(query_text: &str, analyze: bool) -> String {
+        let analyze = if analyze { "ANALYZE, " } else { "" };
+        format!("EXPLAIN ({analyze}FORMAT JSON) {query_text}")
+    }
+
+    fn plan_row(row: &tokio_postgres::Row) -> Result<plan::PlanRow, Error> {
+        let value: serde_json::Value = row.try_get(0).map_err(Error::from_column)?;
+        Ok(plan::PlanRow::new(
+            None,
+            None,
+            plan::from_postgres_json(&value),
+        ))
+    }
+}
+
+impl verify::ClientTypes for Client {
+    type Type = tokio_postgres::types::Type;
 }
 
 impl AsMut<tokio_postgres::Client> for Client {
@@ -94,24 +281,82 @@ impl From<tokio_postgres::Client> for Client {
 impl Client {
     /// Create a new `Client` from a `tokio_postgres::Client`.
     pub fn new(client: tokio_postgres::Client) -> Self {
-        let statements = std::collections::HashMap::new();
-        Client { client, statements }
+        let statements = cache::Statements::default();
+        let hooks = Hooks::default();
+        Client {
+            client,
+            statements,
+            hooks,
+        }
+    }
+
+    /// Add a hook to run around each query, on this client and its transactions.
+    ///
+    /// See the [`hook`](crate::hook) module for details.
+    pub fn add_hook<H: Hook + 'static>(&mut self, hook: H) {
+        self.hooks.add(hook);
+    }
+
+    /// Collect statistics for each query, on this client and its transactions.
+    ///
+    /// See the [`stats`](crate::stats) module for details.
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.hooks.set_stats(stats);
+    }
+
+    /// The statistics collected for this client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.hooks.stats()
+    }
+
+    /// Append a comment to each query, on this client and its transactions.
+    ///
+    /// See the [`comment`](crate::comment) module for details.
+    pub fn set_comments(&mut self, comments: Comments) {
+        self.hooks.set_comments(comments);
     }
 
     async fn prepare_internal<S: Into<String>>(
         &mut self,
+        query_name: Option<&str>,
         query_text: S,
     ) -> Result<tokio_postgres::Statement, Error> {
-        match self.statements.entry(query_text.into()) {
-            std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.get().clone()),
-            std::collections::hash_map::Entry::Vacant(entry) => {
-                let statement = self
-                    .client
-                    .prepare(entry.key())
+        let query_text = query_text.into();
+        if let Some(statement) = self.statements.get(&query_text) {
+            trace::cache_hit(true);
+            return Ok(statement);
+        }
+
+        trace::cache_hit(false);
+        let statement = match query_name {
+            Some(query_name) => {
+                let annotated = self.hooks.annotate(query_name, query_text.clone());
+                self.client.prepare(&annotated).await
+            }
+            None => self.client.prepare(&query_text).await,
+        }
+        .map_err(Error::prepare)?;
+        self.statements.insert(query_text, statement.clone());
+        Ok(statement)
+    }
+
+    /// Prepare a query to run.  If its comments have per-call tags, it's
+    /// prepared for this call only, otherwise it goes through the cache.
+    async fn prepare_query(
+        &mut self,
+        query_name: &str,
+        query_text: String,
+        once: Option<String>,
+    ) -> Result<tokio_postgres::Statement, Error> {
+        match once {
+            Some(annotated) => {
+                trace::cache_hit(false);
+                self.client
+                    .prepare(&annotated)
                     .await
-                    .map_err(Error::prepare)?;
-                Ok(entry.insert(statement).clone())
+                    .map_err(Error::prepare)
             }
+            None => self.prepare_internal(Some(query_name), query_text).await,
         }
     }
 
@@ -140,13 +385,151 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn prepare<S: StaticQueryText>(
-        &mut self,
-    ) -> Result<(), Error> {
-        self.prepare_internal(S::QUERY_TEXT).await?;
+    pub async fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), S::QUERY_TEXT);
+        span.instrument(async move {
+            self.prepare_internal(Some(type_name::<S>()), S::QUERY_TEXT)
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub async fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Self> + Returning,
+        S::Id: FromColumnIndexed<Self>,
+    {
+        self.query_one(&ReturningId::new(statement)).await
+    }
+
+    /// Prepares every query in the [`registry`](crate::registry).
+    ///
+    /// Call this after connecting to catch invalid query text at
+    /// startup rather than first use.  It also warms the statement cache,
+    /// but that holds at most 256 statements.
+    /// Every registered query is prepared, including those of other
+    /// crates, so they should all be meant for this database.  If not,
+    /// use [`prepare_all_matching`](Self::prepare_all_matching).
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub async fn prepare_all(&mut self) -> Result<(), Error> {
+        self.prepare_all_matching(|_| true).await
+    }
+
+    /// Prepares the queries in the [`registry`](crate::registry) that
+    /// match the filter.
+    ///
+    /// Use this when not every registered query is meant for this
+    /// database, e.g. `|query| query.in_module("my_app::sqlite")`.
+    #[cfg(feature = "derive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+    pub async fn prepare_all_matching<F>(&mut self, mut filter: F) -> Result<(), Error>
+    where
+        F: FnMut(&crate::registry::QueryInfo) -> bool,
+    {
+        for query in crate::registry::queries().filter(|query| filter(query)) {
+            let query_text = query.text_for::<Self>();
+            let span = trace::Span::new(DB_SYSTEM, "prepare", query.name(), &query_text);
+            span.instrument(self.prepare_internal(Some(query.name()), query_text))
+                .await?;
+        }
         Ok(())
     }
 
+    /// Prepares a statement, and describes its parameters and result columns.
+    ///
+    /// We'll also look up the nullability of any result columns that come
+    /// directly from a table.  See the [`verify`](crate::verify) module.
+    pub async fn describe(
+        &mut self,
+        query_text: &str,
+    ) -> Result<Description<tokio_postgres::types::Type>, Error> {
+        let statement = self.prepare_internal(None, query_text).await?;
+
+        let params = statement.params().iter().cloned().map(Some).collect();
+
+        let mut columns = vec![];
+        for column in statement.columns() {
+            let nullable = match (column.table_oid(), column.column_id()) {
+                (Some(table_oid), Some(column_id)) => self
+                    .client
+                    .query_opt(
+                        "SELECT NOT attnotnull FROM pg_catalog.pg_attribute \
+                        WHERE attrelid = $1 AND attnum = $2",
+                        &[&table_oid, &column_id],
+                    )
+                    .await
+                    .map_err(Error::query)?
+                    .map(|row| row.get(0)),
+                _ => None,
+            };
+            columns.push(verify::Column {
+                name: column.name().into(),
+                ty: Some(column.type_().clone()),
+                nullable,
+            });
+        }
+
+        Ok(Description { params, columns })
+    }
+
+    /// Verifies a query or statement against the database.
+    ///
+    /// Returns every mismatch found between the database's description
+    /// of the statement and the Rust types.  See the [`verify`](crate::verify)
+    /// module for details.
+    ///
+    /// ```no_run
+    /// # async fn xmain() -> Result<(), aykroyd::tokio_postgres::Error> {
+    /// # use aykroyd::{Query, FromRow};
+    /// # use aykroyd::tokio_postgres::connect;
+    /// # use tokio_postgres::NoTls;
+    /// # #[derive(FromRow)]
+    /// # pub struct Customer {
+    /// #   id: i32,
+    /// #   first: String,
+    /// #   last: String,
+    /// # }
+    /// #[derive(Query)]
+    /// #[aykroyd(row(Customer), text = "
+    ///     SELECT id, first, last FROM customers WHERE first = $1
+    /// ")]
+    /// pub struct GetCustomersByFirstName<'a>(&'a str);
+    ///
+    /// let (mut client, conn) = connect("host=localhost user=postgres", NoTls).await?;
+    ///
+    /// // Check the query against the database.
+    /// let mismatches = client.verify::<GetCustomersByFirstName>().await?;
+    /// assert!(mismatches.is_empty(), "{mismatches:?}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn verify<Q: StaticQueryText + DescribeQuery<Self>>(
+        &mut self,
+    ) -> Result<Vec<Mismatch>, Error> {
+        let description = self.describe(Q::QUERY_TEXT).await?;
+        Ok(verify::check::<Self, Q>(&description))
+    }
+
+    /// Verifies each variant of a query enum against the database.
+    ///
+    /// Returns the name of each variant with the mismatches found for it.
+    /// See [`verify`](Self::verify) for details.
+    pub async fn verify_variants<Q: DescribeVariants<Self>>(
+        &mut self,
+    ) -> Result<VariantMismatches, Error> {
+        let mut results = vec![];
+        for variant in Q::describe_variants() {
+            let description = self.describe(variant.text()).await?;
+            results.push((variant.name(), variant.check(&description)));
+        }
+        Ok(results)
+    }
+
     /// Executes a statement, returning the resulting rows.
     ///
     /// We'll prepare the statement first if we haven't yet.
@@ -177,21 +560,27 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn query<Q: Query<Self>>(
+    pub async fn query<Q: QueryRows<Self, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Vec<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text()).await?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run_async(|rows| rows.len() as u64, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<Q>(), query_text, once)
+                .await?;
 
-        let rows = self
-            .client
-            .query(&statement, params)
-            .await
-            .map_err(Error::query)?;
+            let rows = self
+                .client
+                .query(&statement, params)
+                .await
+                .map_err(Error::query)?;
 
-        FromRow::from_rows(&rows)
+            query.map_rows(&rows)
+        })
+        .await
     }
 
     /// Executes a statement which returns a single row, returning it.
@@ -223,21 +612,27 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn query_one<Q: QueryOne<Self>>(
+    pub async fn query_one<Q: QueryRowsOne<Self, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Q::Row, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text()).await?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_one", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run_async(|_| 1, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<Q>(), query_text, once)
+                .await?;
 
-        let row = self
-            .client
-            .query_one(&statement, params)
-            .await
-            .map_err(Error::query)?;
+            let row = self
+                .client
+                .query_one(&statement, params)
+                .await
+                .map_err(Error::query)?;
 
-        FromRow::from_row(&row)
+            query.map_row(&row)
+        })
+        .await
     }
 
     /// Executes a statement which returns zero or one rows, returning it.
@@ -270,21 +665,27 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn query_opt<Q: QueryOne<Self>>(
+    pub async fn query_opt<Q: QueryRowsOne<Self, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Option<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text()).await?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_opt", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run_async(|row| row.is_some() as u64, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<Q>(), query_text, once)
+                .await?;
 
-        let row = self
-            .client
-            .query_opt(&statement, params)
-            .await
-            .map_err(Error::query)?;
+            let row = self
+                .client
+                .query_opt(&statement, params)
+                .await
+                .map_err(Error::query)?;
 
-        row.map(|row| FromRow::from_row(&row)).transpose()
+            row.map(|row| query.map_row(&row)).transpose()
+        })
+        .await
     }
 
     /// Executes a statement, returning the number of rows modified.
@@ -310,27 +711,123 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn execute<S: Statement<Self>>(
+    pub async fn execute<S: Statement<Self>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "execute", statement)?;
+        let once = self.hooks.annotate_once(type_name::<S>(), &query_text);
+        span.run_async(|rows_affected| *rows_affected, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<S>(), query_text, once)
+                .await?;
+
+            let rows_affected = self
+                .client
+                .execute(&statement, params)
+                .await
+                .map_err(Error::query)?;
+
+            Ok(rows_affected)
+        })
+        .await
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit, all in one transaction.  Returns the total number of rows
+    /// affected.  See the [`bulk`](crate::bulk) module for details.
+    pub async fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Self>,
+    {
+        let mut txn = self.transaction().await?;
+        let rows_affected = txn.insert_many(rows).await?;
+        txn.commit().await?;
+        Ok(rows_affected)
+    }
+
+    /// Copies rows into a table, returning the number of rows copied.
+    ///
+    /// The table may be followed by a list of columns.  The rows are
+    /// sent in the binary format, with the column types looked up by
+    /// preparing a query of the same columns.  See the
+    /// [`copy`](crate::copy) module for details.
+    pub async fn copy_in<T: ToParams<Self>>(
         &mut self,
-        statement: &S,
+        table: &str,
+        rows: &[T],
     ) -> Result<u64, Error> {
-        let params = statement.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(statement.query_text()).await?;
+        let statement = self
+            .prepare_internal(None, copy::select_text(table))
+            .await?;
+        let types = copy::column_types(&statement);
 
-        let rows_affected = self
+        let sink = self
             .client
-            .execute(&statement, &params)
+            .copy_in(&copy::copy_in_text(table))
             .await
             .map_err(Error::query)?;
 
-        Ok(rows_affected)
+        let writer = tokio_postgres::binary_copy::BinaryCopyInWriter::new(sink, &types);
+        copy_in_rows(writer, &types, rows).await
+    }
+
+    /// Copies out the rows of a query.
+    ///
+    /// The rows are read in the binary format, so they must load their
+    /// columns by index.  See the [`copy`](crate::copy) module for details.
+    pub async fn copy_out<Q>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error>
+    where
+        Q: StaticQueryText + Query<copy::Binary>,
+    {
+        let statement = self.prepare_internal(None, Q::QUERY_TEXT).await?;
+        let types = copy::column_types(&statement);
+
+        let stream = self
+            .client
+            .copy_out(&copy::copy_out_text(Q::QUERY_TEXT))
+            .await
+            .map_err(Error::query)?;
+
+        copy_out_rows(stream, &types, query).await
+    }
+
+    /// Listens for notifications of type `N`, on its channel.
+    ///
+    /// Notifications are received on the connection, see
+    /// [`notifications`] and the [`notify`](crate::notify) module.
+    pub async fn listen<N: Notification>(&mut self) -> Result<(), Error> {
+        self.client
+            .batch_execute(&notify::listen_text::<N>())
+            .await
+            .map_err(Error::query)
+    }
+
+    /// Stops listening for notifications of type `N`.
+    pub async fn unlisten<N: Notification>(&mut self) -> Result<(), Error> {
+        self.client
+            .batch_execute(&notify::unlisten_text::<N>())
+            .await
+            .map_err(Error::query)
+    }
+
+    /// Sends a notification to any listeners on its channel.
+    pub async fn notify<N: Notification>(&mut self, notification: &N) -> Result<(), Error> {
+        let payload = notification.to_payload()?;
+        let statement = self.prepare_internal(None, notify::NOTIFY_TEXT).await?;
+
+        self.client
+            .execute(&statement, &[&N::CHANNEL, &payload])
+            .await
+            .map_err(Error::query)?;
+
+        Ok(())
     }
 
     /// Begins a new database transaction.
     ///
     /// The transaction will roll back by default - use the `commit` method to commit it.
-    pub async fn transaction(&mut self) -> Result<Transaction, Error> {
+    pub async fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
         Ok(Transaction {
             txn: self
                 .client
@@ -338,6 +835,7 @@ impl Client {
                 .await
                 .map_err(Error::transaction)?,
             statements: &mut self.statements,
+            hooks: self.hooks.clone(),
         })
     }
 }
@@ -348,37 +846,163 @@ impl Client {
 /// `commit` method to commit the changes made in the transaction.
 pub struct Transaction<'a> {
     txn: tokio_postgres::Transaction<'a>,
-    statements: &'a mut std::collections::HashMap<String, tokio_postgres::Statement>,
+    statements: &'a mut cache::Statements<tokio_postgres::Statement>,
+    hooks: Hooks,
 }
 
 impl<'a> Transaction<'a> {
+    /// The statistics collected for the client, if any.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.hooks.stats()
+    }
+
     async fn prepare_internal<S: Into<String>>(
         &mut self,
+        query_name: Option<&str>,
         query_text: S,
     ) -> Result<tokio_postgres::Statement, Error> {
-        match self.statements.entry(query_text.into()) {
-            std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.get().clone()),
-            std::collections::hash_map::Entry::Vacant(entry) => {
-                let statement = self
-                    .txn
-                    .prepare(entry.key())
-                    .await
-                    .map_err(Error::prepare)?;
-                Ok(entry.insert(statement).clone())
+        let query_text = query_text.into();
+        if let Some(statement) = self.statements.get(&query_text) {
+            trace::cache_hit(true);
+            return Ok(statement);
+        }
+
+        trace::cache_hit(false);
+        let statement = match query_name {
+            Some(query_name) => {
+                let annotated = self.hooks.annotate(query_name, query_text.clone());
+                self.txn.prepare(&annotated).await
+            }
+            None => self.txn.prepare(&query_text).await,
+        }
+        .map_err(Error::prepare)?;
+        self.statements.insert(query_text, statement.clone());
+        Ok(statement)
+    }
+
+    /// Prepare a query to run.  If its comments have per-call tags, it's
+    /// prepared for this call only, otherwise it goes through the cache.
+    async fn prepare_query(
+        &mut self,
+        query_name: &str,
+        query_text: String,
+        once: Option<String>,
+    ) -> Result<tokio_postgres::Statement, Error> {
+        match once {
+            Some(annotated) => {
+                trace::cache_hit(false);
+                self.txn.prepare(&annotated).await.map_err(Error::prepare)
             }
+            None => self.prepare_internal(Some(query_name), query_text).await,
         }
     }
 
     /// Consumes the transaction, committing all changes made within it.
     pub async fn commit(self) -> Result<(), Error> {
-        self.txn.commit().await.map_err(Error::transaction)
+        let span = trace::Span::transaction(DB_SYSTEM, "commit");
+        span.instrument(async move { self.txn.commit().await.map_err(Error::transaction) })
+            .await
     }
 
     /// Rolls the transaction back, discarding all changes made within it.
     ///
     /// This is equivalent to `Transaction`'s `Drop` implementation, but provides any error encountered to the caller.
     pub async fn rollback(self) -> Result<(), Error> {
-        self.txn.rollback().await.map_err(Error::transaction)
+        let span = trace::Span::transaction(DB_SYSTEM, "rollback");
+        span.instrument(async move { self.txn.rollback().await.map_err(Error::transaction) })
+            .await
+    }
+
+    /// Inserts many rows, with as few statements as possible.
+    ///
+    /// The rows are inserted in chunks that fit the database's parameter
+    /// limit.  Returns the total number of rows affected.
+    pub async fn insert_many<S>(&mut self, rows: &[S]) -> Result<u64, Error>
+    where
+        S: InsertValues + ToParams<Client>,
+    {
+        let mut rows_affected = 0;
+        for chunk in bulk::chunks::<Client, S>(rows) {
+            rows_affected += self.execute(&chunk).await?;
+        }
+        Ok(rows_affected)
+    }
+
+    /// Copies rows into a table, returning the number of rows copied.
+    ///
+    /// The table may be followed by a list of columns.  The rows are
+    /// sent in the binary format, with the column types looked up by
+    /// preparing a query of the same columns.  See the
+    /// [`copy`](crate::copy) module for details.
+    pub async fn copy_in<T: ToParams<Client>>(
+        &mut self,
+        table: &str,
+        rows: &[T],
+    ) -> Result<u64, Error> {
+        let statement = self
+            .prepare_internal(None, copy::select_text(table))
+            .await?;
+        let types = copy::column_types(&statement);
+
+        let sink = self
+            .txn
+            .copy_in(&copy::copy_in_text(table))
+            .await
+            .map_err(Error::query)?;
+
+        let writer = tokio_postgres::binary_copy::BinaryCopyInWriter::new(sink, &types);
+        copy_in_rows(writer, &types, rows).await
+    }
+
+    /// Copies out the rows of a query.
+    ///
+    /// The rows are read in the binary format, so they must load their
+    /// columns by index.  See the [`copy`](crate::copy) module for details.
+    pub async fn copy_out<Q>(&mut self, query: &Q) -> Result<Vec<Q::Row>, Error>
+    where
+        Q: StaticQueryText + Query<copy::Binary>,
+    {
+        let statement = self.prepare_internal(None, Q::QUERY_TEXT).await?;
+        let types = copy::column_types(&statement);
+
+        let stream = self
+            .txn
+            .copy_out(&copy::copy_out_text(Q::QUERY_TEXT))
+            .await
+            .map_err(Error::query)?;
+
+        copy_out_rows(stream, &types, query).await
+    }
+
+    /// Listens for notifications of type `N`, on its channel.
+    ///
+    /// See the [`notify`](crate::notify) module for details.
+    pub async fn listen<N: Notification>(&mut self) -> Result<(), Error> {
+        self.txn
+            .batch_execute(&notify::listen_text::<N>())
+            .await
+            .map_err(Error::query)
+    }
+
+    /// Stops listening for notifications of type `N`.
+    pub async fn unlisten<N: Notification>(&mut self) -> Result<(), Error> {
+        self.txn
+            .batch_execute(&notify::unlisten_text::<N>())
+            .await
+            .map_err(Error::query)
+    }
+
+    /// Sends a notification to any listeners on its channel.
+    pub async fn notify<N: Notification>(&mut self, notification: &N) -> Result<(), Error> {
+        let payload = notification.to_payload()?;
+        let statement = self.prepare_internal(None, notify::NOTIFY_TEXT).await?;
+
+        self.txn
+            .execute(&statement, &[&N::CHANNEL, &payload])
+            .await
+            .map_err(Error::query)?;
+
+        Ok(())
     }
 
     /// Creates a new prepared statement.
@@ -407,11 +1031,25 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn prepare<S: StaticQueryText>(
-        &mut self,
-    ) -> Result<(), Error> {
-        self.prepare_internal(S::QUERY_TEXT).await?;
-        Ok(())
+    pub async fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        let span = trace::Span::new(DB_SYSTEM, "prepare", type_name::<S>(), S::QUERY_TEXT);
+        span.instrument(async move {
+            self.prepare_internal(Some(type_name::<S>()), S::QUERY_TEXT)
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Inserts a row, returning its generated id.
+    ///
+    /// See [`Returning`](crate::query::Returning) for details.
+    pub async fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Client> + Returning,
+        S::Id: FromColumnIndexed<Client>,
+    {
+        self.query_one(&ReturningId::new(statement)).await
     }
 
     /// Executes a statement, returning the resulting rows.
@@ -445,21 +1083,27 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn query<Q: Query<Client>>(
+    pub async fn query<Q: QueryRows<Client, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Vec<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text()).await?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run_async(|rows| rows.len() as u64, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<Q>(), query_text, once)
+                .await?;
 
-        let rows = self
-            .txn
-            .query(&statement, &params)
-            .await
-            .map_err(Error::query)?;
+            let rows = self
+                .txn
+                .query(&statement, params)
+                .await
+                .map_err(Error::query)?;
 
-        FromRow::from_rows(&rows)
+            query.map_rows(&rows)
+        })
+        .await
     }
 
     /// Executes a statement which returns a single row, returning it.
@@ -492,21 +1136,27 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn query_one<Q: QueryOne<Client>>(
+    pub async fn query_one<Q: QueryRowsOne<Client, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Q::Row, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text()).await?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_one", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run_async(|_| 1, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<Q>(), query_text, once)
+                .await?;
 
-        let row = self
-            .txn
-            .query_one(&statement, params)
-            .await
-            .map_err(Error::query)?;
+            let row = self
+                .txn
+                .query_one(&statement, params)
+                .await
+                .map_err(Error::query)?;
 
-        FromRow::from_row(&row)
+            query.map_row(&row)
+        })
+        .await
     }
 
     /// Executes a statement which returns zero or one rows, returning it.
@@ -540,21 +1190,27 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn query_opt<Q: QueryOne<Client>>(
+    pub async fn query_opt<Q: QueryRowsOne<Client, M>, M>(
         &mut self,
         query: &Q,
     ) -> Result<Option<Q::Row>, Error> {
-        let params = query.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(query.query_text()).await?;
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "query_opt", query)?;
+        let once = self.hooks.annotate_once(type_name::<Q>(), &query_text);
+        span.run_async(|row| row.is_some() as u64, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<Q>(), query_text, once)
+                .await?;
 
-        let row = self
-            .txn
-            .query_opt(&statement, params)
-            .await
-            .map_err(Error::query)?;
+            let row = self
+                .txn
+                .query_opt(&statement, params)
+                .await
+                .map_err(Error::query)?;
 
-        row.map(|row| FromRow::from_row(&row)).transpose()
+            row.map(|row| query.map_row(&row)).transpose()
+        })
+        .await
     }
 
     /// Executes a statement, returning the number of rows modified.
@@ -581,33 +1237,146 @@ impl<'a> Transaction<'a> {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn execute<S: Statement<Client>>(
+    pub async fn execute<S: Statement<Client>>(&mut self, statement: &S) -> Result<u64, Error> {
+        let (params, query_text, span) = self.hooks.start(DB_SYSTEM, "execute", statement)?;
+        let once = self.hooks.annotate_once(type_name::<S>(), &query_text);
+        span.run_async(|rows_affected| *rows_affected, async move {
+            let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
+            let statement = self
+                .prepare_query(type_name::<S>(), query_text, once)
+                .await?;
+
+            let rows_affected = self
+                .txn
+                .execute(&statement, params)
+                .await
+                .map_err(Error::query)?;
+
+            Ok(rows_affected)
+        })
+        .await
+    }
+}
+
+async fn copy_in_rows<T: ToParams<Client>>(
+    writer: tokio_postgres::binary_copy::BinaryCopyInWriter,
+    types: &[tokio_postgres::types::Type],
+    rows: &[T],
+) -> Result<u64, Error> {
+    let mut writer = std::pin::pin!(writer);
+    for row in rows {
+        let params = row.to_params().unwrap_or_default();
+        copy::check_row(types, &params)?;
+        writer.as_mut().write(&params).await.map_err(Error::query)?;
+    }
+    writer.finish().await.map_err(Error::query)
+}
+
+async fn copy_out_rows<Q: Query<copy::Binary>>(
+    stream: tokio_postgres::CopyOutStream,
+    types: &[tokio_postgres::types::Type],
+    query: &Q,
+) -> Result<Vec<Q::Row>, Error> {
+    use futures_util::StreamExt;
+
+    let rows = tokio_postgres::binary_copy::BinaryCopyOutStream::new(stream, types);
+    let mut rows = std::pin::pin!(rows);
+    let mut result = vec![];
+    while let Some(row) = rows.next().await {
+        result.push(query.map_row(&row.map_err(Error::query)?)?);
+    }
+    Ok(result)
+}
+
+/// A client with read replicas.
+///
+/// See the [`replica`](crate::replica) module for details.
+pub type Replicated = replica::Replicated<Client>;
+
+impl replica::Replicated<Client> {
+    /// Prepares a statement on the primary and every replica.
+    pub async fn prepare<S: StaticQueryText>(&mut self) -> Result<(), Error> {
+        for client in self.clients() {
+            client.prepare::<S>().await?;
+        }
+        Ok(())
+    }
+
+    /// Executes a query on a replica, returning the resulting rows.
+    ///
+    /// Queries marked `primary` run on the primary.
+    pub async fn query<Q: QueryRows<Client, M>, M>(
         &mut self,
-        statement: &S,
-    ) -> Result<u64, Error> {
-        let params = statement.to_params();
-        let params = params.as_ref().map(AsRef::as_ref).unwrap_or(&[][..]);
-        let statement = self.prepare_internal(statement.query_text()).await?;
+        query: &Q,
+    ) -> Result<Vec<Q::Row>, Error> {
+        let route = self.route(Q::PRIMARY);
+        let start = Instant::now();
+        let result = self.client(route).query(query).await;
+        self.record(route, start.elapsed());
+        result
+    }
 
-        let rows_affected = self
-            .txn
-            .execute(&statement, &params)
-            .await
-            .map_err(Error::query)?;
+    /// Executes a query on a replica, returning a single row.
+    ///
+    /// Queries marked `primary` run on the primary.
+    pub async fn query_one<Q: QueryRowsOne<Client, M>, M>(
+        &mut self,
+        query: &Q,
+    ) -> Result<Q::Row, Error> {
+        let route = self.route(Q::PRIMARY);
+        let start = Instant::now();
+        let result = self.client(route).query_one(query).await;
+        self.record(route, start.elapsed());
+        result
+    }
 
-        Ok(rows_affected)
+    /// Executes a query on a replica, returning zero or one rows.
+    ///
+    /// Queries marked `primary` run on the primary.
+    pub async fn query_opt<Q: QueryRowsOne<Client, M>, M>(
+        &mut self,
+        query: &Q,
+    ) -> Result<Option<Q::Row>, Error> {
+        let route = self.route(Q::PRIMARY);
+        let start = Instant::now();
+        let result = self.client(route).query_opt(query).await;
+        self.record(route, start.elapsed());
+        result
+    }
+
+    /// Executes a statement on the primary, returning the number of rows modified.
+    pub async fn execute<S: Statement<Client>>(&mut self, statement: &S) -> Result<u64, Error> {
+        self.primary().execute(statement).await
+    }
+
+    /// Inserts a row on the primary, returning its generated id.
+    ///
+    /// See [`Client::insert`] for details.
+    pub async fn insert<S>(&mut self, statement: &S) -> Result<S::Id, Error>
+    where
+        S: Statement<Client> + Returning,
+        S::Id: FromColumnIndexed<Client>,
+    {
+        self.primary().insert(statement).await
+    }
+
+    /// Begins a new database transaction on the primary.
+    pub async fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        self.primary().transaction().await
     }
 }
 
 // TODO: not derive support
-#[cfg(all(test, feature ="derive"))]
+#[cfg(all(test, feature = "derive"))]
 mod test {
     use super::*;
 
     use tokio_postgres::NoTls;
 
     #[derive(Statement)]
-    #[aykroyd(text = "CREATE TABLE test_tokio_postgres (id SERIAL PRIMARY KEY, label TEXT NOT NULL)")]
+    #[aykroyd(
+        text = "CREATE TABLE test_tokio_postgres (id SERIAL PRIMARY KEY, label TEXT NOT NULL)"
+    )]
     struct CreateTodos;
 
     #[derive(Statement)]
@@ -624,12 +1393,16 @@ mod test {
 
     #[tokio::test]
     async fn end_to_end() {
+        use crate::combinator::{Count, Exists};
+
         const TODO_TEXT: &str = "get things done, please!";
 
         let (mut client, connection) = connect(
             "host=localhost user=aykroyd_test password=aykroyd_test",
             NoTls,
-        ).await.unwrap();
+        )
+        .await
+        .unwrap();
 
         tokio::spawn(async move {
             if let Err(e) = connection.await {
@@ -638,13 +1411,101 @@ mod test {
         });
 
         client.execute(&CreateTodos).await.unwrap();
+        assert!(!client.query_one(&Exists::new(GetAllTodos)).await.unwrap());
 
         client.execute(&InsertTodo(TODO_TEXT)).await.unwrap();
 
         let todos = client.query(&GetAllTodos).await.unwrap();
         assert_eq!(1, todos.len());
         assert_eq!(TODO_TEXT, todos[0].1);
+        assert_eq!(1, client.query_one(&Count::new(GetAllTodos)).await.unwrap());
+        assert!(client.query_one(&Exists::new(GetAllTodos)).await.unwrap());
 
         client.execute(&DropTodos).await.unwrap();
     }
+
+    #[tokio::test]
+    async fn verify() {
+        let (mut client, connection) = connect(
+            "host=localhost user=aykroyd_test password=aykroyd_test",
+            NoTls,
+        )
+        .await
+        .unwrap();
+
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("connection error: {e}");
+            }
+        });
+
+        client.execute(&CreateTodos).await.unwrap();
+
+        let mismatches = client.verify::<GetAllTodos>().await.unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+
+        let mismatches = client.verify::<InsertTodo>().await.unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+
+        client.execute(&DropTodos).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn copy() {
+        let (mut client, connection) = connect(
+            "host=localhost user=aykroyd_test password=aykroyd_test",
+            NoTls,
+        )
+        .await
+        .unwrap();
+
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("connection error: {e}");
+            }
+        });
+
+        client.execute(&CreateTodos).await.unwrap();
+
+        let todos = [InsertTodo("first"), InsertTodo("second")];
+        let rows_copied = client
+            .copy_in("test_tokio_postgres (label)", &todos)
+            .await
+            .unwrap();
+        assert_eq!(2, rows_copied);
+
+        let todos = client.copy_out(&GetAllTodos).await.unwrap();
+        assert_eq!(2, todos.len());
+        assert_eq!("second", todos[1].1);
+
+        client.execute(&DropTodos).await.unwrap();
+    }
+
+    #[derive(crate::Notification)]
+    #[aykroyd(channel = "test_tokio_postgres")]
+    struct TodoAdded(i32);
+
+    #[tokio::test]
+    async fn notify() {
+        use futures_util::StreamExt;
+
+        let (mut client, connection) = connect(
+            "host=localhost user=aykroyd_test password=aykroyd_test",
+            NoTls,
+        )
+        .await
+        .unwrap();
+
+        let (notifications, connection) = notifications(connection);
+        tokio::spawn(connection);
+
+        let added = notifications.subscribe::<TodoAdded>();
+        let mut added = std::pin::pin!(added);
+
+        client.listen::<TodoAdded>().await.unwrap();
+        client.notify(&TodoAdded(42)).await.unwrap();
+
+        let TodoAdded(id) = added.next().await.unwrap().unwrap();
+        assert_eq!(42, id);
+    }
 }
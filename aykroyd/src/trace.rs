@@ -0,0 +1,366 @@
+//! Spans for database operations, with the `tracing` feature.
+//!
+//! Each client operation runs in a span following the OpenTelemetry
+//! semantic conventions for database client calls: `db.system`,
+//! `db.operation` and `db.statement`, plus the Rust type of the query.
+//! Once the operation is done we record the number of rows returned or
+//! affected, and the kind of error if it failed.  The PostgreSQL clients
+//! also record whether the prepared statement was already cached.
+//!
+//! Without the feature, the spans are a no-op, but they also call the
+//! client's [`hooks`](crate::hook) and record its [`stats`](crate::stats)
+//! once each query is done.
+
+use std::any::type_name;
+use std::time::{Duration, Instant};
+
+use crate::client::Client;
+use crate::error::{Error, ErrorKind};
+use crate::hook::Hooks;
+use crate::query::{QueryText, ToParams};
+
+/// A span for one database operation.
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    operation: &'static str,
+    hooked: Option<Hooked>,
+}
+
+/// The hooks to call once a query is done.
+struct Hooked {
+    hooks: Hooks,
+    query_name: &'static str,
+    text: String,
+    param_count: usize,
+    params: Option<Vec<String>>,
+}
+
+impl Span {
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(
+        system: &'static str,
+        operation: &'static str,
+        query_type: &'static str,
+        statement: &str,
+    ) -> Self {
+        Span {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                target: "aykroyd",
+                "db",
+                otel.name = operation,
+                otel.kind = "client",
+                otel.status_code = tracing::field::Empty,
+                db.system = system,
+                db.operation = operation,
+                db.statement = statement,
+                db.response.returned_rows = tracing::field::Empty,
+                db.rows_affected = tracing::field::Empty,
+                aykroyd.query_type = query_type,
+                aykroyd.cache_hit = tracing::field::Empty,
+                error.type = tracing::field::Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            operation,
+            hooked: None,
+        }
+    }
+
+    /// A span for a query, which also calls the `after` hooks and
+    /// records the query's stats.
+    pub(crate) fn query(
+        system: &'static str,
+        operation: &'static str,
+        query_type: &'static str,
+        statement: &str,
+        param_count: usize,
+        params: impl FnOnce() -> Option<Vec<String>>,
+        hooks: &Hooks,
+    ) -> Self {
+        let mut span = Span::new(system, operation, query_type, statement);
+        if !hooks.is_empty() {
+            span.hooked = Some(Hooked {
+                hooks: hooks.clone(),
+                query_name: query_type,
+                text: statement.into(),
+                param_count,
+                params: hooks.wants_params().then(params).flatten(),
+            });
+        }
+        span
+    }
+
+    /// A span for transaction control, which has no query type.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn transaction(system: &'static str, operation: &'static str) -> Self {
+        Span {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                target: "aykroyd",
+                "db",
+                otel.name = operation,
+                otel.kind = "client",
+                otel.status_code = tracing::field::Empty,
+                db.system = system,
+                db.operation = operation,
+                error.type = tracing::field::Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            operation,
+            hooked: None,
+        }
+    }
+
+    /// Run a synchronous operation in the span.
+    #[cfg_attr(
+        not(any(
+            feature = "duckdb",
+            feature = "mysql",
+            feature = "postgres",
+            feature = "rusqlite"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn in_scope<T, E, F>(&self, f: F) -> Result<T, Error<E>>
+    where
+        F: FnOnce() -> Result<T, Error<E>>,
+    {
+        #[cfg(feature = "tracing")]
+        let _entered = self.span.enter();
+        let result = f();
+        self.record_result(&result);
+        result
+    }
+
+    /// Run an asynchronous operation in the span.
+    #[cfg_attr(
+        not(any(feature = "mysql-async", feature = "tokio-postgres")),
+        allow(dead_code)
+    )]
+    pub(crate) async fn instrument<T, E, F>(&self, future: F) -> Result<T, Error<E>>
+    where
+        F: std::future::Future<Output = Result<T, Error<E>>>,
+    {
+        #[cfg(feature = "tracing")]
+        let future = tracing::Instrument::instrument(future, self.span.clone());
+        let result = future.await;
+        self.record_result(&result);
+        result
+    }
+
+    /// Run a synchronous query in the span, counting its rows.
+    #[cfg_attr(
+        not(any(
+            feature = "duckdb",
+            feature = "mysql",
+            feature = "postgres",
+            feature = "rusqlite"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn run<T, E, F>(&self, rows: fn(&T) -> u64, f: F) -> Result<T, Error<E>>
+    where
+        F: FnOnce() -> Result<T, Error<E>>,
+    {
+        let start = Instant::now();
+        let result = self.in_scope(f);
+        self.finish(
+            result.as_ref().map(rows).map_err(Error::kind),
+            start.elapsed(),
+        );
+        result
+    }
+
+    /// Run an asynchronous query in the span, counting its rows.
+    #[cfg_attr(
+        not(any(feature = "mysql-async", feature = "tokio-postgres")),
+        allow(dead_code)
+    )]
+    pub(crate) async fn run_async<T, E, F>(
+        &self,
+        rows: fn(&T) -> u64,
+        future: F,
+    ) -> Result<T, Error<E>>
+    where
+        F: std::future::Future<Output = Result<T, Error<E>>>,
+    {
+        let start = Instant::now();
+        let result = self.instrument(future).await;
+        self.finish(
+            result.as_ref().map(rows).map_err(Error::kind),
+            start.elapsed(),
+        );
+        result
+    }
+
+    /// Record the rows returned or affected, and call the hooks.
+    fn finish(&self, result: Result<u64, ErrorKind>, elapsed: Duration) {
+        #[cfg(feature = "tracing")]
+        if let Ok(rows) = result {
+            match self.operation {
+                "execute" => self.span.record("db.rows_affected", rows),
+                _ => self.span.record("db.response.returned_rows", rows),
+            };
+        }
+
+        if let Some(hooked) = &self.hooked {
+            hooked.hooks.after(
+                hooked.query_name,
+                &hooked.text,
+                hooked.param_count,
+                hooked.params.as_deref(),
+                result,
+                elapsed,
+            );
+        }
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn record_result<T, E>(&self, result: &Result<T, Error<E>>) {
+        #[cfg(feature = "tracing")]
+        if let Err(err) = result {
+            self.span.record("otel.status_code", "ERROR");
+            self.span
+                .record("error.type", tracing::field::debug(err.kind()));
+        }
+    }
+}
+
+/// A query ready to run: its parameters, its text, and its span.
+pub(crate) type Started<'q, C> = (Option<Vec<<C as Client>::Param<'q>>>, String, Span);
+
+impl Hooks {
+    /// Get a query ready to run: convert its parameters, run the `before`
+    /// hooks on its text, and open its span, which calls the `after` hooks
+    /// and records the stats once the query is done.
+    ///
+    /// The text doesn't have the comments yet, clients append them when
+    /// they prepare the statement.
+    pub(crate) fn start<'q, C, Q, E>(
+        &self,
+        system: &'static str,
+        operation: &'static str,
+        query: &'q Q,
+    ) -> Result<Started<'q, C>, Error<E>>
+    where
+        C: Client,
+        Q: QueryText + ToParams<C>,
+    {
+        let params = query.to_params();
+        let param_count = params.as_ref().map_or(0, Vec::len);
+        let query_name = type_name::<Q>();
+        let text = self.before(query_name, query.query_text_for::<C>(), param_count)?;
+        let span = Span::query(
+            system,
+            operation,
+            query_name,
+            &text,
+            param_count,
+            || query.redacted_params(),
+            self,
+        );
+        Ok((params, text, span))
+    }
+}
+
+/// Record whether a statement was found in the cache, on the current span.
+#[cfg_attr(
+    not(any(feature = "postgres", feature = "tokio-postgres")),
+    allow(dead_code)
+)]
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn cache_hit(hit: bool) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("aykroyd.cache_hit", hit);
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod test {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    /// A subscriber that keeps every field recorded on a span.
+    #[derive(Clone, Default)]
+    struct Fields(
+        Arc<Mutex<Vec<(String, String)>>>,
+        Arc<Mutex<Option<&'static tracing::Metadata<'static>>>>,
+    );
+
+    impl tracing::field::Visit for Fields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            let value = format!("{value:?}");
+            self.0.lock().unwrap().push((field.name().into(), value));
+        }
+    }
+
+    impl tracing::Subscriber for Fields {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            span.record(&mut self.clone());
+            *self.1.lock().unwrap() = Some(span.metadata());
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            values.record(&mut self.clone());
+        }
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+
+        fn current_span(&self) -> tracing_core::span::Current {
+            match *self.1.lock().unwrap() {
+                Some(metadata) => {
+                    tracing_core::span::Current::new(tracing::span::Id::from_u64(1), metadata)
+                }
+                None => tracing_core::span::Current::none(),
+            }
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let fields = Fields::default();
+        tracing::subscriber::with_default(fields.clone(), || {
+            let span = Span::new("sqlite", "query", "GetTodos", "SELECT 1");
+            let result: Result<Vec<i32>, Error<()>> = span.run(
+                |rows| rows.len() as u64,
+                || {
+                    cache_hit(true);
+                    Ok(vec![1, 2, 3])
+                },
+            );
+            assert!(result.is_ok());
+
+            let span = Span::new("sqlite", "execute", "DeleteTodos", "DELETE FROM todos");
+            let result: Result<u64, Error<()>> =
+                span.run(|rows| *rows, || Err(Error::query_str("oops", None)));
+            assert!(result.is_err());
+        });
+
+        let fields = fields.0.lock().unwrap();
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(Some("\"sqlite\""), field("db.system"));
+        assert_eq!(Some("\"SELECT 1\""), field("db.statement"));
+        assert_eq!(Some("\"GetTodos\""), field("aykroyd.query_type"));
+        assert_eq!(Some("true"), field("aykroyd.cache_hit"));
+        assert_eq!(Some("Query"), field("error.type"));
+        assert_eq!(Some("3"), field("db.response.returned_rows"));
+    }
+}
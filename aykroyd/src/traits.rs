@@ -1,4 +1,4 @@
-use crate::client::Client;
+use crate::client::{Client, FromColumnIndexed};
 use crate::error::Error;
 use crate::query::{QueryText, ToParams};
 use crate::row::{ColumnsIndexed, FromColumnsIndexed};
@@ -25,7 +25,8 @@ pub struct Customer {
     last_name: String,
 }
 ```
-"##)]
+"##
+)]
 ///
 /// You can opt-in to loading fields by column order on a struct with
 /// named fields, by using the `by_index` attribute.
@@ -43,7 +44,8 @@ pub struct Customer {
     last_name: String,
 }
 ```
-"##)]
+"##
+)]
 ///
 /// For tuple structs, the fields are taken from the row in order.  The
 /// order of the query columns must match the tuple struct fields.
@@ -56,7 +58,8 @@ pub struct Customer {
 #[derive(FromRow)]
 pub struct QueryResults(i32, f32, String);
 ```
-"##)]
+"##
+)]
 ///
 /// If you just need the results of an ad-hoc query, consider using an
 /// anonymous tuple instead.
@@ -74,7 +77,8 @@ use rust_decimal::Decimal;
 ")]
 pub struct SalesByMonth;
 ```
-"##)]
+"##
+)]
 ///
 /// You can also load nested rows, as long as they use the same
 /// column loading strategy.  Use this to share models between queries,
@@ -109,7 +113,8 @@ struct Pet {
 ")]
 struct GetPets;
 ```
-"##)]
+"##
+)]
 ///
 /// See [`FromColumnsIndexed`] and [`FromColumnsNamed`](crate::row::FromColumnsNamed)
 /// for more details.
@@ -154,6 +159,30 @@ impl_tuple_from_row!(T0, T1, T2, T3, T4, T5);
 impl_tuple_from_row!(T0, T1, T2, T3, T4, T5, T6);
 impl_tuple_from_row!(T0, T1, T2, T3, T4, T5, T6, T7);
 
+macro_rules! impl_scalar_from_row {
+    (
+        $(
+            $ty:ty
+        ),+
+        $(,)?
+    ) => {
+        $(
+            /// A row of a single column, as from `SELECT count(*)`.
+            impl<C> FromRow<C> for $ty
+            where
+                C: Client,
+                $ty: FromColumnIndexed<C>,
+            {
+                fn from_row(row: &C::Row<'_>) -> Result<Self, Error<C::Error>> {
+                    FromColumnIndexed::from_column(row, 0)
+                }
+            }
+        )+
+    };
+}
+
+impl_scalar_from_row!(i64, bool);
+
 /// A database statement which returns no results.
 ///
 /// A `Statement` is something that has query text and can be
@@ -177,7 +206,51 @@ pub struct InsertCustomer<'a> {
     last_name: &'a str,
 }
 ```
-"##)]
+"##
+)]
+///
+/// A field marked `#[aykroyd(expand)]` is a list of values, bound to a
+/// single placeholder.  See the [`list`](crate::list) module for details.
+/// An insert statement with the `insert_values` attribute can insert
+/// many rows at once, see the [`bulk`](crate::bulk) module.
+/// With `returning(id: i64)`, the clients' `insert` method runs it and
+/// returns the generated id, see [`Returning`](crate::query::Returning).
+///
+/// Any of the query derive macros can also check the query text at
+/// compile time against a JSON snapshot of the database schema, with
+/// the `check` attribute.  By default the snapshot is read from
+/// `aykroyd-schema.json` in the crate root, or you can give a path
+/// relative to the crate root.
+///
+/// ```json
+/// {
+///   "dialect": "postgres",
+///   "tables": {
+///     "customers": {
+///       "first_name": { "type": "text", "nullable": false },
+///       "last_name": { "type": "text", "nullable": false }
+///     }
+///   }
+/// }
+/// ```
+///
+/// ```ignore
+/// #[derive(Statement)]
+/// #[aykroyd(check = "db/schema.json", text = "
+///     INSERT INTO customers (first_name, last_name) VALUES ($1, $2)
+/// ")]
+/// pub struct InsertCustomer<'a> {
+///     first_name: &'a str,
+///     last_name: &'a str,
+/// }
+/// ```
+///
+/// Unknown tables and columns, a parameter count that doesn't match the
+/// fields, and mismatched parameter and tuple row types are compile errors.
+/// The dialect is one of `postgres` (the default), `mysql` or `sqlite`;
+/// the latter two compare types by affinity rather than by name.  Query
+/// shapes the checker doesn't understand are skipped, so this is no
+/// substitute for [`verify`](crate::verify).
 pub trait Statement<C: Client>: QueryText + ToParams<C> + Sync {}
 
 /// A database query that returns zero or more result rows.
@@ -203,9 +276,16 @@ struct Todo {
 #[aykroyd(row(Todo), text = "SELECT id, label FROM todo")]
 struct GetAllTodos;
 ```
-"##)]
+"##
+)]
 pub trait Query<C: Client>: QueryText + ToParams<C> + Sync {
     type Row: FromRow<C>;
+
+    /// Whether this query must run on the primary database, even when
+    /// there are read replicas, so that it reads its own writes.
+    ///
+    /// The derive macro sets this with `#[aykroyd(primary)]`.
+    const PRIMARY: bool = false;
 }
 
 /// A marker trait for a query that returns at most one row.
@@ -232,5 +312,55 @@ struct Todo {
 ")]
 struct GetTodoById(i32);
 ```
-"##)]
+"##
+)]
 pub trait QueryOne<C: Client>: Query<C> {}
+
+/// A query the clients can run, with a way to convert its rows.
+///
+/// Every [`Query`] is one of these, converting its rows with [`FromRow`],
+/// and that is all most code needs to know.  Combinators such as
+/// [`MapRow`](crate::combinator::MapRow) implement this directly, to
+/// produce rows some other way.
+///
+/// The parameter `M` only tells those implementations apart, and is
+/// always inferred.
+pub trait QueryRows<C: Client, M>: QueryText + ToParams<C> + Sync {
+    type Row;
+
+    /// Whether this query must run on the primary database.
+    ///
+    /// See [`Query::PRIMARY`].
+    const PRIMARY: bool = false;
+
+    /// Convert a database row to this query's row type.
+    fn map_row(&self, row: &C::Row<'_>) -> Result<Self::Row, Error<C::Error>>;
+
+    fn map_rows(&self, rows: &[C::Row<'_>]) -> Result<Vec<Self::Row>, Error<C::Error>> {
+        rows.iter().map(|row| self.map_row(row)).collect()
+    }
+}
+
+/// A marker trait for a [`QueryRows`] that returns at most one row.
+///
+/// Every [`QueryOne`] is one of these.
+pub trait QueryRowsOne<C: Client, M>: QueryRows<C, M> {}
+
+/// The [`QueryRows`] parameter for a [`Query`].
+pub enum FromRows {}
+
+impl<C: Client, Q: Query<C>> QueryRows<C, FromRows> for Q {
+    type Row = <Q as Query<C>>::Row;
+
+    const PRIMARY: bool = <Q as Query<C>>::PRIMARY;
+
+    fn map_row(&self, row: &C::Row<'_>) -> Result<Self::Row, Error<C::Error>> {
+        FromRow::from_row(row)
+    }
+
+    fn map_rows(&self, rows: &[C::Row<'_>]) -> Result<Vec<Self::Row>, Error<C::Error>> {
+        FromRow::from_rows(rows)
+    }
+}
+
+impl<C: Client, Q: QueryOne<C>> QueryRowsOne<C, FromRows> for Q {}
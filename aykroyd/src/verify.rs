@@ -0,0 +1,680 @@
+//! Verification of queries against a live database.
+//!
+//! A `Query` or `Statement` implementation is an assertion by the
+//! developer that the query text, the parameter types and the row
+//! type all line up.  This module lets you check that assertion
+//! against a real database: the client prepares the statement and
+//! asks the database to describe it, then we compare that description
+//! with what the Rust types expect.
+//!
+//! The following are checked, as far as each backend is able to
+//! report them:
+//!
+//! * the number of parameters, and their types,
+//! * the number of result columns, for rows loaded by index,
+//! * the presence of result columns, for rows loaded by name,
+//! * the types of the result columns, and
+//! * whether a nullable column is loaded into a non-`Option` type.
+//!
+//! Every mismatch found is reported, not just the first one.  The
+//! derive macros generate the [`DescribeQuery`] and [`DescribeRow`]
+//! implementations needed, so you can verify any derived query.
+//! Each variant of a derived enum is a query of its own, so check
+//! those with [`verify_variants`] instead.
+#![cfg_attr(
+    all(feature = "derive", feature = "rusqlite"),
+    doc = r##"
+
+```
+# use aykroyd::{FromRow, Query};
+# use aykroyd::rusqlite::Client;
+#[derive(FromRow)]
+struct Todo {
+    id: i32,
+    label: String,
+}
+
+#[derive(Query)]
+#[aykroyd(row(Todo), text = "SELECT id, label FROM todos WHERE id > $1")]
+struct GetTodosAfter(i32);
+
+# fn main() -> Result<(), aykroyd::rusqlite::Error> {
+let mut client = Client::open_in_memory()?;
+# client.as_mut().execute_batch("CREATE TABLE todos (id INTEGER PRIMARY KEY, label TEXT NOT NULL)").unwrap();
+
+let mismatches = aykroyd::verify::<GetTodosAfter, _>(&mut client)?;
+assert!(mismatches.is_empty(), "{mismatches:?}");
+# Ok(())
+# }
+```
+"##
+)]
+
+use crate::client::Client;
+use crate::error::Error;
+use crate::query::StaticQueryText;
+
+/// A database client's type descriptions.
+pub trait ClientTypes: Client {
+    /// The database's description of a parameter or column type.
+    type Type: std::fmt::Debug;
+}
+
+/// A synchronous database client that can describe its statements.
+///
+/// Asynchronous clients provide an inherent `describe` method instead.
+pub trait Describe: ClientTypes {
+    /// Prepare the query text and describe its parameters and columns.
+    fn describe(&mut self, query_text: &str)
+        -> Result<Description<Self::Type>, Error<Self::Error>>;
+}
+
+/// A prepared statement, as described by the database.
+#[derive(Debug, Clone)]
+pub struct Description<T> {
+    /// The statement's parameters, with types if the database reports them.
+    pub params: Vec<Option<T>>,
+    /// The statement's result columns.
+    pub columns: Vec<Column<T>>,
+}
+
+/// A result column, as described by the database.
+#[derive(Debug, Clone)]
+pub struct Column<T> {
+    /// The name of the column.
+    pub name: String,
+    /// The type of the column, if the database reports it.
+    pub ty: Option<T>,
+    /// Whether the column can be null, if the database reports it.
+    pub nullable: Option<bool>,
+}
+
+/// A type that can be checked against a database parameter type.
+///
+/// Each database client provides an implementation for anything
+/// that implements [`ToParam`](crate::client::ToParam).
+pub trait ParamType<C: ClientTypes> {
+    /// Can a value of this type be bound to a parameter of the given type?
+    fn accepts(ty: &C::Type) -> bool;
+}
+
+/// A type that can be checked against a database column type.
+///
+/// Each database client provides an implementation for anything
+/// that implements [`FromColumnIndexed`](crate::client::FromColumnIndexed).
+pub trait ColumnType<C: ClientTypes> {
+    /// Can a value of this type be loaded from a column of the given type?
+    fn accepts(ty: &C::Type) -> bool;
+
+    /// Can a value of this type be loaded from a null column?
+    ///
+    /// Clients answer this by asking the driver to convert a null,
+    /// which succeeds for an `Option` and fails for most other types.
+    fn accepts_null() -> bool;
+}
+
+/// The expectations of a Rust type for a single parameter or column.
+pub struct Expected<C: ClientTypes> {
+    name: Option<String>,
+    type_name: &'static str,
+    nullable: bool,
+    accepts: fn(&C::Type) -> bool,
+}
+
+impl<C: ClientTypes> Expected<C> {
+    /// The expectations of a parameter of type `T`.
+    pub fn param<T: ParamType<C>>() -> Self {
+        Expected {
+            name: None,
+            type_name: std::any::type_name::<T>(),
+            nullable: false,
+            accepts: T::accepts,
+        }
+    }
+
+    /// The expectations of a column of type `T`.
+    pub fn column<T: ColumnType<C>>() -> Self {
+        Expected {
+            name: None,
+            type_name: std::any::type_name::<T>(),
+            nullable: T::accepts_null(),
+            accepts: T::accepts,
+        }
+    }
+
+    /// Match this column by name rather than by index.
+    pub fn named<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Allow null values for this column.
+    pub fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+
+    /// The name of the column, if it is matched by name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The name of the Rust type.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl<C: ClientTypes> std::fmt::Debug for Expected<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Expected")
+            .field("name", &self.name)
+            .field("type_name", &self.type_name)
+            .field("nullable", &self.nullable)
+            .finish()
+    }
+}
+
+/// A type that can describe the columns it loads by index.
+///
+/// This is generated by the derive macros alongside
+/// [`FromColumnsIndexed`](crate::row::FromColumnsIndexed).
+pub trait DescribeColumnsIndexed<C: ClientTypes> {
+    fn describe_columns() -> Vec<Expected<C>>;
+}
+
+impl<C: ClientTypes, T: DescribeColumnsIndexed<C>> DescribeColumnsIndexed<C> for Option<T> {
+    fn describe_columns() -> Vec<Expected<C>> {
+        T::describe_columns()
+            .into_iter()
+            .map(Expected::nullable)
+            .collect()
+    }
+}
+
+/// A type that can describe the columns it loads by name.
+///
+/// This is generated by the derive macros alongside
+/// [`FromColumnsNamed`](crate::row::FromColumnsNamed).
+pub trait DescribeColumnsNamed<C: ClientTypes> {
+    fn describe_columns(prefix: &str) -> Vec<Expected<C>>;
+}
+
+/// A type that can describe the columns of a result row.
+///
+/// This is generated by the [`FromRow`](crate::FromRow) derive macro.
+pub trait DescribeRow<C: ClientTypes> {
+    fn describe_row() -> Vec<Expected<C>>;
+}
+
+/// A query or statement that can describe its parameters and columns.
+///
+/// This is generated by the derive macros for `Query`, `QueryOne`,
+/// and `Statement`.
+pub trait DescribeQuery<C: ClientTypes> {
+    fn describe_params() -> Vec<Expected<C>>;
+    fn describe_columns() -> Vec<Expected<C>>;
+}
+
+/// A query enum that can describe the parameters and columns of each
+/// variant.
+///
+/// Each variant of an enum query is a query of its own, so this is
+/// generated by the derive macros for enums in place of
+/// [`DescribeQuery`].  Check it with [`verify_variants`].
+pub trait DescribeVariants<C: ClientTypes> {
+    fn describe_variants() -> Vec<ExpectedVariant<C>>;
+}
+
+/// The expectations of one variant of a query enum.
+pub struct ExpectedVariant<C: ClientTypes> {
+    name: &'static str,
+    text: String,
+    params: Vec<Expected<C>>,
+    columns: Vec<Expected<C>>,
+}
+
+impl<C: ClientTypes> ExpectedVariant<C> {
+    #[doc(hidden)]
+    pub fn new(
+        name: &'static str,
+        text: &'static str,
+        lists: &[usize],
+        params: Vec<Expected<C>>,
+        columns: Vec<Expected<C>>,
+    ) -> Self {
+        ExpectedVariant {
+            name,
+            text: crate::list::expand_single(text, lists),
+            params,
+            columns,
+        }
+    }
+
+    /// The name of the variant.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The variant's query text, with any list parameters expanded to a
+    /// single item.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Compare a statement description with the expectations of the variant.
+    pub fn check(&self, description: &Description<C::Type>) -> Vec<Mismatch> {
+        compare(&self.params, &self.columns, description)
+    }
+}
+
+impl<C: ClientTypes> std::fmt::Debug for ExpectedVariant<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ExpectedVariant")
+            .field("name", &self.name)
+            .field("text", &self.text)
+            .field("params", &self.params)
+            .field("columns", &self.columns)
+            .finish()
+    }
+}
+
+macro_rules! impl_tuple_describe {
+    (
+        $(
+            $name:ident
+        ),+
+        $(,)?
+    ) => {
+        impl<
+            C,
+            $(
+                $name,
+            )+
+        > DescribeColumnsIndexed<C> for ($($name,)+)
+        where
+            C: ClientTypes,
+            $(
+                $name: ColumnType<C>,
+            )+
+        {
+            fn describe_columns() -> Vec<Expected<C>> {
+                vec![$(
+                    Expected::column::<$name>(),
+                )+]
+            }
+        }
+
+        impl<
+            C,
+            $(
+                $name,
+            )+
+        > DescribeRow<C> for ($($name,)+)
+        where
+            C: ClientTypes,
+            $(
+                $name: ColumnType<C>,
+            )+
+        {
+            fn describe_row() -> Vec<Expected<C>> {
+                <Self as DescribeColumnsIndexed<C>>::describe_columns()
+            }
+        }
+    };
+}
+
+impl_tuple_describe!(T0);
+impl_tuple_describe!(T0, T1);
+impl_tuple_describe!(T0, T1, T2);
+impl_tuple_describe!(T0, T1, T2, T3);
+impl_tuple_describe!(T0, T1, T2, T3, T4);
+impl_tuple_describe!(T0, T1, T2, T3, T4, T5);
+impl_tuple_describe!(T0, T1, T2, T3, T4, T5, T6);
+impl_tuple_describe!(T0, T1, T2, T3, T4, T5, T6, T7);
+
+/// A difference between the Rust types and the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The number of parameters differs.
+    ParamCount { expected: usize, actual: usize },
+
+    /// A parameter's Rust type can't be bound to the database type.
+    ParamType {
+        index: usize,
+        expected: &'static str,
+        actual: String,
+    },
+
+    /// The number of result columns differs.
+    ColumnCount { expected: usize, actual: usize },
+
+    /// A named column isn't in the result.
+    ColumnMissing { name: String },
+
+    /// A column's Rust type can't be loaded from the database type.
+    ColumnType {
+        column: String,
+        expected: &'static str,
+        actual: String,
+    },
+
+    /// A nullable column would be loaded into a non-`Option` type.
+    ColumnNullable {
+        column: String,
+        expected: &'static str,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Mismatch::ParamCount { expected, actual } => {
+                write!(f, "expected {expected} parameters, database has {actual}")
+            }
+            Mismatch::ParamType {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "parameter {} has Rust type {expected}, database has {actual}",
+                index + 1
+            ),
+            Mismatch::ColumnCount { expected, actual } => {
+                write!(f, "expected {expected} columns, database has {actual}")
+            }
+            Mismatch::ColumnMissing { name } => write!(f, "column {name} not found"),
+            Mismatch::ColumnType {
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "column {column} has Rust type {expected}, database has {actual}"
+            ),
+            Mismatch::ColumnNullable { column, expected } => write!(
+                f,
+                "column {column} is nullable, but Rust type {expected} is not an Option"
+            ),
+        }
+    }
+}
+
+/// Compare a statement description with the expectations of `Q`.
+///
+/// Returns every mismatch found, or an empty `Vec` if none are.
+pub fn check<C, Q>(description: &Description<C::Type>) -> Vec<Mismatch>
+where
+    C: ClientTypes,
+    Q: DescribeQuery<C> + ?Sized,
+{
+    compare(&Q::describe_params(), &Q::describe_columns(), description)
+}
+
+fn compare<C: ClientTypes>(
+    params: &[Expected<C>],
+    columns: &[Expected<C>],
+    description: &Description<C::Type>,
+) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+
+    if params.len() != description.params.len() {
+        mismatches.push(Mismatch::ParamCount {
+            expected: params.len(),
+            actual: description.params.len(),
+        });
+    }
+    for (index, (expected, actual)) in params.iter().zip(&description.params).enumerate() {
+        if let Some(actual) = actual {
+            if !(expected.accepts)(actual) {
+                mismatches.push(Mismatch::ParamType {
+                    index,
+                    expected: expected.type_name,
+                    actual: format!("{actual:?}"),
+                });
+            }
+        }
+    }
+
+    if columns.iter().any(|column| column.name.is_none())
+        && columns.len() != description.columns.len()
+    {
+        mismatches.push(Mismatch::ColumnCount {
+            expected: columns.len(),
+            actual: description.columns.len(),
+        });
+    }
+    for (index, expected) in columns.iter().enumerate() {
+        let actual = match &expected.name {
+            Some(name) => description.columns.iter().find(|c| &c.name == name),
+            None => description.columns.get(index),
+        };
+        let actual = match (actual, &expected.name) {
+            (Some(actual), _) => actual,
+            (None, Some(name)) => {
+                mismatches.push(Mismatch::ColumnMissing { name: name.clone() });
+                continue;
+            }
+            (None, None) => continue,
+        };
+
+        if let Some(ty) = &actual.ty {
+            if !(expected.accepts)(ty) {
+                mismatches.push(Mismatch::ColumnType {
+                    column: actual.name.clone(),
+                    expected: expected.type_name,
+                    actual: format!("{ty:?}"),
+                });
+            }
+        }
+
+        if actual.nullable == Some(true) && !expected.nullable {
+            mismatches.push(Mismatch::ColumnNullable {
+                column: actual.name.clone(),
+                expected: expected.type_name,
+            });
+        }
+    }
+
+    mismatches
+}
+
+/// Verify a query or statement against a live database.
+///
+/// The client prepares the query text, then the description is
+/// compared with the Rust types.  Returns every mismatch found,
+/// or an error if the statement could not be prepared.
+pub fn verify<Q, C>(client: &mut C) -> Result<Vec<Mismatch>, Error<C::Error>>
+where
+    C: Describe,
+    Q: StaticQueryText + DescribeQuery<C> + ?Sized,
+{
+    let description = client.describe(Q::QUERY_TEXT)?;
+    Ok(check::<C, Q>(&description))
+}
+
+/// The mismatches found for each variant of a query enum, by name.
+pub type VariantMismatches = Vec<(&'static str, Vec<Mismatch>)>;
+
+/// Verify each variant of a query enum against a live database.
+///
+/// Returns the name of each variant with the mismatches found for it,
+/// or an error if any variant's statement could not be prepared.
+pub fn verify_variants<Q, C>(client: &mut C) -> Result<VariantMismatches, Error<C::Error>>
+where
+    C: Describe,
+    Q: DescribeVariants<C> + ?Sized,
+{
+    Q::describe_variants()
+        .into_iter()
+        .map(|variant| {
+            let description = client.describe(&variant.text)?;
+            Ok((variant.name, variant.check(&description)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::sync_client::TestClient;
+
+    struct Text;
+    impl ParamType<TestClient> for Text {
+        fn accepts(ty: &String) -> bool {
+            ty == "text"
+        }
+    }
+    impl ColumnType<TestClient> for Text {
+        fn accepts(ty: &String) -> bool {
+            ty == "text"
+        }
+
+        fn accepts_null() -> bool {
+            false
+        }
+    }
+
+    struct GetUsers;
+    impl StaticQueryText for GetUsers {
+        const QUERY_TEXT: &'static str = "SELECT name, nickname FROM users WHERE name = $1";
+    }
+    impl DescribeQuery<TestClient> for GetUsers {
+        fn describe_params() -> Vec<Expected<TestClient>> {
+            vec![Expected::param::<Text>()]
+        }
+
+        fn describe_columns() -> Vec<Expected<TestClient>> {
+            vec![
+                Expected::column::<Text>().named("name"),
+                Expected::column::<Option<Text>>().named("nickname"),
+            ]
+        }
+    }
+    impl ColumnType<TestClient> for Option<Text> {
+        fn accepts(ty: &String) -> bool {
+            <Text as ColumnType<TestClient>>::accepts(ty)
+        }
+
+        fn accepts_null() -> bool {
+            true
+        }
+    }
+
+    struct Indexed;
+    impl DescribeQuery<TestClient> for Indexed {
+        fn describe_params() -> Vec<Expected<TestClient>> {
+            vec![]
+        }
+
+        fn describe_columns() -> Vec<Expected<TestClient>> {
+            vec![Expected::column::<Text>(), Expected::column::<Text>()]
+        }
+    }
+
+    fn column(name: &str, ty: &str, nullable: Option<bool>) -> Column<String> {
+        Column {
+            name: name.into(),
+            ty: Some(ty.into()),
+            nullable,
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let mut client = TestClient::new();
+        client.push_describe_result(Ok(Description {
+            params: vec![Some("text".into())],
+            columns: vec![
+                column("nickname", "text", Some(true)),
+                column("name", "text", Some(false)),
+            ],
+        }));
+
+        let mismatches = verify::<GetUsers, _>(&mut client).unwrap();
+        assert_eq!(Vec::<Mismatch>::new(), mismatches);
+
+        let records = client.records();
+        assert_eq!(1, records.len());
+        assert_eq!(GetUsers::QUERY_TEXT, records[0].text);
+    }
+
+    #[test]
+    fn params() {
+        let description = Description {
+            params: vec![Some("int4".into()), None],
+            columns: vec![
+                column("name", "text", None),
+                column("nickname", "text", None),
+            ],
+        };
+
+        let mismatches = check::<TestClient, GetUsers>(&description);
+        assert_eq!(2, mismatches.len());
+        assert_eq!(
+            Mismatch::ParamCount {
+                expected: 1,
+                actual: 2
+            },
+            mismatches[0]
+        );
+        assert!(matches!(
+            &mismatches[1],
+            Mismatch::ParamType { index: 0, actual, .. } if actual == "\"int4\"",
+        ));
+    }
+
+    #[test]
+    fn named_columns() {
+        let description = Description {
+            params: vec![Some("text".into())],
+            columns: vec![
+                column("name", "int4", Some(true)),
+                column("nick", "text", Some(true)),
+            ],
+        };
+
+        let mismatches = check::<TestClient, GetUsers>(&description);
+        assert_eq!(3, mismatches.len());
+        assert!(matches!(
+            &mismatches[0],
+            Mismatch::ColumnType { column, .. } if column == "name",
+        ));
+        assert!(matches!(
+            &mismatches[1],
+            Mismatch::ColumnNullable { column, .. } if column == "name",
+        ));
+        assert_eq!(
+            Mismatch::ColumnMissing {
+                name: "nickname".into()
+            },
+            mismatches[2]
+        );
+    }
+
+    #[test]
+    fn indexed_columns() {
+        let description = Description {
+            params: vec![],
+            columns: vec![column("a", "text", None)],
+        };
+
+        let mismatches = check::<TestClient, Indexed>(&description);
+        assert_eq!(
+            vec![Mismatch::ColumnCount {
+                expected: 2,
+                actual: 1
+            }],
+            mismatches
+        );
+    }
+
+    #[test]
+    fn nullable_nested() {
+        let columns =
+            <Option<(Text, Text)> as DescribeColumnsIndexed<TestClient>>::describe_columns();
+        assert_eq!(2, columns.len());
+        assert!(columns.iter().all(|column| column.nullable));
+    }
+}